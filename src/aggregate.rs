@@ -0,0 +1,130 @@
+//! Client-side aggregation of resource collection rows, for the `count`, `min` and `max`
+//! aggregate functions and star and column counts.
+
+use indexmap::IndexMap;
+use ndc_sdk::models;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+use crate::predicate::{order_values, row_value};
+
+/// Compute the requested aggregates over the rows
+pub fn aggregate_rows(
+    aggregates: &IndexMap<models::FieldName, models::Aggregate>,
+    rows: &[IndexMap<models::FieldName, models::RowFieldValue>],
+) -> Result<IndexMap<models::FieldName, Value>, String> {
+    aggregates
+        .iter()
+        .map(|(name, aggregate)| Ok((name.clone(), aggregate_value(aggregate, rows)?)))
+        .collect()
+}
+
+fn aggregate_value(
+    aggregate: &models::Aggregate,
+    rows: &[IndexMap<models::FieldName, models::RowFieldValue>],
+) -> Result<Value, String> {
+    match aggregate {
+        models::Aggregate::StarCount {} => Ok(Value::from(rows.len())),
+        models::Aggregate::ColumnCount {
+            column,
+            field_path,
+            distinct,
+            ..
+        } => {
+            let mut values = column_values(rows, column, field_path.as_deref())?;
+            if *distinct {
+                values.sort_by_key(|value| value.to_string());
+                values.dedup();
+            }
+            Ok(Value::from(values.len()))
+        }
+        models::Aggregate::SingleColumn {
+            column,
+            field_path,
+            function,
+            ..
+        } => {
+            let values = column_values(rows, column, field_path.as_deref())?;
+            match function.to_string().as_str() {
+                "count" => Ok(Value::from(values.len())),
+                "min" => Ok(extreme_value(values, Ordering::Less)),
+                "max" => Ok(extreme_value(values, Ordering::Greater)),
+                function => Err(format!("Unsupported aggregate function: {}", function)),
+            }
+        }
+    }
+}
+
+/// The non-null values of a column
+fn column_values<'a>(
+    rows: &'a [IndexMap<models::FieldName, models::RowFieldValue>],
+    column: &models::FieldName,
+    field_path: Option<&[models::FieldName]>,
+) -> Result<Vec<&'a Value>, String> {
+    let mut values = Vec::new();
+    for row in rows {
+        let value = row_value(row, column, field_path)?;
+        if !value.is_null() {
+            values.push(value);
+        }
+    }
+    Ok(values)
+}
+
+/// The smallest or largest value, or null if there are none
+fn extreme_value(values: Vec<&Value>, ordering: Ordering) -> Value {
+    values
+        .into_iter()
+        .reduce(|extreme, value| {
+            if order_values(value, extreme) == Some(ordering) {
+                value
+            } else {
+                extreme
+            }
+        })
+        .cloned()
+        .unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_aggregate_rows() {
+        let rows = [
+            json!({"size": 120}),
+            json!({"size": 80}),
+            json!({"size": null}),
+        ]
+        .into_iter()
+        .map(|row| {
+            row.as_object()
+                .unwrap()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        models::FieldName::new(name.as_str().into()),
+                        models::RowFieldValue(value.clone()),
+                    )
+                })
+                .collect::<IndexMap<_, _>>()
+        })
+        .collect::<Vec<_>>();
+        let aggregates: IndexMap<models::FieldName, models::Aggregate> =
+            serde_json::from_value(json!({
+                "resources": {"type": "star_count"},
+                "sized": {"type": "column_count", "column": "size", "distinct": false},
+                "smallest": {"type": "single_column", "column": "size", "function": "min"},
+                "largest": {"type": "single_column", "column": "size", "function": "max"}
+            }))
+            .unwrap();
+
+        let result = aggregate_rows(&aggregates, &rows).unwrap();
+        assert_eq!(
+            serde_json::to_value(result).unwrap(),
+            json!({"resources": 3, "sized": 2, "smallest": 80, "largest": 120})
+        );
+    }
+}
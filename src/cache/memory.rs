@@ -0,0 +1,159 @@
+//! In-memory cache backend, local to a connector process
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{CacheBackend, CacheEntry};
+
+/// Number of entries kept by default
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+struct StoredEntry {
+    entry: CacheEntry,
+    expires_at: Instant,
+    refreshing: bool,
+}
+
+/// Cache backend keeping entries in process memory. Entries are removed once their `ttl`
+/// passes, and when the cache is full the entry closest to expiry is evicted.
+pub struct MemoryCacheBackend {
+    entries: Mutex<HashMap<String, StoredEntry>>,
+    max_entries: usize,
+}
+
+impl Default for MemoryCacheBackend {
+    fn default() -> Self {
+        Self::with_max_entries(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+impl MemoryCacheBackend {
+    /// Create a backend keeping at most `max_entries` entries
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::default(),
+            max_entries: max_entries.max(1),
+        }
+    }
+}
+
+/// Make room for a new entry, removing expired entries and then those closest to expiry
+fn evict(entries: &mut HashMap<String, StoredEntry>, max_entries: usize, now: Instant) {
+    if entries.len() < max_entries {
+        return;
+    }
+    entries.retain(|_, stored| stored.expires_at > now);
+    while entries.len() >= max_entries {
+        let Some(key) = entries
+            .iter()
+            .min_by_key(|(_, stored)| stored.expires_at)
+            .map(|(key, _)| key.clone())
+        else {
+            return;
+        };
+        entries.remove(&key);
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCacheBackend {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(stored) if stored.expires_at <= Instant::now() => {
+                entries.remove(key);
+                Ok(None)
+            }
+            stored => Ok(stored.map(|stored| stored.entry.clone())),
+        }
+    }
+
+    async fn set(&self, key: &str, entry: &CacheEntry, ttl: Duration) -> Result<()> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(key) {
+            evict(&mut entries, self.max_entries, now);
+        }
+        entries.insert(
+            key.to_string(),
+            StoredEntry {
+                entry: entry.clone(),
+                expires_at: now + ttl,
+                refreshing: false,
+            },
+        );
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn claim_refresh(&self, key: &str, _ttl: Duration) -> Result<bool> {
+        let mut entries = self.entries.lock().unwrap();
+        Ok(match entries.get_mut(key) {
+            Some(stored) if !stored.refreshing => {
+                stored.refreshing = true;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn release_refresh(&self, key: &str) -> Result<()> {
+        if let Some(stored) = self.entries.lock().unwrap().get_mut(key) {
+            stored.refreshing = false;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CacheControl;
+    use rmcp::model::{CallToolResult, Content};
+
+    #[tokio::test]
+    async fn test_memory_backend_eviction() {
+        let backend = MemoryCacheBackend::with_max_entries(2);
+        let entry = CacheEntry {
+            result: CallToolResult::success(vec![Content::text("sunny")]),
+            stored_at_ms: 0,
+            cache_control: CacheControl::default(),
+        };
+
+        // Expired entries are not returned
+        backend
+            .set("expired", &entry, Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(backend.get("expired").await.unwrap().is_none());
+
+        // When full, expired entries and then the entry closest to expiry are evicted
+        backend
+            .set("expired", &entry, Duration::ZERO)
+            .await
+            .unwrap();
+        backend
+            .set("short", &entry, Duration::from_secs(10))
+            .await
+            .unwrap();
+        backend
+            .set("long", &entry, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(backend.entries.lock().unwrap().len(), 2);
+        backend
+            .set("longer", &entry, Duration::from_secs(120))
+            .await
+            .unwrap();
+        assert!(backend.get("short").await.unwrap().is_none());
+        assert!(backend.get("long").await.unwrap().is_some());
+        assert!(backend.get("longer").await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,408 @@
+//! Response cache for read-only tool calls. Entries are only stored when the tool's
+//! cache-control directives, from the configuration or the `_meta.cacheControl` field of the
+//! tool result, allow it. Entries are kept in memory, or in a shared backend such as Redis so
+//! that connector replicas share cached results.
+
+mod memory;
+mod redis;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rmcp::model::{CallToolRequestParam, CallToolResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::{CacheBackendConfig, CacheControl, McpServerName};
+
+pub use self::memory::MemoryCacheBackend;
+pub use self::redis::RedisCacheBackend;
+
+/// A cached tool result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub result: CallToolResult,
+    /// Milliseconds since the Unix epoch, so entries can be shared between replicas
+    pub stored_at_ms: u64,
+    pub cache_control: CacheControl,
+}
+
+impl CacheEntry {
+    /// How long the entry is usable, fresh or stale
+    fn lifetime(&self) -> Duration {
+        Duration::from_secs(
+            self.cache_control.max_age_seconds.unwrap_or(0)
+                + self
+                    .cache_control
+                    .stale_while_revalidate_seconds
+                    .unwrap_or(0),
+        )
+    }
+}
+
+/// Storage for cached tool results
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    /// Get an entry
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>>;
+
+    /// Store an entry, which may be evicted after `ttl`
+    async fn set(&self, key: &str, entry: &CacheEntry, ttl: Duration) -> Result<()>;
+
+    /// Remove an entry
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Claim the refresh of a stale entry. Returns `true` for only one caller until the
+    /// entry is stored again, the claim is released or `ttl` passes.
+    async fn claim_refresh(&self, key: &str, ttl: Duration) -> Result<bool>;
+
+    /// Release the claimed refresh of an entry after the refresh failed, so it can be
+    /// claimed again
+    async fn release_refresh(&self, key: &str) -> Result<()>;
+}
+
+/// Create the cache backend described by the configuration
+pub async fn create_cache_backend(config: &CacheBackendConfig) -> Result<Arc<dyn CacheBackend>> {
+    match config {
+        CacheBackendConfig::Memory => Ok(Arc::new(MemoryCacheBackend::default())),
+        CacheBackendConfig::Redis { url, key_prefix } => {
+            let url = url
+                .resolve()?
+                .ok_or_else(|| anyhow::anyhow!("No Redis URL configured for the response cache"))?;
+            let client = RedisCacheBackend::client(&url)?;
+            // An unreachable Redis doesn't prevent the connector from starting
+            match RedisCacheBackend::connect(client, key_prefix.clone()).await {
+                Ok(backend) => Ok(Arc::new(backend)),
+                Err(err) => {
+                    tracing::warn!("{:#}, caching tool responses in memory instead", err);
+                    Ok(Arc::new(MemoryCacheBackend::default()))
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of a cache lookup
+pub enum CacheLookup {
+    /// The entry is within its `max-age`
+    Fresh(CallToolResult),
+    /// The entry is past its `max-age` but within `stale-while-revalidate`. It may be served
+    /// while the caller refreshes it in the background.
+    Stale(CallToolResult),
+    /// There is no usable entry
+    Miss,
+}
+
+/// Cache of tool results keyed by server, tool and arguments. Backend errors are logged and
+/// treated as cache misses, so they never fail a call.
+pub struct ResponseCache {
+    backend: Arc<dyn CacheBackend>,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(Arc::new(MemoryCacheBackend::default()))
+    }
+}
+
+impl ResponseCache {
+    /// Create a cache using the given backend
+    pub fn new(backend: Arc<dyn CacheBackend>) -> Self {
+        Self { backend }
+    }
+
+    /// Look up a cached result
+    pub async fn lookup(&self, key: &str) -> CacheLookup {
+        self.lookup_at(key, SystemTime::now()).await
+    }
+
+    async fn lookup_at(&self, key: &str, now: SystemTime) -> CacheLookup {
+        let entry = match self.backend.get(key).await {
+            Ok(Some(entry)) => entry,
+            Ok(None) => return CacheLookup::Miss,
+            Err(err) => {
+                tracing::warn!("Failed to read cached tool response: {}", err);
+                return CacheLookup::Miss;
+            }
+        };
+
+        let age = Duration::from_millis(unix_millis(now).saturating_sub(entry.stored_at_ms));
+        let max_age = Duration::from_secs(entry.cache_control.max_age_seconds.unwrap_or(0));
+
+        if age < max_age {
+            CacheLookup::Fresh(entry.result)
+        } else if age < entry.lifetime() {
+            // Only one caller is asked to refresh a stale entry
+            let remaining = entry.lifetime() - age;
+            match self.backend.claim_refresh(key, remaining).await {
+                Ok(true) => CacheLookup::Stale(entry.result),
+                Ok(false) => CacheLookup::Fresh(entry.result),
+                Err(err) => {
+                    tracing::warn!("Failed to claim cached tool response refresh: {}", err);
+                    CacheLookup::Fresh(entry.result)
+                }
+            }
+        } else {
+            if let Err(err) = self.backend.delete(key).await {
+                tracing::warn!("Failed to remove expired tool response: {}", err);
+            }
+            CacheLookup::Miss
+        }
+    }
+
+    /// Release the claimed refresh of an entry whose refresh failed
+    pub async fn release_refresh(&self, key: &str) {
+        if let Err(err) = self.backend.release_refresh(key).await {
+            tracing::warn!("Failed to release cached tool response refresh: {}", err);
+        }
+    }
+
+    /// Store a result if its cache-control directives allow it
+    pub async fn store(&self, key: String, result: &CallToolResult, cache_control: CacheControl) {
+        self.store_at(key, result, cache_control, SystemTime::now())
+            .await;
+    }
+
+    async fn store_at(
+        &self,
+        key: String,
+        result: &CallToolResult,
+        cache_control: CacheControl,
+        now: SystemTime,
+    ) {
+        let outcome = if is_cacheable(result, &cache_control) {
+            let entry = CacheEntry {
+                result: result.clone(),
+                stored_at_ms: unix_millis(now),
+                cache_control,
+            };
+            self.backend.set(&key, &entry, entry.lifetime()).await
+        } else {
+            self.backend.delete(&key).await
+        };
+        if let Err(err) = outcome {
+            tracing::warn!("Failed to store tool response in the cache: {}", err);
+        }
+    }
+}
+
+/// Milliseconds since the Unix epoch
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Check if a result may be stored under the given cache-control directives
+fn is_cacheable(result: &CallToolResult, cache_control: &CacheControl) -> bool {
+    !cache_control.no_store
+        && result.is_error != Some(true)
+        && (cache_control.max_age_seconds.unwrap_or(0) > 0
+            || cache_control.stale_while_revalidate_seconds.unwrap_or(0) > 0)
+}
+
+/// Build the cache key for a tool call. Arguments are canonicalized, so calls whose arguments
+/// differ only in the order of object keys share a key. The key is a SHA-256 hash, as
+/// arguments may hold secrets that must not be stored in a shared backend.
+pub fn cache_key(server_name: &McpServerName, call_request: &CallToolRequestParam) -> String {
+    let arguments = call_request
+        .arguments
+        .as_ref()
+        .map(|arguments| canonical_json(&Value::Object(arguments.clone())).to_string())
+        .unwrap_or_default();
+    let key = format!("{}\0{}\0{}", server_name.0, call_request.name, arguments);
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// A JSON value with the keys of its objects sorted
+fn canonical_json(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries = object.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonical_json(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(elements) => Value::Array(elements.iter().map(canonical_json).collect()),
+        value => value.clone(),
+    }
+}
+
+/// Read cache-control directives from the `_meta.cacheControl` field of a tool result
+pub fn cache_control_from_result(result: &CallToolResult) -> Option<CacheControl> {
+    result
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.get("cacheControl"))
+        .and_then(|cache_control| cache_control.as_str())
+        .map(parse_cache_control)
+}
+
+/// Parse an HTTP `Cache-Control` style value such as
+/// `max-age=60, stale-while-revalidate=30` or `no-store`
+pub fn parse_cache_control(value: &str) -> CacheControl {
+    let mut cache_control = CacheControl::default();
+    for directive in value.split(',') {
+        let (name, argument) = match directive.split_once('=') {
+            Some((name, argument)) => (name.trim(), Some(argument.trim())),
+            None => (directive.trim(), None),
+        };
+        let seconds = argument.and_then(|argument| argument.parse::<u64>().ok());
+        match name.to_ascii_lowercase().as_str() {
+            "no-store" | "no-cache" => cache_control.no_store = true,
+            "max-age" => cache_control.max_age_seconds = seconds,
+            "stale-while-revalidate" => cache_control.stale_while_revalidate_seconds = seconds,
+            _ => {}
+        }
+    }
+    cache_control
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    #[test]
+    fn test_parse_cache_control() {
+        let cache_control = parse_cache_control("max-age=60, stale-while-revalidate=30");
+        assert_eq!(cache_control.max_age_seconds, Some(60));
+        assert_eq!(cache_control.stale_while_revalidate_seconds, Some(30));
+        assert!(!cache_control.no_store);
+
+        assert!(parse_cache_control("No-Store").no_store);
+    }
+
+    #[test]
+    fn test_cache_key_canonical_arguments() {
+        let server_name = McpServerName("weather".to_string());
+        let call_request = |arguments: &str| CallToolRequestParam {
+            name: "forecast".into(),
+            arguments: serde_json::from_str(arguments).unwrap(),
+        };
+        assert_eq!(
+            cache_key(
+                &server_name,
+                &call_request(r#"{"city": "Oslo", "options": {"units": "metric", "days": 3}}"#)
+            ),
+            cache_key(
+                &server_name,
+                &call_request(r#"{"options": {"days": 3, "units": "metric"}, "city": "Oslo"}"#)
+            )
+        );
+        assert_ne!(
+            cache_key(&server_name, &call_request(r#"{"city": "Oslo"}"#)),
+            cache_key(&server_name, &call_request(r#"{"city": "Bergen"}"#))
+        );
+
+        // Argument values never appear in keys
+        let key = cache_key(&server_name, &call_request(r#"{"api_key": "secret"}"#));
+        assert_eq!(key.len(), 64);
+        assert!(!key.contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_redis_falls_back_to_memory() {
+        let config = serde_json::from_value(serde_json::json!({
+            "type": "redis",
+            "url": "redis://127.0.0.1:1"
+        }))
+        .unwrap();
+        let cache = ResponseCache::new(create_cache_backend(&config).await.unwrap());
+        let result = CallToolResult::success(vec![Content::text("sunny")]);
+        let cache_control = CacheControl {
+            max_age_seconds: Some(60),
+            ..Default::default()
+        };
+        cache
+            .store("forecast".to_string(), &result, cache_control)
+            .await;
+        assert!(matches!(
+            cache.lookup("forecast").await,
+            CacheLookup::Fresh(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_freshness() {
+        let cache = ResponseCache::default();
+        let result = CallToolResult::success(vec![Content::text("sunny")]);
+        let now = SystemTime::now();
+        let cache_control = CacheControl {
+            no_store: false,
+            max_age_seconds: Some(60),
+            stale_while_revalidate_seconds: Some(30),
+        };
+        cache
+            .store_at("key".to_string(), &result, cache_control, now)
+            .await;
+
+        assert!(matches!(
+            cache.lookup_at("key", now + Duration::from_secs(10)).await,
+            CacheLookup::Fresh(_)
+        ));
+        // The first stale lookup refreshes, later ones are served without refreshing
+        assert!(matches!(
+            cache.lookup_at("key", now + Duration::from_secs(70)).await,
+            CacheLookup::Stale(_)
+        ));
+        assert!(matches!(
+            cache.lookup_at("key", now + Duration::from_secs(75)).await,
+            CacheLookup::Fresh(_)
+        ));
+        assert!(matches!(
+            cache.lookup_at("key", now + Duration::from_secs(95)).await,
+            CacheLookup::Miss
+        ));
+        assert!(matches!(
+            cache.lookup_at("key", now).await,
+            CacheLookup::Miss
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_release_refresh() {
+        let cache = ResponseCache::default();
+        let result = CallToolResult::success(vec![Content::text("sunny")]);
+        let now = SystemTime::now();
+        let cache_control = CacheControl {
+            no_store: false,
+            max_age_seconds: Some(60),
+            stale_while_revalidate_seconds: Some(30),
+        };
+        cache
+            .store_at("key".to_string(), &result, cache_control, now)
+            .await;
+
+        let stale_at = now + Duration::from_secs(70);
+        assert!(matches!(
+            cache.lookup_at("key", stale_at).await,
+            CacheLookup::Stale(_)
+        ));
+        // After a failed refresh the next stale lookup refreshes again
+        cache.release_refresh("key").await;
+        assert!(matches!(
+            cache.lookup_at("key", stale_at).await,
+            CacheLookup::Stale(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_no_store() {
+        let cache = ResponseCache::default();
+        let result = CallToolResult::success(vec![Content::text("sunny")]);
+        let cache_control = CacheControl {
+            no_store: true,
+            max_age_seconds: Some(60),
+            stale_while_revalidate_seconds: None,
+        };
+        cache.store("key".to_string(), &result, cache_control).await;
+        assert!(matches!(cache.lookup("key").await, CacheLookup::Miss));
+    }
+}
@@ -0,0 +1,183 @@
+//! Redis cache backend, shared by all connector replicas using the same Redis instance
+
+use ::redis::aio::ConnectionManager;
+use ::redis::AsyncCommands;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::time::Duration;
+
+use super::{CacheBackend, CacheEntry};
+
+/// Prefix of the Redis keys when none is configured
+const DEFAULT_KEY_PREFIX: &str = "ndc-mcp:";
+
+/// Cache backend storing entries as JSON in Redis. Entries expire through Redis TTLs, and
+/// refreshes of stale entries are claimed with `SET NX` so only one replica refreshes an
+/// entry.
+pub struct RedisCacheBackend {
+    connection: ConnectionManager,
+    key_prefix: String,
+}
+
+impl RedisCacheBackend {
+    /// Create a Redis client, without connecting
+    pub fn client(url: &str) -> Result<::redis::Client> {
+        ::redis::Client::open(url).context("Invalid Redis URL")
+    }
+
+    /// Connect to Redis. Keys are prefixed with `key_prefix`, `ndc-mcp:` by default.
+    pub async fn connect(client: ::redis::Client, key_prefix: Option<String>) -> Result<Self> {
+        let connection = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis")?;
+        Ok(Self {
+            connection,
+            key_prefix: key_prefix.unwrap_or_else(|| DEFAULT_KEY_PREFIX.to_string()),
+        })
+    }
+
+    fn entry_key(&self, key: &str) -> String {
+        entry_key(&self.key_prefix, key)
+    }
+
+    fn refresh_key(&self, key: &str) -> String {
+        refresh_key(&self.key_prefix, key)
+    }
+}
+
+/// Redis key of an entry
+fn entry_key(key_prefix: &str, key: &str) -> String {
+    format!("{}{}", key_prefix, key)
+}
+
+/// Redis key of the refresh claim of an entry
+fn refresh_key(key_prefix: &str, key: &str) -> String {
+    format!("{}{}:refresh", key_prefix, key)
+}
+
+/// Redis expiry in milliseconds, at least 1 as Redis rejects 0
+fn ttl_millis(ttl: Duration) -> u64 {
+    (ttl.as_millis() as u64).max(1)
+}
+
+#[async_trait]
+impl CacheBackend for RedisCacheBackend {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        let mut connection = self.connection.clone();
+        let value: Option<String> = connection.get(self.entry_key(key)).await?;
+        value
+            .map(|value| serde_json::from_str(&value).context("Invalid cached tool response"))
+            .transpose()
+    }
+
+    async fn set(&self, key: &str, entry: &CacheEntry, ttl: Duration) -> Result<()> {
+        let mut connection = self.connection.clone();
+        let value = serde_json::to_string(entry)?;
+        ::redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(self.entry_key(key))
+            .arg(value)
+            .arg("PX")
+            .arg(ttl_millis(ttl))
+            .ignore()
+            .del(self.refresh_key(key))
+            .ignore()
+            .query_async::<_, ()>(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let mut connection = self.connection.clone();
+        connection
+            .del::<_, ()>(&[self.entry_key(key), self.refresh_key(key)])
+            .await?;
+        Ok(())
+    }
+
+    async fn claim_refresh(&self, key: &str, ttl: Duration) -> Result<bool> {
+        let mut connection = self.connection.clone();
+        let claimed: Option<String> = ::redis::cmd("SET")
+            .arg(self.refresh_key(key))
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_millis(ttl))
+            .query_async(&mut connection)
+            .await?;
+        Ok(claimed.is_some())
+    }
+
+    async fn release_refresh(&self, key: &str) -> Result<()> {
+        let mut connection = self.connection.clone();
+        connection.del::<_, ()>(self.refresh_key(key)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CacheControl;
+    use rmcp::model::{CallToolResult, Content};
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(entry_key("ndc-mcp:", "abc"), "ndc-mcp:abc");
+        assert_eq!(refresh_key("ndc-mcp:", "abc"), "ndc-mcp:abc:refresh");
+    }
+
+    #[test]
+    fn test_ttl_millis() {
+        assert_eq!(ttl_millis(Duration::from_secs(2)), 2000);
+        assert_eq!(ttl_millis(Duration::ZERO), 1);
+        assert_eq!(ttl_millis(Duration::from_micros(10)), 1);
+    }
+
+    /// Runs against the Redis instance at `NDC_MCP_TEST_REDIS_URL`, and is skipped when it is
+    /// not set
+    #[tokio::test]
+    async fn test_redis_backend() {
+        let Ok(url) = std::env::var("NDC_MCP_TEST_REDIS_URL") else {
+            return;
+        };
+        let client = RedisCacheBackend::client(&url).unwrap();
+        let backend = RedisCacheBackend::connect(client, Some("ndc-mcp-test:".to_string()))
+            .await
+            .unwrap();
+        let key = "test_redis_backend";
+        backend.delete(key).await.unwrap();
+        assert!(backend.get(key).await.unwrap().is_none());
+
+        let entry = CacheEntry {
+            result: CallToolResult::success(vec![Content::text("sunny")]),
+            stored_at_ms: 1,
+            cache_control: CacheControl {
+                max_age_seconds: Some(60),
+                ..Default::default()
+            },
+        };
+        backend
+            .set(key, &entry, Duration::from_secs(60))
+            .await
+            .unwrap();
+        let stored = backend.get(key).await.unwrap().unwrap();
+        assert_eq!(stored.stored_at_ms, 1);
+        assert_eq!(stored.cache_control, entry.cache_control);
+
+        // Only one caller claims a refresh until it is released
+        let ttl = Duration::from_secs(60);
+        assert!(backend.claim_refresh(key, ttl).await.unwrap());
+        assert!(!backend.claim_refresh(key, ttl).await.unwrap());
+        backend.release_refresh(key).await.unwrap();
+        assert!(backend.claim_refresh(key, ttl).await.unwrap());
+
+        // Storing the entry again releases the claim
+        backend.set(key, &entry, ttl).await.unwrap();
+        assert!(backend.claim_refresh(key, ttl).await.unwrap());
+
+        backend.delete(key).await.unwrap();
+        assert!(backend.get(key).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,49 @@
+//! Sampling of tool calls for detailed logging. Servers with a `log_sample_rate` have that
+//! fraction of their tool calls logged with arguments and results, spread evenly over the
+//! calls, so busy servers can be observed without logging every payload.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::McpServerName;
+
+/// Number of tool calls made to each server, to pick the calls to log
+#[derive(Default)]
+pub struct CallSampler {
+    calls: Mutex<HashMap<McpServerName, u64>>,
+}
+
+impl CallSampler {
+    /// Count a call to a server, returning whether it is logged in detail. A rate of 0.01
+    /// logs every 100th call, and rates are clamped to between 0 and 1.
+    pub fn sample(&self, server_name: &McpServerName, rate: f64) -> bool {
+        let rate = rate.clamp(0.0, 1.0);
+        if rate == 0.0 {
+            return false;
+        }
+        let mut calls = self.calls.lock().unwrap();
+        let count = calls.entry(server_name.clone()).or_insert(0);
+        let previous = *count as f64 * rate;
+        *count += 1;
+        (*count as f64 * rate).floor() > previous.floor()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_calls() {
+        let sampler = CallSampler::default();
+        let server_name = McpServerName("search".to_string());
+        let sampled = (0..1000)
+            .filter(|_| sampler.sample(&server_name, 0.01))
+            .count();
+        assert_eq!(sampled, 10);
+
+        let other = McpServerName("files".to_string());
+        assert!((0..10).all(|_| sampler.sample(&other, 1.0)));
+        assert!(!(0..10).any(|_| sampler.sample(&other, 0.0)));
+    }
+}
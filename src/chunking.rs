@@ -0,0 +1,186 @@
+//! Splitting of oversized text arguments of tools configured with `chunked_input` into
+//! sequential calls, for servers with strict per-message size caps. The results of the calls
+//! are concatenated.
+
+use rmcp::model::{CallToolRequestParam, CallToolResult};
+use serde_json::Value;
+use std::future::Future;
+use std::sync::Mutex;
+
+use crate::config::ChunkedInputConfig;
+
+/// The calls to make for a tool call, one per chunk of its chunked argument, or `None` if the
+/// argument fits in a single call
+pub fn chunked_calls(
+    chunked_input: &ChunkedInputConfig,
+    call_request: &CallToolRequestParam,
+) -> Option<Vec<CallToolRequestParam>> {
+    let text = call_request
+        .arguments
+        .as_ref()?
+        .get(&chunked_input.argument)?
+        .as_str()?;
+    if text.len() <= chunked_input.max_bytes {
+        return None;
+    }
+    let calls = split_text(text, chunked_input.max_bytes)
+        .into_iter()
+        .map(|chunk| {
+            let mut chunk_request = call_request.clone();
+            if let Some(arguments) = &mut chunk_request.arguments {
+                arguments.insert(chunked_input.argument.clone(), Value::String(chunk));
+            }
+            chunk_request
+        })
+        .collect();
+    Some(calls)
+}
+
+/// Split a text into chunks of at most `max_bytes` bytes, at the last whitespace of a chunk
+/// where there is one so words are not cut. Chunks never split a character.
+pub fn split_text(text: &str, max_bytes: usize) -> Vec<String> {
+    let max_bytes = max_bytes.max(4);
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > max_bytes {
+        let mut end = max_bytes;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if let Some(whitespace) = rest[..end].rfind(char::is_whitespace) {
+            if whitespace > 0 {
+                end = whitespace + rest[whitespace..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        chunks.push(rest[..end].to_string());
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+/// The calls of a chunked tool call that completed and their merged results, kept across
+/// retries of the call
+#[derive(Default)]
+pub struct ChunkProgress {
+    completed: usize,
+    merged: Option<CallToolResult>,
+}
+
+/// Make the calls of a chunked tool call one after the other, stopping at the first error
+/// result. Completed calls are recorded in `progress`, so making the calls again after one
+/// failed resumes from the failed call instead of sending every chunk again. Returns `None`
+/// if there are no calls.
+pub async fn call_chunks<E, F, Fut>(
+    calls: Vec<CallToolRequestParam>,
+    progress: &Mutex<ChunkProgress>,
+    mut call: F,
+) -> Result<Option<CallToolResult>, E>
+where
+    F: FnMut(CallToolRequestParam) -> Fut,
+    Fut: Future<Output = Result<CallToolResult, E>>,
+{
+    let completed = progress.lock().unwrap().completed;
+    for chunk_request in calls.into_iter().skip(completed) {
+        let result = call(chunk_request).await?;
+        let is_error = result.is_error == Some(true);
+        let mut progress = progress.lock().unwrap();
+        progress.completed += 1;
+        match &mut progress.merged {
+            Some(merged) => merge_results(merged, result),
+            None => progress.merged = Some(result),
+        }
+        if is_error {
+            break;
+        }
+    }
+    Ok(progress.lock().unwrap().merged.clone())
+}
+
+/// Append the result of the call with the next chunk to the results of the previous calls.
+/// Content is concatenated and the structured content of the last call is kept.
+pub fn merge_results(merged: &mut CallToolResult, next: CallToolResult) {
+    merged.content.extend(next.content);
+    if next.structured_content.is_some() {
+        merged.structured_content = next.structured_content;
+    }
+    merged.is_error = next.is_error;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    #[test]
+    fn test_split_text() {
+        assert_eq!(
+            split_text("the quick brown fox", 10),
+            ["the quick ", "brown fox"]
+        );
+        // Long words are cut, but never inside a character
+        assert_eq!(split_text("ééééé", 5), ["éé", "éé", "é"]);
+
+        let chunked_input = ChunkedInputConfig {
+            argument: "message".to_string(),
+            max_bytes: 10,
+        };
+        let call_request = CallToolRequestParam {
+            name: "chat".into(),
+            arguments: serde_json::json!({"message": "the quick brown fox", "thread": 7})
+                .as_object()
+                .cloned(),
+        };
+        let calls = chunked_calls(&chunked_input, &call_request).unwrap();
+        assert_eq!(calls.len(), 2);
+        let arguments = calls[1].arguments.as_ref().unwrap();
+        assert_eq!(arguments["message"], "brown fox");
+        assert_eq!(arguments["thread"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_call_chunks_resumes_after_failure() {
+        let calls = ["one", "two", "three"]
+            .into_iter()
+            .map(|chunk| CallToolRequestParam {
+                name: "chat".into(),
+                arguments: serde_json::json!({ "message": chunk }).as_object().cloned(),
+            })
+            .collect::<Vec<_>>();
+        let sent = Mutex::new(Vec::new());
+        let progress = Mutex::default();
+        let call = |fail_on: &'static str| {
+            let sent = &sent;
+            move |call_request: CallToolRequestParam| async move {
+                let chunk = call_request.arguments.unwrap()["message"]
+                    .as_str()
+                    .unwrap()
+                    .to_string();
+                sent.lock().unwrap().push(chunk.clone());
+                if chunk == fail_on {
+                    return Err("connection reset");
+                }
+                Ok(CallToolResult::success(vec![Content::text(chunk)]))
+            }
+        };
+
+        let err = call_chunks(calls.clone(), &progress, call("two")).await;
+        assert_eq!(err.unwrap_err(), "connection reset");
+        let result = call_chunks(calls, &progress, call("none"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The retry starts at the chunk that failed
+        assert_eq!(*sent.lock().unwrap(), ["one", "two", "two", "three"]);
+        let texts = result
+            .content
+            .iter()
+            .filter_map(|content| content.raw.as_text())
+            .map(|text| text.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["one", "two", "three"]);
+    }
+}
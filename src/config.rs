@@ -1,40 +1,105 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use rmcp::model::{JsonObject, Prompt, Resource, ResourceTemplate, Tool};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::config_error::parse_config;
+use crate::features::Feature;
+
 pub static CONFIG_FILE_NAME: &str = "configuration.json";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum EnvVariableValue {
     Literal(String),
     FromEnv {
         #[serde(rename = "fromEnv")]
         from_env: String,
+        /// Value used when the environment variable is not set
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<String>,
+        /// Omit the value instead of failing when the environment variable is not set
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        optional: bool,
     },
 }
 
 impl EnvVariableValue {
-    pub fn resolve(&self) -> Result<String> {
+    /// Resolve the value. Returns `None` for an optional value whose environment variable is
+    /// not set and which has no default.
+    pub fn resolve(&self) -> Result<Option<String>> {
         match self {
-            EnvVariableValue::Literal(value) => Ok(value.clone()),
-            EnvVariableValue::FromEnv { from_env } => std::env::var(from_env)
-                .map_err(|_| anyhow!("Environment variable {} not found", from_env)),
+            EnvVariableValue::Literal(value) => Ok(Some(value.clone())),
+            EnvVariableValue::FromEnv {
+                from_env,
+                default,
+                optional,
+            } => match std::env::var(from_env) {
+                Ok(value) => Ok(Some(value)),
+                Err(_) if default.is_some() => Ok(default.clone()),
+                Err(_) if *optional => Ok(None),
+                Err(_) => Err(anyhow!("Environment variable {} not found", from_env)),
+            },
         }
     }
 }
 
+/// Options of an MCP server independent of its transport
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ServerOptions {
+    /// Expose all tools of the server as functions, procedures or both, instead of by whether
+    /// they are read-only
+    #[serde(
+        default,
+        alias = "exposeToolsAs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expose_tools_as: Option<ToolExposure>,
+
+    /// Fraction of tool calls to the server logged with their arguments and results, between
+    /// 0 and 1
+    #[serde(
+        default,
+        alias = "logSampleRate",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub log_sample_rate: Option<f64>,
+
+    /// Seconds to wait for the response to a tool call, resource read or prompt request before
+    /// failing it with 504 Gateway Timeout
+    #[serde(
+        default,
+        alias = "callTimeoutSeconds",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub call_timeout_seconds: Option<u64>,
+
+    /// Retries of calls to the server, overriding the top-level `retry`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryConfig>,
+
+    /// Limit on the size of tool results and resource contents of the server
+    #[serde(
+        default,
+        alias = "responseLimit",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub response_limit: Option<ResponseLimitConfig>,
+}
+
 /// Configuration for a stdio-based MCP server
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct StdioConfig {
-    /// Command to start the server executable
-    pub command: String,
+    /// Command to start the server executable. Can be read from an environment variable.
+    pub command: EnvVariableValue,
 
-    /// Arguments passed to the command
+    /// Arguments passed to the command. Each can be read from an environment variable, and
+    /// optional ones whose variable is not set are omitted.
     #[serde(default)]
-    pub args: Vec<String>,
+    pub args: Vec<EnvVariableValue>,
 
     /// Environment variables for the server
     #[serde(default)]
@@ -43,10 +108,34 @@ pub struct StdioConfig {
     /// Path to an .env file from which to load additional environment variables
     #[serde(skip_serializing_if = "Option::is_none")]
     pub env_file: Option<String>,
+
+    /// Environment variables replacing those of `env` when the `update` command introspects
+    /// the server, e.g. a read-only token distinct from the one used to call tools
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub introspection_env: HashMap<String, EnvVariableValue>,
+
+    /// Options of the server independent of its transport
+    #[serde(flatten)]
+    pub options: ServerOptions,
+}
+
+impl StdioConfig {
+    /// Resolve the command and its arguments
+    pub fn resolve_command(&self) -> Result<(String, Vec<String>)> {
+        let command = self
+            .command
+            .resolve()?
+            .ok_or_else(|| anyhow!("The command is not set"))?;
+        let mut args = Vec::with_capacity(self.args.len());
+        for arg in &self.args {
+            args.extend(arg.resolve()?);
+        }
+        Ok((command, args))
+    }
 }
 
 /// Configuration for an SSE-based MCP server (DEPRECATED - use HTTP instead)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SseConfig {
     /// URL of the server
     pub url: String,
@@ -54,10 +143,14 @@ pub struct SseConfig {
     /// HTTP headers for the server
     #[serde(default)]
     pub headers: HashMap<String, String>,
+
+    /// Options of the server independent of its transport
+    #[serde(flatten)]
+    pub options: ServerOptions,
 }
 
 /// Configuration for a streamable HTTP-based MCP server
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct StreamableHttpConfig {
     /// URL of the server
     pub url: String,
@@ -66,15 +159,49 @@ pub struct StreamableHttpConfig {
     #[serde(default)]
     pub headers: HashMap<String, EnvVariableValue>,
 
+    /// HTTP headers replacing those of `headers` when the `update` command introspects the
+    /// server, e.g. a read-only token distinct from the one used to call tools
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub introspection_headers: HashMap<String, EnvVariableValue>,
+
     /// Timeout for HTTP requests in seconds
     #[serde(default = "default_timeout")]
     pub timeout_seconds: u64,
+
+    /// Fall back to the HTTP+SSE transport of earlier protocol versions if the server doesn't
+    /// accept streamable HTTP
+    #[serde(default = "default_sse_fallback")]
+    pub sse_fallback: bool,
+
+    /// Options of the server independent of its transport
+    #[serde(flatten)]
+    pub options: ServerOptions,
+}
+
+/// Configuration for an MCP server connected with a transport registered by an embedder with
+/// `transport::register_transport`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CustomTransportConfig {
+    /// Name of the registered transport
+    pub transport: String,
+
+    /// Settings passed to the transport, as described by its configuration schema
+    #[serde(default)]
+    pub settings: serde_json::Value,
+
+    /// Options of the server independent of its transport
+    #[serde(flatten)]
+    pub options: ServerOptions,
 }
 
 fn default_timeout() -> u64 {
     30
 }
 
+fn default_sse_fallback() -> bool {
+    true
+}
+
 /// Configuration for an MCP server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -85,24 +212,1065 @@ pub enum McpServerConfig {
     Sse(SseConfig),
     #[serde(rename = "http")]
     Http(StreamableHttpConfig),
+    #[serde(rename = "custom")]
+    Custom(CustomTransportConfig),
+}
+
+impl McpServerConfig {
+    /// The configuration to introspect the server with, using its introspection credentials
+    /// instead of the runtime ones where configured
+    pub fn for_introspection(&self) -> McpServerConfig {
+        let mut config = self.clone();
+        match &mut config {
+            McpServerConfig::Stdio(stdio_config) => {
+                let introspection_env = std::mem::take(&mut stdio_config.introspection_env);
+                stdio_config.env.extend(introspection_env);
+            }
+            McpServerConfig::Http(http_config) => {
+                let introspection_headers = std::mem::take(&mut http_config.introspection_headers);
+                http_config.headers.extend(introspection_headers);
+            }
+            McpServerConfig::Sse(_) | McpServerConfig::Custom(_) => {}
+        }
+        config
+    }
+
+    /// The name of the transport connecting to the server
+    pub fn transport_name(&self) -> &str {
+        match self {
+            McpServerConfig::Stdio(_) => "stdio",
+            McpServerConfig::Sse(_) => "sse",
+            McpServerConfig::Http(_) => "http",
+            McpServerConfig::Custom(config) => &config.transport,
+        }
+    }
+
+    /// The settings passed to the transport connecting to the server
+    pub fn transport_settings(&self) -> Result<serde_json::Value> {
+        let settings = match self {
+            McpServerConfig::Stdio(config) => serde_json::to_value(config)?,
+            McpServerConfig::Sse(config) => serde_json::to_value(config)?,
+            McpServerConfig::Http(config) => serde_json::to_value(config)?,
+            McpServerConfig::Custom(config) => config.settings.clone(),
+        };
+        Ok(settings)
+    }
+
+    /// The options of the server independent of its transport
+    pub fn options(&self) -> &ServerOptions {
+        match self {
+            McpServerConfig::Stdio(config) => &config.options,
+            McpServerConfig::Sse(config) => &config.options,
+            McpServerConfig::Http(config) => &config.options,
+            McpServerConfig::Custom(config) => &config.options,
+        }
+    }
+}
+
+/// How the tools of a server are exposed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolExposure {
+    /// Every tool is a function
+    Functions,
+    /// Every tool is a procedure
+    Procedures,
+    /// Every tool is both a function suffixed with `_function` and a procedure suffixed with
+    /// `_procedure`
+    Both,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(transparent)]
 pub struct McpServerName(pub String);
 
+/// How tools and arguments marked `deprecated: true` are exposed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeprecatedPolicy {
+    /// Keep deprecated tools and arguments, marking them in their descriptions
+    #[default]
+    Annotate,
+    /// Omit deprecated tools and optional arguments from the schema
+    Hide,
+}
+
+/// Where the server name goes in generated collection, function and procedure names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamingStyle {
+    /// `{server}{separator}{name}`
+    #[default]
+    Prefix,
+    /// `{name}{separator}{server}`
+    Suffix,
+    /// `{name}`, for single-server setups
+    None,
+}
+
+/// Which text content of tool results the `text` field holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextField {
+    /// The first text content block
+    First,
+    /// All text content blocks, separated by newlines
+    Merged,
+}
+
+impl TextField {
+    /// The text of the content blocks of a tool result, `None` if there is no text content
+    pub fn text<'a>(&self, texts: impl IntoIterator<Item = &'a str>) -> Option<String> {
+        let mut texts = texts.into_iter();
+        match self {
+            TextField::First => texts.next().map(str::to_string),
+            TextField::Merged => {
+                let texts = texts.collect::<Vec<_>>();
+                (!texts.is_empty()).then(|| texts.join("\n"))
+            }
+        }
+    }
+}
+
+/// How error results of tools are returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolErrors {
+    /// In a `tool_error` field of the tool output, whose content is then empty
+    Field,
+    /// As the error response of the query or mutation
+    Response,
+}
+
+/// How generated collection, function and procedure names are built
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamingConfig {
+    /// Where the server name goes
+    #[serde(default)]
+    pub style: NamingStyle,
+
+    /// Separator between the server name and the resource, tool or prompt name
+    #[serde(default = "default_naming_separator")]
+    pub separator: String,
+}
+
+fn default_naming_separator() -> String {
+    "__".to_string()
+}
+
+impl Default for NamingConfig {
+    fn default() -> Self {
+        Self {
+            style: NamingStyle::default(),
+            separator: default_naming_separator(),
+        }
+    }
+}
+
+impl NamingConfig {
+    /// Build the NDC name of a resource, tool or prompt of a server
+    pub fn name(&self, server_prefix: &str, name: &str) -> String {
+        match self.style {
+            NamingStyle::Prefix => format!("{}{}{}", server_prefix, self.separator, name),
+            NamingStyle::Suffix => format!("{}{}{}", name, self.separator, server_prefix),
+            NamingStyle::None => name.to_string(),
+        }
+    }
+}
+
+/// Forwarding of HTTP request headers into tool arguments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardedHeadersConfig {
+    /// Name of the argument the engine forwards request headers in
+    #[serde(default = "default_headers_argument")]
+    pub argument: String,
+
+    /// Map from header name to the tool argument populated from it. Mapped arguments are
+    /// hidden from the schema and can only be set through the forwarded header.
+    pub mappings: HashMap<String, String>,
+}
+
+fn default_headers_argument() -> String {
+    "_headers".to_string()
+}
+
+/// URI schemes the resources of a server may be read with
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UriSchemePolicy {
+    /// Only read resources with these schemes, if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow: Option<Vec<String>>,
+
+    /// Never read resources with these schemes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+impl UriSchemePolicy {
+    /// Check if a resource URI may be read. Schemes can be configured as `file` or
+    /// `file://`, and are compared case-insensitively. URIs without a scheme are only
+    /// allowed when no allow list is set.
+    pub fn allows(&self, uri: &str) -> bool {
+        let scheme = uri
+            .split_once(':')
+            .map(|(scheme, _)| scheme)
+            .filter(|scheme| {
+                !scheme.is_empty()
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+            });
+        let matches = |schemes: &[String]| {
+            scheme.is_some_and(|scheme| {
+                schemes.iter().any(|configured| {
+                    configured
+                        .trim_end_matches("://")
+                        .trim_end_matches(':')
+                        .eq_ignore_ascii_case(scheme)
+                })
+            })
+        };
+        if matches(&self.deny) {
+            return false;
+        }
+        self.allow.as_deref().is_none_or(matches)
+    }
+}
+
+/// Decryption of sealed argument values before they are forwarded to MCP servers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedArgumentsConfig {
+    /// Names of the arguments whose string values are sealed
+    pub arguments: Vec<String>,
+
+    /// How sealed values are decrypted. May be omitted when the decryptor is provided
+    /// programmatically through `McpConnectorSetup::with_decryptor`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decryptor: Option<DecryptorConfig>,
+}
+
+/// Configuration for a built-in argument decryptor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DecryptorConfig {
+    /// Pipe the sealed value to a command and read the plaintext from its standard output
+    #[serde(rename = "command")]
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// Cache-control directives for the responses of a read-only tool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheControl {
+    /// Never cache responses
+    #[serde(default)]
+    pub no_store: bool,
+
+    /// How long a response is served from the cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_seconds: Option<u64>,
+
+    /// How long a response past `max_age_seconds` is still served while it is refreshed in
+    /// the background
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale_while_revalidate_seconds: Option<u64>,
+}
+
+/// Where cached tool responses are stored
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CacheBackendConfig {
+    /// Keep responses in the memory of each connector process
+    #[default]
+    #[serde(rename = "memory")]
+    Memory,
+    /// Share responses between connector replicas through Redis
+    #[serde(rename = "redis")]
+    Redis {
+        /// Redis connection URL, such as `redis://localhost:6379`
+        url: EnvVariableValue,
+        /// Prefix of the Redis keys, `ndc-mcp:` by default
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key_prefix: Option<String>,
+    },
+}
+
+/// Failing fast for servers that keep failing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Number of consecutive failed calls after which a server is considered degraded
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// How long calls to a degraded server fail fast before a call is let through again
+    #[serde(default = "default_cooldown_seconds")]
+    pub cooldown_seconds: u64,
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_cooldown_seconds() -> u64 {
+    30
+}
+
+/// Retries of calls that failed with transient errors
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RetryConfig {
+    /// Maximum number of retries of a call
+    #[serde(default = "default_max_retries", alias = "maxRetries")]
+    pub max_retries: u32,
+
+    /// Share of the calls to a server that may be retries
+    #[serde(default = "default_budget_ratio", alias = "budgetRatio")]
+    pub budget_ratio: f64,
+
+    /// Delay before the first retry, doubled for each further retry
+    #[serde(default = "default_backoff_ms", alias = "backoffMs")]
+    pub backoff_ms: u64,
+
+    /// Fraction of each delay added or taken away at random, so that clients failing together
+    /// don't retry together
+    #[serde(default = "default_jitter")]
+    pub jitter: f64,
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_jitter() -> f64 {
+    0.2
+}
+
+/// Limit on the size of tool results, measured as JSON
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResponseLimitConfig {
+    /// Maximum size of a tool result in bytes
+    #[serde(alias = "maxResponseBytes")]
+    pub max_response_bytes: usize,
+
+    /// What to do with larger results
+    #[serde(default, alias = "onExceeded")]
+    pub on_exceeded: ResponseLimitAction,
+}
+
+/// What to do with tool results exceeding the response limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseLimitAction {
+    /// Fail the call
+    #[default]
+    Error,
+    /// Cut text content to fit, marking where it was cut
+    Truncate,
+    /// Remove images, audio and binary resources
+    DropBinary,
+}
+
+/// Concurrent execution of the operations of a mutation. Results keep the order of the
+/// operations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParallelMutationsConfig {
+    /// Maximum number of operations of a mutation calling the same MCP server at a time
+    #[serde(default = "default_mutation_concurrency_per_server")]
+    pub max_concurrency_per_server: usize,
+}
+
+fn default_mutation_concurrency_per_server() -> usize {
+    4
+}
+
+fn default_variable_set_concurrency() -> usize {
+    10
+}
+
+fn default_budget_ratio() -> f64 {
+    0.1
+}
+
+fn default_backoff_ms() -> u64 {
+    100
+}
+
+/// Configuration for an individual tool
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolConfig {
+    /// Cache-control directives, overriding the `_meta.cacheControl` hints of the server
+    #[serde(
+        default,
+        alias = "cacheControl",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cache_control: Option<CacheControl>,
+
+    /// Output schema of the tool, e.g. as proposed by the `propose-output-schemas` command,
+    /// overriding the one it declares. Results are typed and projected from it.
+    #[serde(
+        default,
+        alias = "outputSchema",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub output_schema: Option<JsonObject>,
+
+    /// Expose the tool as a function (`true`) or a procedure (`false`), overriding the
+    /// classification from its annotations and name
+    #[serde(default, alias = "readOnly", skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+
+    /// Values sent in the `_meta` field of every call to the tool, e.g. to select a tenant
+    /// of a multi-tenant stdio server. Values can be read from environment variables.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub meta: HashMap<String, EnvVariableValue>,
+
+    /// Split a text argument longer than a size limit into sequential calls, concatenating
+    /// their results
+    #[serde(
+        default,
+        alias = "chunkedInput",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub chunked_input: Option<ChunkedInputConfig>,
+
+    /// Expose page numbers instead of the opaque cursors the tool paginates its results with,
+    /// remembering the cursors of each pagination session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<PaginationConfig>,
+
+    /// Return the results of a read-only tool as a single object, or as a collection with a
+    /// row per element of the array in its structured content
+    #[serde(default, alias = "resultMode", skip_serializing_if = "Option::is_none")]
+    pub result_mode: Option<ResultMode>,
+
+    /// Return the result of the previous identical call, with the same arguments, instead of
+    /// calling the tool again within this many seconds, e.g. to guard against agents sending
+    /// an email twice
+    #[serde(
+        default,
+        alias = "duplicateCallWindowSeconds",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub duplicate_call_window_seconds: Option<u64>,
+
+    /// Retry calls of the tool that failed with transient errors (`true`) or never (`false`),
+    /// overriding the default of retrying read-only and idempotent tools only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<bool>,
+
+    /// Seconds to wait for the response to a call to the tool, overriding the timeout of its
+    /// server
+    #[serde(
+        default,
+        alias = "callTimeoutSeconds",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub call_timeout_seconds: Option<u64>,
+
+    /// Hints for agents planning tool use, appended to the description of the tool as JSON
+    #[serde(
+        default,
+        alias = "routingHints",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub routing_hints: Option<RoutingHints>,
+}
+
+/// How costly, slow and risky calling a tool is, for agents choosing between tools
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutingHints {
+    /// Relative cost of a call, e.g. of a paid upstream API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<HintLevel>,
+
+    /// Relative latency of a call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<HintLevel>,
+
+    /// Whether a call can be made without review
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety: Option<SafetyLevel>,
+}
+
+/// A relative level of a routing hint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HintLevel {
+    Low,
+    Medium,
+    High,
+}
+
+/// Whether calling a tool needs review
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyLevel {
+    /// Calls have no harmful side effects
+    Safe,
+    /// Calls should be confirmed with the user
+    Review,
+    /// Calls may cause irreversible harm
+    Dangerous,
+}
+
+/// How the results of a tool are returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultMode {
+    /// A function returning the result object
+    Object,
+    /// A collection with a row per element of the array of the structured content, typed by
+    /// the items of the output schema
+    Rows,
+}
+
+/// A text argument sent in chunks of limited size, one call per chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkedInputConfig {
+    /// Name of the text argument
+    pub argument: String,
+
+    /// Maximum size of the argument in a call, in bytes
+    pub max_bytes: usize,
+}
+
+/// Pagination of a tool that returns the cursor of the next page in the `nextCursor` field of
+/// the `_meta` of its results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginationConfig {
+    /// Name of the argument the cursor of a page is passed in
+    #[serde(default = "default_cursor_argument")]
+    pub cursor_argument: String,
+
+    /// Name of the argument taking the page number, for tools with their own `page` argument
+    #[serde(default = "default_page_argument")]
+    pub page_argument: String,
+
+    /// Name of the argument naming the pagination session, for tools with their own
+    /// `pagination_session` argument
+    #[serde(default = "default_session_argument")]
+    pub session_argument: String,
+
+    /// Highest page that can be requested, bounding the calls made for a single request
+    #[serde(default = "default_max_pages")]
+    pub max_pages: u64,
+}
+
+fn default_cursor_argument() -> String {
+    "cursor".to_string()
+}
+
+fn default_page_argument() -> String {
+    "page".to_string()
+}
+
+fn default_session_argument() -> String {
+    "pagination_session".to_string()
+}
+
+fn default_max_pages() -> u64 {
+    100
+}
+
+impl ToolConfig {
+    /// Resolve the `_meta` values of the tool. Returns `None` if there are none.
+    pub fn resolve_meta(&self) -> Result<Option<JsonObject>> {
+        let mut meta = JsonObject::new();
+        for (key, value) in &self.meta {
+            if let Some(value) = value.resolve()? {
+                meta.insert(key.clone(), serde_json::Value::String(value));
+            }
+        }
+        Ok(Some(meta).filter(|meta| !meta.is_empty()))
+    }
+}
+
+/// Configuration for an individual resource
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceConfig {
+    /// Parse the content of the resource into typed rows when it is read, so it can be
+    /// filtered, ordered and paginated like a table
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows: Option<ResourceRowsConfig>,
+}
+
+/// How the content of a resource is parsed into rows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceRowsConfig {
+    /// Format of the content
+    pub format: RowsFormat,
+
+    /// Columns of the rows and their types. Other fields of the content are ignored.
+    pub columns: BTreeMap<String, ColumnType>,
+}
+
+/// Format of resource content parsed into rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowsFormat {
+    /// A JSON array of objects
+    Json,
+    /// CSV with a header line naming the columns
+    Csv,
+}
+
+/// Type of a column of resource rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    String,
+    Int,
+    Float,
+    Boolean,
+    Json,
+}
+
+impl ColumnType {
+    /// Name of the scalar type of the column
+    pub fn scalar_type(&self) -> &'static str {
+        match self {
+            ColumnType::String => "String",
+            ColumnType::Int => "Int",
+            ColumnType::Float => "Float",
+            ColumnType::Boolean => "Boolean",
+            ColumnType::Json => "JSON",
+        }
+    }
+}
+
+/// Resources, tools and prompts introspected from an MCP server, stored by the `update` command
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerIntrospection {
+    /// Resources provided by the server
+    #[serde(default)]
+    pub resources: Vec<Resource>,
+
+    /// Resource templates provided by the server
+    #[serde(default)]
+    pub resource_templates: Vec<ResourceTemplate>,
+
+    /// Tools provided by the server
+    #[serde(default)]
+    pub tools: Vec<Tool>,
+
+    /// Prompts provided by the server
+    #[serde(default)]
+    pub prompts: Vec<Prompt>,
+}
+
 /// Configuration for the NDC MCP connector
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectorConfig {
     /// List of MCP servers
     pub servers: HashMap<McpServerName, McpServerConfig>,
+
+    /// Per-tool configuration, keyed by server name and then tool name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tools: HashMap<McpServerName, HashMap<String, ToolConfig>>,
+
+    /// Per-resource configuration, keyed by server name and then resource name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub resources: HashMap<McpServerName, HashMap<String, ResourceConfig>>,
+
+    /// How generated names combine server and resource, tool or prompt names
+    #[serde(default)]
+    pub naming: NamingConfig,
+
+    /// How deprecated tools and arguments are exposed
+    #[serde(default)]
+    pub deprecated: DeprecatedPolicy,
+
+    /// Classify tools as read-only only by their `readOnlyHint` annotation, without guessing
+    /// from names such as `get_*` or `list_*`
+    #[serde(default)]
+    pub trust_read_only_hint: bool,
+
+    /// Hide tools annotated with `destructiveHint: true`
+    #[serde(default)]
+    pub hide_destructive_tools: bool,
+
+    /// Return the `structured_content` of tools without an output schema as a JSON string, as
+    /// older versions of the connector did, instead of a nested JSON value
+    #[serde(default)]
+    pub stringify_structured_content: bool,
+
+    /// Add a `text` field to tool results with the first or the merged text content, so
+    /// consumers can select a single string instead of the content array
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_field: Option<TextField>,
+
+    /// Read the code, message and data of tool error results into a typed `ToolError`, returned
+    /// in a `tool_error` field or as an error response, instead of mixing the error into the
+    /// normal content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_errors: Option<ToolErrors>,
+
+    /// Fail calls to a server fast while it is degraded after repeated failures, instead of
+    /// letting every call time out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+
+    /// Retry calls that failed with transient errors, within a retry budget per server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryConfig>,
+
+    /// Check arguments against the `minimum`, `maximum`, length, `pattern` and item count
+    /// constraints of the tool's input schema before calling the tool
+    #[serde(default)]
+    pub validate_arguments: bool,
+
+    /// Validate query and mutation requests against the schema, rejecting unknown names,
+    /// missing or mistyped arguments and unsupported query features with 400 Bad Request
+    #[serde(default)]
+    pub strict_requests: bool,
+
+    /// Maximum number of variable sets of a query executed concurrently
+    #[serde(default = "default_variable_set_concurrency")]
+    pub variable_set_concurrency: usize,
+
+    /// Maximum decoded size in bytes of each base64-encoded binary argument
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_binary_argument_bytes: Option<usize>,
+
+    /// Execute every operation of a mutation even if some fail. Failed operations return an
+    /// `error` object in their result instead of failing the whole mutation.
+    #[serde(default)]
+    pub best_effort_mutations: bool,
+
+    /// Execute the operations of a mutation concurrently instead of one after the other
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel_mutations: Option<ParallelMutationsConfig>,
+
+    /// Forwarding of HTTP request headers into tool arguments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarded_headers: Option<ForwardedHeadersConfig>,
+
+    /// Where cached responses of read-only tools are stored
+    #[serde(default)]
+    pub cache_backend: CacheBackendConfig,
+
+    /// Decryption of sealed argument values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed_arguments: Option<SealedArgumentsConfig>,
+
+    /// URI schemes the resources of each server may be read with
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub resource_uri_schemes: HashMap<McpServerName, UriSchemePolicy>,
+
+    /// Feature flags toggling connector subsystems, by flag name. See `Feature` for the flags
+    /// and their defaults.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub features: BTreeMap<String, bool>,
+
+    /// Omit all procedures from the schema and reject every mutation, so the connector can't
+    /// call tools that modify upstream systems. Also enabled by the `NDC_MCP_READ_ONLY`
+    /// environment variable.
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// Expose the `_diagnostics` function with runtime diagnostics such as inferred output
+    /// schemas
+    #[serde(default)]
+    pub diagnostics: bool,
+
+    /// Address to serve the connection events of every server on as JSON, at
+    /// `/debug/connection-events`, such as `127.0.0.1:9091`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug_address: Option<String>,
+
+    /// Serve the schema from the stored introspection without connecting to any server.
+    /// Queries and mutations fail with 503 Service Unavailable.
+    #[serde(default)]
+    pub offline: bool,
+
+    /// Generate the schema by introspecting the servers on every schema request, instead of
+    /// from the stored introspection
+    #[serde(default)]
+    pub live_schema_introspection: bool,
+
+    /// Introspection results per server, written by the `update` command
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub introspection: HashMap<McpServerName, ServerIntrospection>,
 }
 
 impl ConnectorConfig {
     /// Load configuration from a file
     pub fn from_file(path: &PathBuf) -> Result<Self> {
         let content = fs::read_to_string(path)?;
-        let config: ConnectorConfig = serde_json::from_str(&content)?;
+        let config = parse_config(&content)
+            .with_context(|| format!("Invalid configuration {}", path.display()))?;
         Ok(config)
     }
+
+    /// Check if a feature is enabled by the feature flags
+    pub fn feature_enabled(&self, feature: Feature) -> bool {
+        feature.is_enabled(&self.features)
+    }
+
+    /// Check if the schema is generated from the stored introspection. It is unless live
+    /// introspection is enabled, or some server has no stored introspection and the connector
+    /// is not offline.
+    pub fn schema_from_stored_introspection(&self) -> bool {
+        self.offline
+            || (!self.live_schema_introspection
+                && self
+                    .servers
+                    .keys()
+                    .all(|server_name| self.introspection.contains_key(server_name)))
+    }
+
+    /// The timeout of calls to a server, or to one of its tools, from the configuration of the
+    /// tool or else of the server
+    pub fn call_timeout(
+        &self,
+        server_name: &McpServerName,
+        tool_name: Option<&str>,
+    ) -> Option<std::time::Duration> {
+        tool_name
+            .and_then(|tool_name| self.tool_config(server_name, tool_name))
+            .and_then(|tool_config| tool_config.call_timeout_seconds)
+            .or_else(|| {
+                self.servers
+                    .get(server_name)
+                    .and_then(|server_config| server_config.options().call_timeout_seconds)
+            })
+            .map(std::time::Duration::from_secs)
+    }
+
+    /// Get the configuration of a tool, if any
+    pub fn tool_config(&self, server_name: &McpServerName, tool_name: &str) -> Option<&ToolConfig> {
+        self.tools
+            .get(server_name)
+            .and_then(|tools| tools.get(tool_name))
+    }
+
+    /// Get the row parsing configuration of a resource, if any
+    pub fn resource_rows(
+        &self,
+        server_name: &McpServerName,
+        resource_name: &str,
+    ) -> Option<&ResourceRowsConfig> {
+        self.resources
+            .get(server_name)
+            .and_then(|resources| resources.get(resource_name))
+            .and_then(|resource| resource.rows.as_ref())
+    }
+
+    /// How the tools of a server are exposed, if configured
+    pub fn tool_exposure(&self, server_name: &McpServerName) -> Option<ToolExposure> {
+        self.servers
+            .get(server_name)
+            .and_then(|server_config| server_config.options().expose_tools_as)
+    }
+
+    /// Write configuration to a file
+    pub fn to_file(&self, path: &PathBuf) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content + "\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_introspection_credentials() {
+        let config: McpServerConfig = serde_json::from_value(json!({
+            "type": "http",
+            "url": "http://localhost:8080/mcp",
+            "headers": {
+                "Authorization": "Bearer runtime",
+                "X-Tenant": "acme"
+            },
+            "introspection_headers": {"Authorization": "Bearer read-only"}
+        }))
+        .unwrap();
+        let McpServerConfig::Http(introspection_config) = config.for_introspection() else {
+            panic!("Expected an HTTP server");
+        };
+        let header = |name: &str| introspection_config.headers[name].resolve().unwrap();
+        assert_eq!(header("Authorization").as_deref(), Some("Bearer read-only"));
+        assert_eq!(header("X-Tenant").as_deref(), Some("acme"));
+        assert!(introspection_config.introspection_headers.is_empty());
+    }
+
+    #[test]
+    fn test_call_timeout() {
+        let config: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {"search": {
+                "type": "http",
+                "url": "http://localhost:8080/mcp",
+                "call_timeout_seconds": 30
+            }},
+            "tools": {"search": {"crawl": {"call_timeout_seconds": 300}}}
+        }))
+        .unwrap();
+        let server_name = McpServerName("search".to_string());
+        let timeout = |tool_name| {
+            config
+                .call_timeout(&server_name, tool_name)
+                .map(|timeout| timeout.as_secs())
+        };
+        assert_eq!(timeout(Some("crawl")), Some(300));
+        assert_eq!(timeout(Some("search")), Some(30));
+        assert_eq!(timeout(None), Some(30));
+        assert_eq!(
+            config.call_timeout(&McpServerName("other".to_string()), None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_server_options() {
+        let config: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {"files": {
+                "type": "stdio",
+                "command": "mcp-files",
+                "exposeToolsAs": "functions",
+                "logSampleRate": 0.5,
+                "callTimeoutSeconds": 10,
+                "retry": {"maxRetries": 3},
+                "responseLimit": {"maxResponseBytes": 1024, "onExceeded": "truncate"}
+            }}
+        }))
+        .unwrap();
+        let options = config.servers[&McpServerName("files".to_string())].options();
+        assert_eq!(options.expose_tools_as, Some(ToolExposure::Functions));
+        assert_eq!(options.log_sample_rate, Some(0.5));
+        assert_eq!(options.call_timeout_seconds, Some(10));
+        assert_eq!(
+            options.retry.as_ref().map(|retry| retry.max_retries),
+            Some(3)
+        );
+        assert_eq!(
+            options
+                .response_limit
+                .as_ref()
+                .map(|limit| limit.on_exceeded),
+            Some(ResponseLimitAction::Truncate)
+        );
+    }
+
+    #[test]
+    fn test_resolve_stdio_command() {
+        std::env::set_var("NDC_MCP_TEST_FILES_COMMAND", "/opt/mcp/files");
+        let config: StdioConfig = serde_json::from_value(json!({
+            "command": {"fromEnv": "NDC_MCP_TEST_FILES_COMMAND"},
+            "args": [
+                "--root",
+                {"fromEnv": "NDC_MCP_TEST_FILES_ROOT", "default": "/data"},
+                {"fromEnv": "NDC_MCP_TEST_FILES_TOKEN_FLAG", "optional": true}
+            ]
+        }))
+        .unwrap();
+        let (command, args) = config.resolve_command().unwrap();
+        assert_eq!(command, "/opt/mcp/files");
+        assert_eq!(args, vec!["--root", "/data"]);
+    }
+
+    #[test]
+    fn test_schema_from_stored_introspection() {
+        let mut config: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {"files": {"type": "stdio", "command": "mcp-files"}},
+            "introspection": {"files": {"tools": []}}
+        }))
+        .unwrap();
+        assert!(config.schema_from_stored_introspection());
+
+        config.live_schema_introspection = true;
+        assert!(!config.schema_from_stored_introspection());
+
+        config.live_schema_introspection = false;
+        config.introspection.clear();
+        assert!(!config.schema_from_stored_introspection());
+        config.offline = true;
+        assert!(config.schema_from_stored_introspection());
+    }
+
+    #[test]
+    fn test_text_field() {
+        let texts = ["first", "second"];
+        assert_eq!(TextField::First.text(texts), Some("first".to_string()));
+        assert_eq!(
+            TextField::Merged.text(texts),
+            Some("first\nsecond".to_string())
+        );
+        assert_eq!(TextField::Merged.text([]), None);
+    }
+
+    #[test]
+    fn test_uri_scheme_policy() {
+        let policy: UriSchemePolicy =
+            serde_json::from_value(json!({"allow": ["file://", "https"], "deny": ["git"]}))
+                .unwrap();
+        assert!(policy.allows("file:///srv/docs/readme.md"));
+        assert!(policy.allows("HTTPS://example.com"));
+        assert!(!policy.allows("git://github.com/hasura/ndc-mcp-rs"));
+        assert!(!policy.allows("postgres://localhost/db"));
+        assert!(!policy.allows("/etc/passwd"));
+
+        let policy: UriSchemePolicy = serde_json::from_value(json!({"deny": ["file"]})).unwrap();
+        assert!(!policy.allows("file:///etc/passwd"));
+        assert!(policy.allows("memo://notes/1"));
+    }
+
+    #[test]
+    fn test_naming_styles() {
+        let naming = NamingConfig::default();
+        assert_eq!(naming.name("github", "list_issues"), "github__list_issues");
+
+        let naming: NamingConfig =
+            serde_json::from_value(json!({"style": "suffix", "separator": "_via_"})).unwrap();
+        assert_eq!(
+            naming.name("github", "list_issues"),
+            "list_issues_via_github"
+        );
+
+        let naming: NamingConfig = serde_json::from_value(json!({"style": "none"})).unwrap();
+        assert_eq!(naming.name("github", "list_issues"), "list_issues");
+    }
+
+    #[test]
+    fn test_resolve_env_variable_fallbacks() {
+        let required: EnvVariableValue =
+            serde_json::from_value(json!({"fromEnv": "NDC_MCP_TEST_UNSET_REQUIRED"})).unwrap();
+        assert!(required.resolve().is_err());
+
+        let optional: EnvVariableValue = serde_json::from_value(
+            json!({"fromEnv": "NDC_MCP_TEST_UNSET_OPTIONAL", "optional": true}),
+        )
+        .unwrap();
+        assert_eq!(optional.resolve().unwrap(), None);
+
+        let with_default: EnvVariableValue = serde_json::from_value(
+            json!({"fromEnv": "NDC_MCP_TEST_UNSET_DEFAULT", "default": "fallback"}),
+        )
+        .unwrap();
+        assert_eq!(with_default.resolve().unwrap().as_deref(), Some("fallback"));
+
+        let literal: EnvVariableValue = serde_json::from_value(json!("value")).unwrap();
+        assert_eq!(literal.resolve().unwrap().as_deref(), Some("value"));
+    }
+
+    #[test]
+    fn test_resolve_tool_meta() {
+        let tool_config: ToolConfig = serde_json::from_value(json!({
+            "meta": {
+                "tenant": "acme",
+                "region": {"fromEnv": "NDC_MCP_TEST_UNSET_REGION", "optional": true}
+            }
+        }))
+        .unwrap();
+        let meta = tool_config.resolve_meta().unwrap().unwrap();
+        assert_eq!(meta.len(), 1);
+        assert_eq!(meta["tenant"], "acme");
+
+        assert_eq!(ToolConfig::default().resolve_meta().unwrap(), None);
+    }
 }
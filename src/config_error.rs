@@ -0,0 +1,261 @@
+//! Errors in the configuration file, reported with the JSON pointer of the invalid value, the
+//! expected type and a suggestion when a key or value looks like a typo.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+use crate::config::{ConnectorConfig, SseConfig, StdioConfig, StreamableHttpConfig};
+
+/// An invalid configuration
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigError {
+    /// JSON pointer of the invalid value, `None` for syntax errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Line and column of syntax errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.path, self.line, self.column) {
+            (Some(path), _, _) if path.is_empty() => write!(f, "at the root: ")?,
+            (Some(path), _, _) => write!(f, "at {}: ", path)?,
+            (None, Some(line), Some(column)) => write!(f, "at line {} column {}: ", line, column)?,
+            _ => {}
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(expected) = &self.expected {
+            write!(f, ", expected {}", expected)?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Parse the contents of the configuration file
+pub fn parse_config(content: &str) -> Result<ConnectorConfig, ConfigError> {
+    let value: Value = serde_json::from_str(content).map_err(|err| ConfigError {
+        path: None,
+        line: Some(err.line()),
+        column: Some(err.column()),
+        message: syntax_error_message(&err),
+        expected: None,
+        suggestion: None,
+    })?;
+    let (path, err) = match deserialize_at::<ConnectorConfig>(&value, Vec::new()) {
+        Ok(config) => return Ok(config),
+        Err(err) => refine_server_error(&value, &err.0).unwrap_or(err),
+    };
+    Err(config_error(&value, path, &err))
+}
+
+/// Deserialize the value at a path of the configuration, returning the path of errors
+fn deserialize_at<T: DeserializeOwned>(
+    root: &Value,
+    prefix: Vec<String>,
+) -> Result<T, (Vec<String>, serde_json::Error)> {
+    let value = root
+        .pointer(&json_pointer(&prefix))
+        .cloned()
+        .unwrap_or(Value::Null);
+    serde_path_to_error::deserialize(value).map_err(|err| {
+        let mut path = prefix;
+        path.extend(err.path().iter().filter_map(|segment| match segment {
+            serde_path_to_error::Segment::Seq { index } => Some(index.to_string()),
+            serde_path_to_error::Segment::Map { key } => Some(key.clone()),
+            serde_path_to_error::Segment::Enum { variant } => Some(variant.clone()),
+            serde_path_to_error::Segment::Unknown => None,
+        }));
+        (path, err.into_inner())
+    })
+}
+
+/// Servers are tagged with their transport type, which hides the path of errors inside a
+/// server's configuration. Deserialize the server with the configuration type of its
+/// transport to find it.
+fn refine_server_error(root: &Value, path: &[String]) -> Option<(Vec<String>, serde_json::Error)> {
+    let [servers, _] = path else {
+        return None;
+    };
+    if servers != "servers" {
+        return None;
+    }
+    let server = root.pointer(&json_pointer(path))?;
+    let result = match server.get("type")?.as_str()? {
+        "stdio" => deserialize_at::<StdioConfig>(root, path.to_vec()).map(|_| ()),
+        "sse" => deserialize_at::<SseConfig>(root, path.to_vec()).map(|_| ()),
+        "http" => deserialize_at::<StreamableHttpConfig>(root, path.to_vec()).map(|_| ()),
+        _ => return None,
+    };
+    result.err()
+}
+
+/// Describe an error in a value of the configuration
+fn config_error(root: &Value, path: Vec<String>, err: &serde_json::Error) -> ConfigError {
+    let message = err.to_string();
+    let value = root.pointer(&json_pointer(&path));
+    let object_keys = value
+        .and_then(Value::as_object)
+        .map(|object| object.keys().map(String::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let (message, expected, suggestion) = if let Some(field) = quoted(&message, "missing field ") {
+        // A misspelled key leaves the field missing
+        let suggestion = closest(field, &object_keys)
+            .map(|key| format!("did you mean `{}` instead of `{}`?", field, key));
+        (message.clone(), None, suggestion)
+    } else if let Some((unknown, expected)) = message.split_once(", expected one of ") {
+        let variants = expected
+            .split(", ")
+            .map(|variant| variant.trim_matches('`'))
+            .collect::<Vec<_>>();
+        let suggestion = quoted(unknown, "unknown variant ")
+            .and_then(|unknown| closest(unknown, &variants))
+            .map(|variant| format!("did you mean `{}`?", variant));
+        (
+            unknown.to_string(),
+            Some(format!("one of {}", expected)),
+            suggestion,
+        )
+    } else if message.contains("untagged enum EnvVariableValue") {
+        let suggestion = closest("fromEnv", &object_keys)
+            .map(|key| format!("did you mean `fromEnv` instead of `{}`?", key));
+        (
+            "invalid value".to_string(),
+            Some("a string or an object with a `fromEnv` key".to_string()),
+            suggestion,
+        )
+    } else if let Some((invalid, expected)) = message.split_once(", expected ") {
+        (invalid.to_string(), Some(expected.to_string()), None)
+    } else {
+        (message.clone(), None, None)
+    };
+
+    ConfigError {
+        path: Some(json_pointer(&path)),
+        line: None,
+        column: None,
+        message,
+        expected,
+        suggestion,
+    }
+}
+
+/// The message of a syntax error, without the position which is reported separately
+fn syntax_error_message(err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+/// The name quoted in backticks after a prefix of a message, like `missing field `url``
+fn quoted<'a>(message: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = message.strip_prefix(prefix)?.strip_prefix('`')?;
+    rest.split('`').next()
+}
+
+/// The candidate closest to a name, if any is close enough to be a typo of it
+fn closest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let normalize = |name: &str| {
+        name.chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    let normalized = normalize(name);
+    candidates
+        .iter()
+        .filter(|candidate| **candidate != name)
+        .map(|candidate| {
+            (
+                edit_distance(&normalize(candidate), &normalized),
+                *candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous = distances[0];
+        distances[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous + usize::from(a_char != *b_char);
+            previous = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(previous + 1);
+        }
+    }
+    distances[b.len()]
+}
+
+/// JSON pointer of a path
+fn json_pointer(path: &[String]) -> String {
+    path.iter()
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_errors() {
+        let err =
+            parse_config(r#"{"servers": {"github": {"type": "http", "url": 8080}}}"#).unwrap_err();
+        assert_eq!(err.path.as_deref(), Some("/servers/github/url"));
+        assert_eq!(err.expected.as_deref(), Some("a string"));
+
+        let err = parse_config(
+            r#"{"servers": {"github": {"type": "http", "url": "http://localhost:8080/mcp",
+                "headers": {"Authorization": {"from_env": "GITHUB_TOKEN"}}}}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.path.as_deref(),
+            Some("/servers/github/headers/Authorization")
+        );
+        assert_eq!(
+            err.suggestion.as_deref(),
+            Some("did you mean `fromEnv` instead of `from_env`?")
+        );
+
+        let err = parse_config(r#"{"servers": {"files": {"type": "stdo", "command": "ls"}}}"#)
+            .unwrap_err();
+        assert_eq!(err.path.as_deref(), Some("/servers/files"));
+        assert_eq!(err.suggestion.as_deref(), Some("did you mean `stdio`?"));
+
+        let err = parse_config(r#"{"servers": {"files": {"type": "stdio", "comand": "ls"}}}"#)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at /servers/files: missing field `command` (did you mean `command` instead of `comand`?)"
+        );
+
+        let err = parse_config("{\n  \"servers\": {,}\n}").unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert_eq!(err.path, None);
+    }
+}
@@ -2,29 +2,75 @@
 //! The routes are defined here.
 
 use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
 use http::StatusCode;
 use indexmap::IndexMap;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
 
 use ndc_sdk::connector::ErrorResponse;
 use ndc_sdk::connector::{Connector, ConnectorSetup};
 use ndc_sdk::json_response::JsonResponse;
 use ndc_sdk::models;
 use rmcp::{
-    model::{ErrorCode, ErrorData},
-    ServiceError,
+    model::{
+        Annotations, CallToolRequest, CallToolRequestParam, CallToolResult, ClientRequest,
+        ErrorCode, ErrorData, Meta, PromptMessage, RawContent, RawResource, RequestId,
+        ResourceContents, ServerCapabilities, ServerResult, Tool,
+    },
+    service::{PeerRequestOptions, RunningService},
+    RoleClient, ServiceError,
 };
 
+use crate::aggregate::aggregate_rows;
+use crate::cache::{
+    cache_control_from_result, cache_key, create_cache_backend, CacheLookup, ResponseCache,
+};
+use crate::chunking::{call_chunks, chunked_calls, ChunkProgress};
 use crate::config::{
-    ConnectorConfig, EnvVariableValue, McpServerConfig, McpServerName, StdioConfig,
-    StreamableHttpConfig,
+    ConnectorConfig, DeprecatedPolicy, EnvVariableValue, McpServerConfig, McpServerName,
+    PaginationConfig, ServerIntrospection, StreamableHttpConfig, ToolErrors,
+};
+use crate::config_error::ConfigError;
+use crate::debug_endpoint;
+use crate::decrypt::{create_decryptor, ArgumentDecryptor};
+use crate::diagnostics::{diagnostics, DIAGNOSTICS_FUNCTION_NAME};
+use crate::events::ConnectionEventKind;
+use crate::explain::{mutation_explain_details, query_explain_details};
+use crate::failed_calls::{redact_arguments, FailedCall};
+use crate::features::{unknown_feature_flags, Feature};
+use crate::fields::{select_nested_field, select_rowset_fields};
+use crate::health::ServerHealth;
+use crate::initialization::{InitializationReport, ServerInitializationFailure};
+use crate::materialize::parse_rows;
+use crate::operation_error::{OperationError, OperationErrorClass};
+use crate::ordering::{order_rows, paginate_rows};
+use crate::pagination::{
+    conflicting_page_argument, follow_pages, session_key, PageError, PageRequest,
 };
-use crate::schema::generate_schema_from_state;
-use crate::state::{ConnectorState, McpClient};
-use crate::transport::create_mcp_client;
+use crate::predicate::evaluate_predicate;
+use crate::request_validation::{validate_mutation_request, validate_query_request};
+use crate::response_limit::{limit_response, LimitedResponse};
+use crate::retry::RetryBudget;
+use crate::schema::{
+    check_argument_constraints, check_binary_arguments, generate_schema_from_state,
+    is_deprecated_tool, is_destructive_tool, is_function_tool, is_read_only_tool, is_rows_tool,
+    project_structured_content, remove_deprecated_arguments, resolve_schema_refs, sanitized_names,
+    tool_procedure_kind, tool_rows_schema, tool_title,
+};
+use crate::schema_hash::{log_schema_change, schema_hash};
+use crate::server_info::{mcp_server_rows, MCP_SERVERS_COLLECTION_NAME};
+use crate::sessions::SessionStats;
+use crate::startup_summary::StartupSummary;
+use crate::state::{ConnectorState, McpClient, McpItemKind};
+use crate::tool_error::{tool_error, ToolError};
+use crate::transport::{create_mcp_client, transport_factory};
+use crate::uri_template::{expand_uri_template, template_variables};
 
 /// NDC MCP Connector
 #[derive(Default)]
@@ -32,32 +78,148 @@ pub struct McpConnector;
 
 /// Setup for the MCP connector
 #[derive(Default)]
-pub struct McpConnectorSetup;
+pub struct McpConnectorSetup {
+    /// Decryptor for sealed arguments, overriding the one in the configuration
+    decryptor: Option<Arc<dyn ArgumentDecryptor>>,
+}
 
-/// Helper function to initialize MCP clients and build schema
-async fn initialize_mcp_clients(
+impl McpConnectorSetup {
+    /// Use a custom decryptor for the arguments listed in `sealed_arguments`
+    pub fn with_decryptor(decryptor: Arc<dyn ArgumentDecryptor>) -> Self {
+        Self {
+            decryptor: Some(decryptor),
+        }
+    }
+}
+
+/// Helper function to initialize MCP clients and build schema. Every server is initialized
+/// before failing, and the error details list all servers that failed.
+pub(crate) async fn initialize_mcp_clients(
     configuration: &ConnectorConfig,
 ) -> Result<ConnectorState, ErrorResponse> {
     let mut connector_state = ConnectorState::new();
+    let mut report = InitializationReport::default();
     // Initialize clients
     for (server_name, server_config) in &configuration.servers {
         // Create MCP client
-        let service = create_mcp_client(server_config).await.map_err(|e| {
-            ErrorResponse::new(
-                StatusCode::BAD_REQUEST,
-                format!("Failed to create MCP client: {}", e),
-                serde_json::Value::Null,
-            )
-        })?;
+        let service = match create_mcp_client(server_config).await {
+            Ok(service) => service,
+            Err(err) => {
+                connector_state.connection_events.record(
+                    server_name,
+                    ConnectionEventKind::Failed,
+                    Some(err.to_string()),
+                );
+                report.push(ServerInitializationFailure::connect(server_name, &err));
+                continue;
+            }
+        };
 
-        // Introspect resources from the server
-        let mut resources = HashMap::new();
-        match service.list_all_resources().await {
-            Ok(resources_result) => {
-                for resource in resources_result {
-                    resources.insert(resource.raw.name.clone(), resource);
-                }
+        // Introspect resources and tools from the server
+        let introspection = match introspect_server(server_name, &service).await {
+            Ok(introspection) => introspection,
+            Err(err) => {
+                let failure = ServerInitializationFailure::introspect(server_name, &err);
+                connector_state.connection_events.record(
+                    server_name,
+                    ConnectionEventKind::Failed,
+                    Some(failure.message.clone()),
+                );
+                report.push(failure);
+                continue;
             }
+        };
+        connector_state
+            .connection_events
+            .record(server_name, ConnectionEventKind::Connected, None);
+
+        // Create client with introspected data
+        let client = build_client(Some(service), server_name, &introspection, configuration);
+
+        // Add client to state
+        connector_state.add_client(server_name.clone(), client);
+    }
+
+    report.into_result()?;
+    connector_state.assign_ndc_names(&configuration.naming, &configuration.servers);
+    Ok(connector_state)
+}
+
+/// Helper function to build state from the introspection stored in the configuration,
+/// without connecting to any MCP server
+pub(crate) fn initialize_offline_state(
+    configuration: &ConnectorConfig,
+) -> Result<ConnectorState, ErrorResponse> {
+    let mut connector_state = ConnectorState::new();
+    for server_name in configuration.servers.keys() {
+        let introspection = configuration
+            .introspection
+            .get(server_name)
+            .ok_or_else(|| {
+                ErrorResponse::new(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "No stored introspection for server {}; run the update command first",
+                        server_name.0
+                    ),
+                    serde_json::Value::Null,
+                )
+            })?;
+        let client = build_client(None, server_name, introspection, configuration);
+        connector_state.add_client(server_name.clone(), client);
+    }
+    connector_state.assign_ndc_names(&configuration.naming, &configuration.servers);
+    Ok(connector_state)
+}
+
+/// Features a server advertises in its capabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AdvertisedFeatures {
+    resources: bool,
+    tools: bool,
+    prompts: bool,
+}
+
+impl AdvertisedFeatures {
+    /// Servers that didn't report their capabilities are assumed to support everything
+    fn from_capabilities(capabilities: Option<&ServerCapabilities>) -> Self {
+        match capabilities {
+            Some(capabilities) => Self {
+                resources: capabilities.resources.is_some(),
+                tools: capabilities.tools.is_some(),
+                prompts: capabilities.prompts.is_some(),
+            },
+            None => Self {
+                resources: true,
+                tools: true,
+                prompts: true,
+            },
+        }
+    }
+}
+
+/// Helper function to list the resources, tools and prompts of an MCP server. Only the
+/// features the server advertises are listed, so e.g. a prompts-only server isn't asked for
+/// its tools.
+pub(crate) async fn introspect_server(
+    server_name: &McpServerName,
+    service: &RunningService<RoleClient, ()>,
+) -> Result<ServerIntrospection, ErrorResponse> {
+    let mut introspection = ServerIntrospection::default();
+    let features =
+        AdvertisedFeatures::from_capabilities(service.peer_info().map(|info| &info.capabilities));
+    tracing::debug!(
+        server = %server_name.0,
+        resources = features.resources,
+        tools = features.tools,
+        prompts = features.prompts,
+        "Introspecting MCP server"
+    );
+
+    // Introspect resources from the server
+    if features.resources {
+        match service.list_all_resources().await {
+            Ok(resources) => introspection.resources = resources,
             Err(err) => {
                 let err_message = format!(
                     "Failed to list resources for server {}: {}",
@@ -70,19 +232,41 @@ async fn initialize_mcp_clients(
                         serde_json::Value::Null,
                     ));
                 }
-                // If method not found, just log and continue with empty resources
-                tracing::info!("Server {} does not support resources", server_name.0);
+                // Servers may advertise a feature without implementing it
+                tracing::debug!("Server {} does not support resources", server_name.0);
             }
         }
+    }
 
-        // Introspect tools from the server
-        let mut tools = HashMap::new();
-        match service.list_all_tools().await {
-            Ok(tools_result) => {
-                for tool in tools_result {
-                    tools.insert(tool.name.to_string(), tool);
+    // Introspect resource templates from the server
+    if features.resources {
+        match service.list_all_resource_templates().await {
+            Ok(resource_templates) => introspection.resource_templates = resource_templates,
+            Err(err) => {
+                let err_message = format!(
+                    "Failed to list resource templates for server {}: {}",
+                    server_name.0, err
+                );
+                if !is_method_not_found_error(&err) {
+                    return Err(ErrorResponse::new(
+                        StatusCode::BAD_REQUEST,
+                        err_message,
+                        serde_json::Value::Null,
+                    ));
                 }
+                // Servers may advertise a feature without implementing it
+                tracing::debug!(
+                    "Server {} does not support resource templates",
+                    server_name.0
+                );
             }
+        }
+    }
+
+    // Introspect tools from the server
+    if features.tools {
+        match service.list_all_tools().await {
+            Ok(tools) => introspection.tools = tools,
             Err(err) => {
                 let err_message =
                     format!("Failed to list tools for server {}: {}", server_name.0, err);
@@ -93,23 +277,1695 @@ async fn initialize_mcp_clients(
                         serde_json::Value::Null,
                     ));
                 }
-                // If method not found, just log and continue with empty tools
-                tracing::info!("Server {} does not support tools", server_name.0);
+                // Servers may advertise a feature without implementing it
+                tracing::debug!("Server {} does not support tools", server_name.0);
+            }
+        }
+    }
+
+    // Introspect prompts from the server
+    if features.prompts {
+        match service.list_all_prompts().await {
+            Ok(prompts) => introspection.prompts = prompts,
+            Err(err) => {
+                let err_message = format!(
+                    "Failed to list prompts for server {}: {}",
+                    server_name.0, err
+                );
+                if !is_method_not_found_error(&err) {
+                    return Err(ErrorResponse::new(
+                        StatusCode::BAD_REQUEST,
+                        err_message,
+                        serde_json::Value::Null,
+                    ));
+                }
+                // Servers may advertise a feature without implementing it
+                tracing::debug!("Server {} does not support prompts", server_name.0);
+            }
+        }
+    }
+
+    Ok(introspection)
+}
+
+/// Helper function to build a client from introspected resources, tools and prompts
+fn build_client(
+    service: Option<RunningService<RoleClient, ()>>,
+    server_name: &McpServerName,
+    introspection: &ServerIntrospection,
+    configuration: &ConnectorConfig,
+) -> McpClient {
+    // Resources, tools and prompts are keyed by their sanitized names, which are used in the
+    // NDC schema
+    let resource_names = sanitized_names(
+        introspection
+            .resources
+            .iter()
+            .map(|resource| resource.raw.name.as_str()),
+    );
+    let mut resources = HashMap::new();
+    for resource in &introspection.resources {
+        resources.insert(resource_names[&resource.raw.name].clone(), resource.clone());
+    }
+
+    let template_names = sanitized_names(
+        introspection
+            .resource_templates
+            .iter()
+            .map(|template| template.raw.name.as_str()),
+    );
+    let mut resource_templates = HashMap::new();
+    for template in &introspection.resource_templates {
+        resource_templates.insert(template_names[&template.raw.name].clone(), template.clone());
+    }
+
+    let tool_names = sanitized_names(introspection.tools.iter().map(|tool| tool.name.as_ref()));
+    let mut tools = HashMap::new();
+    for tool in &introspection.tools {
+        let mut tool = tool.clone();
+        if configuration.hide_destructive_tools && is_destructive_tool(&tool) {
+            continue;
+        }
+        if configuration.deprecated == DeprecatedPolicy::Hide {
+            if is_deprecated_tool(&tool) {
+                continue;
             }
+            remove_deprecated_arguments(&mut tool);
         }
+        // A configured output schema overrides the one the tool declares, if any
+        if let Some(output_schema) = configuration
+            .tool_config(server_name, &tool.name)
+            .and_then(|tool_config| tool_config.output_schema.clone())
+        {
+            tool.output_schema = Some(Arc::new(output_schema));
+        }
+        tools.insert(tool_names[tool.name.as_ref()].clone(), tool);
+    }
+
+    let prompt_names = sanitized_names(
+        introspection
+            .prompts
+            .iter()
+            .map(|prompt| prompt.name.as_str()),
+    );
+    let mut prompts = HashMap::new();
+    for prompt in &introspection.prompts {
+        prompts.insert(prompt_names[&prompt.name].clone(), prompt.clone());
+    }
+
+    McpClient {
+        service,
+        resources,
+        resource_templates,
+        tools,
+        prompts,
+    }
+}
+
+/// Helper function to get the service of a client, failing if the connector is offline
+fn client_service(client: &McpClient) -> Result<&RunningService<RoleClient, ()>, OperationError> {
+    client.service.as_ref().ok_or_else(|| {
+        OperationError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            OperationErrorClass::Unavailable,
+            "The connector is running in offline mode".to_string(),
+        )
+    })
+}
+
+/// Build the row set of a resource collection query, keeping the page of rows matching the
+/// predicate in the requested order and computing the requested aggregates over them
+fn resource_rowset(
+    query: &models::Query,
+    rows: Vec<IndexMap<models::FieldName, models::RowFieldValue>>,
+) -> Result<models::RowSet, ErrorResponse> {
+    let bad_request =
+        |message| ErrorResponse::new(StatusCode::BAD_REQUEST, message, serde_json::Value::Null);
+
+    let mut matching_rows = Vec::new();
+    for row in rows {
+        let matches = match &query.predicate {
+            Some(predicate) => evaluate_predicate(predicate, &row).map_err(bad_request)?,
+            None => true,
+        };
+        if matches {
+            matching_rows.push(row);
+        }
+    }
+    if let Some(order_by) = &query.order_by {
+        matching_rows = order_rows(order_by, matching_rows).map_err(bad_request)?;
+    }
+    let matching_rows = paginate_rows(query.offset, query.limit, matching_rows);
+
+    let aggregates = query
+        .aggregates
+        .as_ref()
+        .map(|aggregates| aggregate_rows(aggregates, &matching_rows))
+        .transpose()
+        .map_err(bad_request)?;
+
+    Ok(models::RowSet {
+        rows: query.fields.as_ref().map(|_| matching_rows),
+        aggregates,
+        groups: None,
+    })
+}
+
+/// Build the row set of a function query, whose result is a single `__value` row. Aggregates
+/// over that row would only ever count one value, so they are rejected.
+fn function_rowset(query: &models::Query, value: Value) -> Result<models::RowSet, ErrorResponse> {
+    if query
+        .aggregates
+        .as_ref()
+        .is_some_and(|aggregates| !aggregates.is_empty())
+    {
+        return Err(ErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "Aggregates are only supported on collections; function results are a single value"
+                .to_string(),
+            serde_json::Value::Null,
+        ));
+    }
+    let mut row = IndexMap::new();
+    row.insert("__value".into(), models::RowFieldValue(value));
+    Ok(models::RowSet {
+        rows: Some(vec![row]),
+        aggregates: None,
+        groups: None,
+    })
+}
+
+/// The rows of the tool catalog collection of a server, in order of tool name
+fn tool_catalog_rows(
+    configuration: &ConnectorConfig,
+    state: &ConnectorState,
+    server_name: &McpServerName,
+    client: &McpClient,
+) -> Vec<IndexMap<models::FieldName, models::RowFieldValue>> {
+    let mut tools = client.tools.iter().collect::<Vec<_>>();
+    tools.sort_by(|a, b| a.0.cmp(b.0));
+    tools
+        .into_iter()
+        .map(|(tool_id, tool)| {
+            let read_only = is_read_only_tool(server_name, tool, configuration);
+            // The name of the function if the tool is one, else of the procedure
+            let ndc_name = if is_function_tool(server_name, tool, configuration) {
+                Some(state.ndc_name(server_name, McpItemKind::Tool, tool_id))
+            } else if !configuration.read_only {
+                Some(state.ndc_name(
+                    server_name,
+                    tool_procedure_kind(server_name, configuration),
+                    tool_id,
+                ))
+            } else {
+                None
+            };
+            let fields = [
+                ("name", Value::String(tool.name.to_string())),
+                (
+                    "ndc_name",
+                    ndc_name.map(Value::String).unwrap_or(Value::Null),
+                ),
+                (
+                    "title",
+                    tool_title(tool)
+                        .map(|title| Value::String(title.to_string()))
+                        .unwrap_or(Value::Null),
+                ),
+                (
+                    "description",
+                    tool.description
+                        .as_ref()
+                        .map(|description| Value::String(description.to_string()))
+                        .unwrap_or(Value::Null),
+                ),
+                ("read_only", Value::Bool(read_only)),
+                (
+                    "input_schema",
+                    Value::Object(tool.input_schema.as_ref().clone()),
+                ),
+                (
+                    "output_schema",
+                    tool.output_schema
+                        .as_ref()
+                        .map(|output_schema| Value::Object(output_schema.as_ref().clone()))
+                        .unwrap_or(Value::Null),
+                ),
+                (
+                    "routing_hints",
+                    configuration
+                        .tool_config(server_name, &tool.name)
+                        .and_then(|tool_config| tool_config.routing_hints.as_ref())
+                        .and_then(|routing_hints| serde_json::to_value(routing_hints).ok())
+                        .unwrap_or(Value::Null),
+                ),
+            ];
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    (
+                        models::FieldName::new(name.into()),
+                        models::RowFieldValue(value),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Resolve the arguments of a query, taking the values of variable arguments from a set of
+/// variables
+fn resolve_arguments(
+    arguments: &BTreeMap<models::ArgumentName, models::Argument>,
+    variables: &BTreeMap<models::VariableName, Value>,
+) -> Result<BTreeMap<models::ArgumentName, Value>, ErrorResponse> {
+    arguments
+        .iter()
+        .map(|(argument_name, argument)| {
+            let value = match argument {
+                models::Argument::Literal { value } => value.clone(),
+                models::Argument::Variable { name } => {
+                    variables.get(name).cloned().ok_or_else(|| {
+                        ErrorResponse::new(
+                            StatusCode::BAD_REQUEST,
+                            format!("Missing variable: {}", name),
+                            serde_json::Value::Null,
+                        )
+                    })?
+                }
+            };
+            Ok((argument_name.clone(), value))
+        })
+        .collect()
+}
+
+/// Query a collection or function with resolved arguments
+async fn query_rowset(
+    configuration: &ConnectorConfig,
+    state: &Arc<ConnectorState>,
+    collection: &models::CollectionName,
+    query: &models::Query,
+    arguments: BTreeMap<models::ArgumentName, Value>,
+) -> Result<models::RowSet, ErrorResponse> {
+    // Parse the collection or function name to extract server_name and resource/tool name
+    let name = collection.to_string();
+    if configuration.diagnostics && name == DIAGNOSTICS_FUNCTION_NAME {
+        return function_rowset(query, diagnostics(state));
+    }
+    if name == MCP_SERVERS_COLLECTION_NAME {
+        let rows = mcp_server_rows(configuration, state);
+        let rowset = resource_rowset(query, rows)?;
+        return Ok(rowset);
+    }
+
+    // Find the client for this server
+    let (server_name, client, kind, resource_or_tool_name) =
+        state.resolve_name(&name).ok_or_else(|| {
+            ErrorResponse::new(
+                StatusCode::NOT_FOUND,
+                format!("Collection or function not found: {}", name),
+                serde_json::Value::Null,
+            )
+        })?;
+
+    // Check if this is a resource (collection) or a tool (function)
+    if let Some(resource) = client
+        .resources
+        .get(resource_or_tool_name)
+        .filter(|_| kind == McpItemKind::Resource)
+    {
+        // This is a resource (collection)
+        let row = read_resource_row(
+            configuration,
+            state,
+            server_name,
+            client,
+            resource.raw.clone(),
+            resource.annotations.as_ref(),
+        )
+        .await?;
+
+        // Resources configured with rows are datasets, with a row per record of their content
+        if let Some(rows_config) = configuration.resource_rows(server_name, &resource.raw.name) {
+            let text = row
+                .get(&models::FieldName::new("text".into()))
+                .and_then(|text| text.0.as_str())
+                .unwrap_or_default();
+            let rows = parse_rows(rows_config, text).map_err(|message| {
+                ErrorResponse::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!(
+                        "Failed to parse the rows of resource {}: {}",
+                        resource.raw.name, message
+                    ),
+                    serde_json::Value::Null,
+                )
+            })?;
+            return resource_rowset(query, rows);
+        }
+        let rowset = resource_rowset(query, vec![row])?;
+
+        // Return response with the row, if it matches the predicate
+        Ok(rowset)
+    } else if let Some(template) = client
+        .resource_templates
+        .get(resource_or_tool_name)
+        .filter(|_| kind == McpItemKind::ResourceTemplate)
+    {
+        // This is a resource template (collection with arguments)
+        let mut values = HashMap::new();
+        for (argument_name, value) in &arguments {
+            let value = match value {
+                Value::Null => continue,
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            values.insert(argument_name.to_string(), value);
+        }
+        if let Some(variable) = template_variables(&template.raw.uri_template)
+            .into_iter()
+            .find(|variable| !variable.optional && !values.contains_key(&variable.name))
+        {
+            return Err(ErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                format!("Missing argument: {}", variable.name),
+                serde_json::Value::Null,
+            ));
+        }
+
+        let uri = expand_uri_template(&template.raw.uri_template, &values);
+        let mut resource = RawResource::new(uri, template.raw.name.clone());
+        resource.title = template.raw.title.clone();
+        resource.description = template.raw.description.clone();
+        resource.mime_type = template.raw.mime_type.clone();
+        let row = read_resource_row(
+            configuration,
+            state,
+            server_name,
+            client,
+            resource,
+            template.annotations.as_ref(),
+        )
+        .await?;
+        let rowset = resource_rowset(query, vec![row])?;
+
+        Ok(rowset)
+    } else if let Some(tool) = client
+        .tools
+        .get(resource_or_tool_name)
+        .filter(|_| kind == McpItemKind::Tool)
+    {
+        // Tools that are only exposed as procedures must not be queried
+        if !is_function_tool(server_name, tool, configuration) {
+            return Err(ErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Tool {} is not exposed as a function, use a mutation to call it",
+                    resource_or_tool_name
+                ),
+                serde_json::Value::Null,
+            ));
+        }
+
+        // Extract input from arguments if provided
+        let mut arguments_map = serde_json::Map::new();
+        for (argument_name, value) in arguments {
+            arguments_map.insert(argument_name.to_string(), value);
+        }
+        let pagination = configuration
+            .tool_config(server_name, &tool.name)
+            .and_then(|tool_config| tool_config.pagination.as_ref())
+            .map(|pagination| {
+                PageRequest::take(&mut arguments_map, pagination)
+                    .map(|page_request| (pagination, page_request))
+            })
+            .transpose()
+            .map_err(|message| {
+                ErrorResponse::new(StatusCode::BAD_REQUEST, message, serde_json::Value::Null)
+            })?;
+        decode_string_arguments(tool, &mut arguments_map);
+        apply_default_arguments(tool, &mut arguments_map);
+        apply_forwarded_headers(configuration, tool, &mut arguments_map);
+        decrypt_sealed_arguments(configuration, state, &mut arguments_map).await?;
+        check_binary_arguments(
+            tool,
+            &arguments_map,
+            configuration.max_binary_argument_bytes,
+        )
+        .map_err(|message| {
+            ErrorResponse::new(StatusCode::BAD_REQUEST, message, serde_json::Value::Null)
+        })?;
+        if configuration.validate_arguments {
+            check_argument_constraints(tool, &arguments_map).map_err(|message| {
+                ErrorResponse::new(StatusCode::BAD_REQUEST, message, serde_json::Value::Null)
+            })?;
+        }
+
+        // Execute the tool
+        let call_request = rmcp::model::CallToolRequestParam {
+            name: tool.name.clone(),
+            arguments: if arguments_map.is_empty() {
+                None
+            } else {
+                Some(arguments_map)
+            },
+        };
+
+        let mut result = match pagination {
+            Some((pagination, page_request)) => {
+                call_tool_page(
+                    configuration,
+                    state,
+                    server_name,
+                    client,
+                    call_request,
+                    pagination,
+                    page_request,
+                )
+                .await?
+            }
+            None => {
+                call_read_only_tool(configuration, state, server_name, client, call_request).await?
+            }
+        };
+        let rows_tool = is_rows_tool(server_name, tool, configuration);
+        if let Some(error) = tool_error(&result).filter(|_| rows_tool) {
+            // Rows can't carry the error of the tool
+            return Err(OperationError::from(error).into());
+        }
+        let tool_error = take_tool_error(configuration, &mut result)?;
+
+        // Infer an output schema for tools that don't declare one
+        if let (None, Some(structured_content)) = (&tool.output_schema, &result.structured_content)
+        {
+            state
+                .output_schema_inference
+                .observe(server_name, &tool.name, structured_content);
+        }
+
+        let contents = result
+            .content
+            .into_iter()
+            .map(|content| content.raw)
+            .collect::<Vec<_>>();
+
+        let structured_content =
+            structured_content_value(configuration, tool, result.structured_content, &contents);
+        if rows_tool {
+            let rows = tool_result_rows(tool, structured_content);
+            return resource_rowset(query, rows);
+        }
+
+        let output = tool_output(configuration, &contents, structured_content, tool_error);
+
+        // Return response with a single row
+        function_rowset(query, output)
+    } else if let Some(prompt) = client
+        .prompts
+        .get(resource_or_tool_name)
+        .filter(|_| kind == McpItemKind::Prompt)
+    {
+        // This is a prompt (function)
+        let mut arguments_map = serde_json::Map::new();
+        for (argument_name, value) in arguments {
+            if !value.is_null() {
+                arguments_map.insert(argument_name.to_string(), value);
+            }
+        }
+
+        let get_prompt_request = rmcp::model::GetPromptRequestParam {
+            name: prompt.name.clone(),
+            arguments: if arguments_map.is_empty() {
+                None
+            } else {
+                Some(arguments_map)
+            },
+        };
+
+        let failed_call_request = get_prompt_request
+            .arguments
+            .as_ref()
+            .map(|arguments| redact_arguments(configuration, arguments))
+            .unwrap_or(Value::Null);
+        let started_at = Instant::now();
+        let service = available_service(state, server_name, client)?;
+        let timeout = configuration.call_timeout(server_name, None);
+        let result = call_with_retries(state, server_name, || {
+            with_timeout(timeout, service.get_prompt(get_prompt_request.clone()))
+        })
+        .await;
+        state.record_outcome(server_name, &result);
+        let result = result.map_err(|e| {
+            state.failed_calls.record(FailedCall::new(
+                server_name,
+                McpItemKind::Prompt,
+                &prompt.name,
+                failed_call_request,
+                Value::String(e.to_string()),
+                started_at.elapsed(),
+            ));
+            ErrorResponse::from(OperationError::from_service_error(
+                "Failed to get prompt",
+                &e,
+            ))
+        })?;
+
+        function_rowset(query, prompt_message_values(&result.messages))
+    } else if kind == McpItemKind::ToolCatalog {
+        // List the tools from the introspected state
+        let rows = tool_catalog_rows(configuration, state, server_name, client);
+        let rowset = resource_rowset(query, rows)?;
+        Ok(rowset)
+    } else {
+        Err(ErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            format!(
+                "Resource, tool or prompt not found: {}",
+                resource_or_tool_name
+            ),
+            serde_json::Value::Null,
+        ))
+    }
+}
+
+/// Get the service of a client, failing fast while the server is degraded
+fn available_service<'a>(
+    state: &ConnectorState,
+    server_name: &McpServerName,
+    client: &'a McpClient,
+) -> Result<&'a RunningService<RoleClient, ()>, OperationError> {
+    if let Err(retry_in) = state.server_health.admit(server_name) {
+        return Err(OperationError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            OperationErrorClass::Unavailable,
+            format!(
+                "Server {} is degraded after repeated failures; retrying in {}s",
+                server_name.0,
+                retry_in.as_secs().max(1)
+            ),
+        ));
+    }
+    client_service(client)
+}
+
+/// Whether failed calls of a procedure's tool may be retried: tools annotated read-only or
+/// idempotent, unless configured otherwise
+fn is_retryable_tool(
+    configuration: &ConnectorConfig,
+    server_name: &McpServerName,
+    tool: &Tool,
+) -> bool {
+    configuration
+        .tool_config(server_name, &tool.name)
+        .and_then(|tool_config| tool_config.retry)
+        .unwrap_or_else(|| {
+            tool.annotations.as_ref().is_some_and(|annotations| {
+                annotations.read_only_hint == Some(true)
+                    || annotations.idempotent_hint == Some(true)
+            })
+        })
+}
+
+/// Make a call to a server, retrying transient failures while its retry budget allows
+async fn call_with_retries<T, F, Fut>(
+    state: &ConnectorState,
+    server_name: &McpServerName,
+    mut call: F,
+) -> Result<T, ServiceError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ServiceError>>,
+{
+    state.retry_budget.record_call(server_name);
+    let mut retries = 0;
+    loop {
+        match call().await {
+            Err(err) => match state.retry_budget.retry_after(server_name, &err, retries) {
+                Some(delay) => {
+                    tracing::debug!(
+                        "Retrying call to MCP server {} in {:?}: {}",
+                        server_name.0,
+                        delay,
+                        err
+                    );
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                None => return Err(err),
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Fail a call to a server with a timeout error if it doesn't complete in time
+async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    call: impl Future<Output = Result<T, ServiceError>>,
+) -> Result<T, ServiceError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, call)
+            .await
+            .unwrap_or(Err(ServiceError::Timeout { timeout })),
+        None => call.await,
+    }
+}
+
+/// Resolve the `_meta` values configured for a tool
+fn tool_call_meta(
+    configuration: &ConnectorConfig,
+    server_name: &McpServerName,
+    tool_name: &str,
+) -> Result<Option<Meta>, OperationError> {
+    let Some(tool_config) = configuration.tool_config(server_name, tool_name) else {
+        return Ok(None);
+    };
+    let meta = tool_config.resolve_meta().map_err(|e| {
+        OperationError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            OperationErrorClass::Configuration,
+            format!(
+                "Failed to resolve the _meta values of tool {}: {}",
+                tool_name, e
+            ),
+        )
+    })?;
+    Ok(meta.map(Meta))
+}
+
+/// Apply the response limit of a server to a tool result or resource contents
+fn limit_server_response<R: LimitedResponse>(
+    configuration: &ConnectorConfig,
+    server_name: &McpServerName,
+    result: R,
+) -> Result<R, OperationError> {
+    let Some(limit) = configuration
+        .servers
+        .get(server_name)
+        .and_then(|server_config| server_config.options().response_limit.as_ref())
+    else {
+        return Ok(result);
+    };
+    limit_response(limit, result).map_err(|message| {
+        OperationError::new(
+            StatusCode::BAD_GATEWAY,
+            OperationErrorClass::ResponseTooLarge,
+            message,
+        )
+    })
+}
+
+/// Call a tool, sending the given values in the `_meta` field of the request. The JSON-RPC
+/// id of the request is stored in `request_id` once it is sent, so failures can be
+/// correlated with the server logs.
+pub(crate) async fn call_tool_with_meta(
+    service: &RunningService<RoleClient, ()>,
+    call_request: CallToolRequestParam,
+    meta: Option<Meta>,
+    request_id: &Mutex<Option<RequestId>>,
+) -> Result<CallToolResult, ServiceError> {
+    let mut request = CallToolRequest::new(call_request);
+    if let Some(meta) = meta {
+        request.extensions.insert(meta);
+    }
+    let handle = service
+        .send_request_with_option(
+            ClientRequest::CallToolRequest(request),
+            PeerRequestOptions::no_options(),
+        )
+        .await?;
+    *request_id.lock().unwrap() = Some(handle.id.clone());
+    match handle.await_response().await? {
+        ServerResult::CallToolResult(result) => Ok(result),
+        _ => Err(ServiceError::UnexpectedResponse),
+    }
+}
+
+/// Call a tool, recording the call in the failed call log if it fails or returns an error.
+/// Tools configured with `chunked_input` are called once per chunk of an oversized argument,
+/// stopping at the first error. Retries of a chunked call resume from the chunk that failed,
+/// with the chunks completed so far kept in `chunk_progress`.
+#[allow(clippy::too_many_arguments)]
+async fn call_tool_recorded(
+    configuration: &ConnectorConfig,
+    state: &ConnectorState,
+    server_name: &McpServerName,
+    service: &RunningService<RoleClient, ()>,
+    call_request: CallToolRequestParam,
+    meta: Option<Meta>,
+    request_id: &Mutex<Option<RequestId>>,
+    chunk_progress: &Mutex<ChunkProgress>,
+) -> Result<CallToolResult, ServiceError> {
+    let chunked_calls = configuration
+        .tool_config(server_name, &call_request.name)
+        .and_then(|tool_config| tool_config.chunked_input.as_ref())
+        .and_then(|chunked_input| chunked_calls(chunked_input, &call_request));
+    let Some(chunked_calls) = chunked_calls else {
+        return call_tool_once(
+            configuration,
+            state,
+            server_name,
+            service,
+            call_request,
+            meta,
+            request_id,
+        )
+        .await;
+    };
+
+    call_chunks(chunked_calls, chunk_progress, |chunk_request| {
+        call_tool_once(
+            configuration,
+            state,
+            server_name,
+            service,
+            chunk_request,
+            meta.clone(),
+            request_id,
+        )
+    })
+    .await?
+    .ok_or(ServiceError::UnexpectedResponse)
+}
+
+/// Make a single call to a tool, recording it in the failed call log if it fails or returns an
+/// error
+async fn call_tool_once(
+    configuration: &ConnectorConfig,
+    state: &ConnectorState,
+    server_name: &McpServerName,
+    service: &RunningService<RoleClient, ()>,
+    call_request: CallToolRequestParam,
+    meta: Option<Meta>,
+    request_id: &Mutex<Option<RequestId>>,
+) -> Result<CallToolResult, ServiceError> {
+    let tool_name = call_request.name.to_string();
+    let request = call_request
+        .arguments
+        .as_ref()
+        .map(|arguments| redact_arguments(configuration, arguments))
+        .unwrap_or(Value::Null);
+    let sampled = configuration
+        .servers
+        .get(server_name)
+        .and_then(|server_config| server_config.options().log_sample_rate)
+        .is_some_and(|rate| state.call_sampler.sample(server_name, rate));
+    let started_at = Instant::now();
+    let timeout = configuration.call_timeout(server_name, Some(tool_name.as_str()));
+    let in_flight_call = state.sessions.start_call(server_name);
+    let result = with_timeout(
+        timeout,
+        call_tool_with_meta(service, call_request, meta, request_id),
+    )
+    .await;
+    drop(in_flight_call);
+    if sampled {
+        let response = match &result {
+            Ok(result) => serde_json::to_value(result).unwrap_or(Value::Null),
+            Err(err) => Value::String(err.to_string()),
+        };
+        tracing::info!(
+            server = %server_name.0,
+            tool = %tool_name,
+            arguments = %request,
+            response = %response,
+            duration_ms = started_at.elapsed().as_millis() as u64,
+            "Sampled tool call"
+        );
+    }
+    let error = match &result {
+        Ok(result) if result.is_error == Some(true) => {
+            serde_json::to_value(result).unwrap_or(Value::Null)
+        }
+        Ok(_) => return result,
+        Err(err) => Value::String(err.to_string()),
+    };
+    let mut failed_call = FailedCall::new(
+        server_name,
+        McpItemKind::Tool,
+        &tool_name,
+        request,
+        error,
+        started_at.elapsed(),
+    );
+    failed_call.request_id = request_id
+        .lock()
+        .unwrap()
+        .clone()
+        .map(RequestId::into_json_value);
+    state.failed_calls.record(failed_call);
+    result
+}
+
+/// Call a read-only tool, serving the response from the cache when its cache-control
+/// directives allow it. Stale responses are served while being refreshed in the background.
+async fn call_read_only_tool(
+    configuration: &ConnectorConfig,
+    state: &Arc<ConnectorState>,
+    server_name: &McpServerName,
+    client: &McpClient,
+    call_request: CallToolRequestParam,
+) -> Result<CallToolResult, ErrorResponse> {
+    let configured_cache_control = configuration
+        .tool_config(server_name, &call_request.name)
+        .and_then(|tool_config| tool_config.cache_control.clone());
+    let key = cache_key(server_name, &call_request);
+    let meta = tool_call_meta(configuration, server_name, &call_request.name)?;
+
+    let caching = configuration.feature_enabled(Feature::ResponseCache);
+    let lookup = if caching {
+        state.response_cache.lookup(&key).await
+    } else {
+        CacheLookup::Miss
+    };
+    match lookup {
+        CacheLookup::Fresh(result) => return Ok(result),
+        CacheLookup::Stale(result) => {
+            let state = state.clone();
+            let server_name = server_name.clone();
+            let timeout =
+                configuration.call_timeout(&server_name, Some(call_request.name.as_ref()));
+            let response_limit = configuration
+                .servers
+                .get(&server_name)
+                .and_then(|server_config| server_config.options().response_limit.as_ref())
+                .cloned();
+            tokio::spawn(async move {
+                let service = state
+                    .clients
+                    .get(&server_name)
+                    .and_then(|client| client.service.as_ref())
+                    .filter(|_| state.server_health.admit(&server_name).is_ok());
+                let Some(service) = service else {
+                    // A later lookup refreshes the entry once the server is available
+                    state.response_cache.release_refresh(&key).await;
+                    return;
+                };
+                let result = with_timeout(
+                    timeout,
+                    call_tool_with_meta(service, call_request, meta, &Mutex::default()),
+                )
+                .await;
+                state.record_outcome(&server_name, &result);
+                let result =
+                    result.map_err(|err| err.to_string()).and_then(
+                        |result| match &response_limit {
+                            Some(limit) => limit_response(limit, result),
+                            None => Ok(result),
+                        },
+                    );
+                match result {
+                    Ok(result) => {
+                        let cache_control = configured_cache_control
+                            .or_else(|| cache_control_from_result(&result))
+                            .unwrap_or_default();
+                        state
+                            .response_cache
+                            .store(key, &result, cache_control)
+                            .await;
+                    }
+                    Err(err) => {
+                        tracing::warn!("Failed to refresh cached tool response: {}", err);
+                        state.response_cache.release_refresh(&key).await;
+                    }
+                }
+            });
+            return Ok(result);
+        }
+        CacheLookup::Miss => {}
+    }
+
+    let call = async {
+        let service = available_service(state, server_name, client)?;
+        let request_id = Mutex::default();
+        let chunk_progress = Mutex::default();
+        let call_tool = || {
+            call_tool_recorded(
+                configuration,
+                state,
+                server_name,
+                service,
+                call_request.clone(),
+                meta.clone(),
+                &request_id,
+                &chunk_progress,
+            )
+        };
+        let retry = configuration
+            .tool_config(server_name, &call_request.name)
+            .and_then(|tool_config| tool_config.retry)
+            .unwrap_or(true);
+        let result = if retry {
+            call_with_retries(state, server_name, call_tool).await
+        } else {
+            call_tool().await
+        };
+        state.record_outcome(server_name, &result);
+        let result = result.map_err(|e| {
+            OperationError::from_service_error("Failed to execute tool", &e)
+                .with_request_id(request_id.into_inner().unwrap())
+        })?;
+        limit_server_response(configuration, server_name, result)
+    };
+    let result = if configuration.feature_enabled(Feature::RequestCoalescing) {
+        state.in_flight_calls.call(key.clone(), None, call).await?
+    } else {
+        call.await?
+    };
+
+    if caching {
+        let cache_control = configured_cache_control
+            .or_else(|| cache_control_from_result(&result))
+            .unwrap_or_default();
+        state
+            .response_cache
+            .store(key, &result, cache_control)
+            .await;
+    }
+    Ok(result)
+}
+
+/// Decrypt sealed argument values before they are forwarded to the MCP server
+async fn decrypt_sealed_arguments(
+    configuration: &ConnectorConfig,
+    state: &ConnectorState,
+    arguments_map: &mut serde_json::Map<String, Value>,
+) -> Result<(), OperationError> {
+    let Some(sealed_arguments) = &configuration.sealed_arguments else {
+        return Ok(());
+    };
+    for argument_name in &sealed_arguments.arguments {
+        let Some(Value::String(sealed_value)) = arguments_map.get(argument_name) else {
+            continue;
+        };
+        // Sealed values are never forwarded as they are
+        let decryptor = state.decryptor.as_ref().ok_or_else(|| {
+            OperationError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                OperationErrorClass::Configuration,
+                format!(
+                    "No decryptor configured for sealed argument {}",
+                    argument_name
+                ),
+            )
+        })?;
+        let plaintext = decryptor.decrypt(sealed_value).await.map_err(|e| {
+            OperationError::new(
+                StatusCode::BAD_REQUEST,
+                OperationErrorClass::InvalidArguments,
+                format!("Failed to decrypt argument {}: {}", argument_name, e),
+            )
+        })?;
+        arguments_map.insert(argument_name.clone(), Value::String(plaintext));
+    }
+    Ok(())
+}
+
+/// The JSON type a property schema expects. Nullable types such as `["object", "null"]`
+/// expect their non-null type, and open-ended maps declaring only `properties` or
+/// `additionalProperties` expect an object.
+fn expected_json_type(property: &Value) -> Option<&str> {
+    match property.get("type") {
+        Some(Value::String(expected_type)) => Some(expected_type),
+        Some(Value::Array(types)) => {
+            let mut non_null_types = types
+                .iter()
+                .filter_map(|expected_type| expected_type.as_str())
+                .filter(|expected_type| *expected_type != "null");
+            match (non_null_types.next(), non_null_types.next()) {
+                (Some(expected_type), None) => Some(expected_type),
+                _ => None,
+            }
+        }
+        None if property.get("properties").is_some()
+            || property.get("additionalProperties").is_some() =>
+        {
+            Some("object")
+        }
+        _ => None,
+    }
+}
+
+/// Decode arguments sent as strings for a schema type that isn't a string. Object and
+/// array arguments used to be exposed as `String` rather than `JSON`, so clients may still
+/// send them encoded, and 64-bit integers (`BigInt` and `BigInteger`) are sent as strings by
+/// the engine.
+fn decode_string_arguments(tool: &Tool, arguments_map: &mut serde_json::Map<String, Value>) {
+    let Some(properties) = tool
+        .input_schema
+        .get("properties")
+        .and_then(|properties| properties.as_object())
+    else {
+        return;
+    };
+    for (argument_name, value) in arguments_map.iter_mut() {
+        let expected_type = properties.get(argument_name).and_then(expected_json_type);
+        let decoded = match (expected_type, &*value) {
+            (Some("object" | "array"), Value::String(encoded)) => {
+                serde_json::from_str::<Value>(encoded)
+                    .ok()
+                    .filter(|decoded| decoded.is_object() || decoded.is_array())
+            }
+            (Some("integer"), Value::String(encoded)) => encoded
+                .parse::<i64>()
+                .map(Value::from)
+                .or_else(|_| encoded.parse::<u64>().map(Value::from))
+                .ok(),
+            _ => None,
+        };
+        if let Some(decoded) = decoded {
+            *value = decoded;
+        }
+    }
+}
+
+/// Execute a procedure by calling its tool, returning the procedure result
+async fn execute_procedure(
+    configuration: &ConnectorConfig,
+    state: &ConnectorState,
+    name: &str,
+    arguments: BTreeMap<models::ArgumentName, Value>,
+) -> Result<Value, OperationError> {
+    // Resolve the procedure name to the server and tool name
+    let (server_name, client, kind, tool_name) = state
+        .resolve_name(name)
+        .ok_or_else(|| OperationError::not_found(format!("Procedure not found: {}", name)))?;
+
+    // Check if the tool exists
+    let tool = client
+        .tools
+        .get(tool_name)
+        .filter(|_| kind == tool_procedure_kind(server_name, configuration))
+        .ok_or_else(|| OperationError::not_found(format!("Tool not found: {}", tool_name)))?;
+
+    // Extract input from arguments if provided
+    let mut arguments_map = serde_json::Map::new();
+    for (argument_name, value) in arguments {
+        arguments_map.insert(argument_name.to_string(), value);
+    }
+    decode_string_arguments(tool, &mut arguments_map);
+    apply_default_arguments(tool, &mut arguments_map);
+    apply_forwarded_headers(configuration, tool, &mut arguments_map);
+    decrypt_sealed_arguments(configuration, state, &mut arguments_map).await?;
+    check_binary_arguments(
+        tool,
+        &arguments_map,
+        configuration.max_binary_argument_bytes,
+    )
+    .map_err(|message| {
+        OperationError::new(
+            StatusCode::BAD_REQUEST,
+            OperationErrorClass::InvalidArguments,
+            message,
+        )
+    })?;
+    if configuration.validate_arguments {
+        check_argument_constraints(tool, &arguments_map).map_err(|message| {
+            OperationError::new(
+                StatusCode::BAD_REQUEST,
+                OperationErrorClass::InvalidArguments,
+                message,
+            )
+        })?;
+    }
+
+    // Execute the tool
+    let call_request = rmcp::model::CallToolRequestParam {
+        name: tool.name.clone(),
+        arguments: if arguments_map.is_empty() {
+            None
+        } else {
+            Some(arguments_map)
+        },
+    };
+
+    // Identical calls within the duplicate call window of the tool return the result of the
+    // first one
+    let duplicate_call_window = configuration
+        .tool_config(server_name, &tool.name)
+        .and_then(|tool_config| tool_config.duplicate_call_window_seconds);
+    let duplicate_call_key = cache_key(server_name, &call_request);
+
+    let call = async {
+        let meta = tool_call_meta(configuration, server_name, &tool.name)?;
+        let request_id = Mutex::default();
+        let service = available_service(state, server_name, client)?;
+        let chunk_progress = Mutex::default();
+        let call_tool = || {
+            call_tool_recorded(
+                configuration,
+                state,
+                server_name,
+                service,
+                call_request.clone(),
+                meta.clone(),
+                &request_id,
+                &chunk_progress,
+            )
+        };
+        // Procedures are only retried when calling them again is safe
+        let result = if is_retryable_tool(configuration, server_name, tool) {
+            call_with_retries(state, server_name, call_tool).await
+        } else {
+            call_tool().await
+        };
+        state.record_outcome(server_name, &result);
+        let request_id = request_id.into_inner().unwrap();
+        let result = result.map_err(|e| {
+            OperationError::from_service_error("Failed to execute tool", &e)
+                .with_request_id(request_id.clone())
+        })?;
+        let mut result = limit_server_response(configuration, server_name, result)
+            .map_err(|err| err.with_request_id(request_id.clone()))?;
+        let is_error = result.is_error == Some(true);
+        let tool_error = take_tool_error(configuration, &mut result)
+            .map_err(|err| err.with_request_id(request_id))?;
+
+        // Infer an output schema for tools that don't declare one
+        if let (None, Some(structured_content)) = (&tool.output_schema, &result.structured_content)
+        {
+            state
+                .output_schema_inference
+                .observe(server_name, &tool.name, structured_content);
+        }
+
+        let raw_contents = result
+            .content
+            .into_iter()
+            .map(|content| content.raw)
+            .collect::<Vec<_>>();
+
+        // Convert the result to a JSON value
+        let structured_content = structured_content_value(
+            configuration,
+            tool,
+            result.structured_content,
+            &raw_contents,
+        );
+        let output = tool_output(configuration, &raw_contents, structured_content, tool_error);
+        Ok::<_, OperationError>((output, is_error))
+    };
+    match duplicate_call_window {
+        Some(window) => {
+            // Error results fail the remembered call, so that a retry calls the tool again
+            let call = async {
+                match call.await {
+                    Ok((output, false)) => Ok(output),
+                    Ok((output, true)) => Err(Ok(output)),
+                    Err(err) => Err(Err(err)),
+                }
+            };
+            state
+                .duplicate_calls
+                .call(duplicate_call_key, Some(Duration::from_secs(window)), call)
+                .await
+                .or_else(|result| result)
+        }
+        None => call.await.map(|(output, _)| output),
+    }
+}
+
+/// Call a paginated tool for a page, following the cursors from the closest page whose cursor
+/// the pagination session remembers
+async fn call_tool_page(
+    configuration: &ConnectorConfig,
+    state: &Arc<ConnectorState>,
+    server_name: &McpServerName,
+    client: &McpClient,
+    call_request: CallToolRequestParam,
+    pagination: &PaginationConfig,
+    page_request: PageRequest,
+) -> Result<CallToolResult, ErrorResponse> {
+    let key = page_request
+        .session
+        .as_deref()
+        .map(|session| session_key(server_name, &call_request, session));
+    let call_page = |cursor: Option<String>| {
+        let mut page_call_request = call_request.clone();
+        if let Some(cursor) = cursor {
+            page_call_request
+                .arguments
+                .get_or_insert_with(Default::default)
+                .insert(pagination.cursor_argument.clone(), Value::String(cursor));
+        }
+        call_read_only_tool(configuration, state, server_name, client, page_call_request)
+    };
+    follow_pages(
+        &state.pagination,
+        key.as_deref(),
+        page_request.page,
+        call_page,
+    )
+    .await
+    .map_err(|err| match err {
+        PageError::Call(err) => err,
+        PageError::PastLastPage { last_page } => ErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Page {} is past the last page {} of {}",
+                page_request.page, last_page, call_request.name
+            ),
+            serde_json::Value::Null,
+        ),
+        PageError::RepeatedCursor { page } => ErrorResponse::new(
+            StatusCode::BAD_GATEWAY,
+            format!(
+                "Tool {} returned the cursor of an earlier page for page {}",
+                call_request.name, page
+            ),
+            serde_json::Value::Null,
+        ),
+    })
+}
+
+/// The rows of the structured content of a tool returning its results as rows, with a column
+/// per property of the rows in its output schema
+fn tool_result_rows(
+    tool: &Tool,
+    structured_content: Value,
+) -> Vec<IndexMap<models::FieldName, models::RowFieldValue>> {
+    let Some((rows_property, row_schema)) = tool_rows_schema(tool) else {
+        return Vec::new();
+    };
+    let columns = row_schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| properties.keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let elements = match rows_property {
+        Some(property) => structured_content.get(&property).cloned(),
+        None => Some(structured_content),
+    };
+    let Some(Value::Array(elements)) = elements else {
+        return Vec::new();
+    };
+    elements
+        .into_iter()
+        .map(|element| {
+            columns
+                .iter()
+                .map(|column| {
+                    let value = element.get(column).cloned().unwrap_or(Value::Null);
+                    (
+                        models::FieldName::new(column.as_str().into()),
+                        models::RowFieldValue(value),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Take the error of a tool error result when `tool_errors` is configured, leaving the result
+/// without content. Fails with the error in `response` mode.
+fn take_tool_error(
+    configuration: &ConnectorConfig,
+    result: &mut CallToolResult,
+) -> Result<Option<ToolError>, OperationError> {
+    let Some(mode) = configuration.tool_errors else {
+        return Ok(None);
+    };
+    let Some(error) = tool_error(result) else {
+        return Ok(None);
+    };
+    match mode {
+        ToolErrors::Field => {
+            result.content.clear();
+            result.structured_content = None;
+            Ok(Some(error))
+        }
+        ToolErrors::Response => Err(error.into()),
+    }
+}
+
+/// The output of a tool call, with the same fields for function and procedure results
+fn tool_output(
+    configuration: &ConnectorConfig,
+    content: &[RawContent],
+    structured_content: Value,
+    tool_error: Option<ToolError>,
+) -> Value {
+    let mut output = serde_json::json!({
+        "content": content_values(content),
+        "structured_content": structured_content
+    });
+    insert_text_field(configuration, &mut output, content);
+    insert_tool_error_field(configuration, &mut output, tool_error);
+    output
+}
+
+/// Add the `tool_error` field to a tool output, if configured
+fn insert_tool_error_field(
+    configuration: &ConnectorConfig,
+    output: &mut Value,
+    tool_error: Option<ToolError>,
+) {
+    if configuration.tool_errors == Some(ToolErrors::Field) {
+        output["tool_error"] = serde_json::to_value(tool_error).unwrap_or(Value::Null);
+    }
+}
+
+/// The content items of a tool result as `Content` objects
+fn content_values(content: &[RawContent]) -> Value {
+    content
+        .iter()
+        .map(|content| content_object(serde_json::to_value(content).unwrap_or(Value::Null)))
+        .collect()
+}
+
+/// The messages of a prompt, with their content as `Content` objects like the content of tool
+/// results
+fn prompt_message_values(messages: &[PromptMessage]) -> Value {
+    messages
+        .iter()
+        .map(|message| {
+            let mut content = serde_json::to_value(&message.content).unwrap_or(Value::Null);
+            if let Some(content) = content.as_object_mut() {
+                // Embedded resources of messages are annotated, nesting the resource once more
+                if content.get("type").and_then(Value::as_str) == Some("resource") {
+                    if let Some(Value::Object(embedded)) = content.remove("resource") {
+                        content.extend(embedded);
+                    }
+                }
+                content.remove("annotations");
+            }
+            serde_json::json!({ "role": message.role, "content": content_object(content) })
+        })
+        .collect()
+}
+
+/// A serialized content item as a `Content` object. Embedded resources are flattened, with
+/// their blob in `data` like the data of images and audio.
+fn content_object(mut value: Value) -> Value {
+    let resource = value
+        .as_object_mut()
+        .and_then(|object| object.remove("resource"));
+    if let Some(Value::Object(resource)) = resource {
+        for (key, field) in resource {
+            let key = if key == "blob" {
+                "data".to_string()
+            } else {
+                key
+            };
+            value[key.as_str()] = field;
+        }
+    }
+    value
+}
+
+/// Add the `text` field with the text content to a tool output, if configured
+fn insert_text_field(configuration: &ConnectorConfig, output: &mut Value, content: &[RawContent]) {
+    let Some(text_field) = configuration.text_field else {
+        return;
+    };
+    let text = text_field.text(
+        content
+            .iter()
+            .filter_map(RawContent::as_text)
+            .map(|text| text.text.as_str()),
+    );
+    output["text"] = text.map(Value::String).unwrap_or(Value::Null);
+}
+
+/// The `structured_content` field of a tool result. It is a JSON string for tools without an
+/// output schema when `stringify_structured_content` is set. For tools with an output
+/// schema, it is projected onto the schema, and read from JSON text content if the tool
+/// returned no structured content, as tools with a configured schema usually don't.
+fn structured_content_value(
+    configuration: &ConnectorConfig,
+    tool: &Tool,
+    structured_content: Option<Value>,
+    content: &[RawContent],
+) -> Value {
+    let Some(output_schema) = &tool.output_schema else {
+        return match structured_content {
+            Some(content) if configuration.stringify_structured_content => {
+                serde_json::to_string(&content)
+                    .map(Value::String)
+                    .unwrap_or(Value::Null)
+            }
+            Some(content) => content,
+            None => Value::Null,
+        };
+    };
+    structured_content
+        .or_else(|| {
+            content
+                .iter()
+                .filter_map(RawContent::as_text)
+                .find_map(|text| serde_json::from_str::<Value>(&text.text).ok())
+        })
+        .map(|content| project_structured_content(output_schema, content))
+        .unwrap_or(Value::Null)
+}
+
+/// Check if a MIME type is JSON, such as `application/json` or `application/geo+json`
+fn is_json_mime_type(mime_type: &str) -> bool {
+    let essence = mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+/// Parse the text contents of a resource with a JSON MIME type. Invalid JSON is not parsed.
+fn parse_json_contents(text: Option<&str>, mime_type: Option<&str>) -> Option<Value> {
+    text.filter(|_| mime_type.is_some_and(is_json_mime_type))
+        .and_then(|text| serde_json::from_str(text).ok())
+}
+
+/// The first text and the first base64 blob of the contents of a resource, with the first
+/// MIME type the contents declare
+#[derive(Debug, Default, PartialEq)]
+struct ResourceContentFields {
+    text: Option<String>,
+    blob: Option<String>,
+    mime_type: Option<String>,
+}
+
+impl ResourceContentFields {
+    fn from_contents(contents: &[ResourceContents]) -> Self {
+        let mut fields = Self::default();
+        for contents in contents {
+            let mime_type = match contents {
+                ResourceContents::TextResourceContents {
+                    text, mime_type, ..
+                } => {
+                    fields.text.get_or_insert_with(|| text.clone());
+                    mime_type
+                }
+                ResourceContents::BlobResourceContents {
+                    blob, mime_type, ..
+                } => {
+                    fields.blob.get_or_insert_with(|| blob.clone());
+                    mime_type
+                }
+            };
+            fields.mime_type = fields.mime_type.or_else(|| mime_type.clone());
+        }
+        fields
+    }
+}
+
+/// Read a resource and convert it to a `ResourceOutput` row, with metadata columns from the
+/// introspected resource
+async fn read_resource_row(
+    configuration: &ConnectorConfig,
+    state: &ConnectorState,
+    server_name: &McpServerName,
+    client: &McpClient,
+    resource: RawResource,
+    annotations: Option<&Annotations>,
+) -> Result<IndexMap<models::FieldName, models::RowFieldValue>, ErrorResponse> {
+    // Only pass URIs with allowed schemes to the server
+    if let Some(policy) = configuration.resource_uri_schemes.get(server_name) {
+        if !policy.allows(&resource.uri) {
+            return Err(ErrorResponse::new(
+                StatusCode::FORBIDDEN,
+                format!(
+                    "Reading resource {} is not allowed for server {}",
+                    resource.uri, server_name.0
+                ),
+                serde_json::Value::Null,
+            ));
+        }
+    }
+
+    let read_request = rmcp::model::ReadResourceRequestParam {
+        uri: resource.uri.clone(),
+    };
+
+    let started_at = Instant::now();
+    let service = available_service(state, server_name, client)?;
+    let timeout = configuration.call_timeout(server_name, None);
+    let result = call_with_retries(state, server_name, || {
+        with_timeout(timeout, service.read_resource(read_request.clone()))
+    })
+    .await;
+    state.record_outcome(server_name, &result);
+    let result = result.map_err(|e| {
+        state.failed_calls.record(FailedCall::new(
+            server_name,
+            McpItemKind::Resource,
+            &resource.name,
+            serde_json::json!({"uri": resource.uri}),
+            Value::String(e.to_string()),
+            started_at.elapsed(),
+        ));
+        ErrorResponse::from(OperationError::from_service_error(
+            "Failed to read resource",
+            &e,
+        ))
+    })?;
+    let result = limit_server_response(configuration, server_name, result)?;
+
+    // Convert content to a row
+    let content = serde_json::to_value(&result.contents).unwrap_or(Value::Null);
+
+    // Expose the first text and the first base64 blob contents as their own fields
+    let ResourceContentFields {
+        text,
+        blob,
+        mime_type,
+    } = ResourceContentFields::from_contents(&result.contents);
+
+    // Prefer the MIME type of the read contents over the introspected one
+    let mime_type = mime_type.or(resource.mime_type);
+
+    // Parse JSON text contents, so their data can be queried as nested JSON
+    let json = parse_json_contents(text.as_deref(), mime_type.as_deref());
 
-        // Create client with introspected data
-        let client = McpClient {
-            service,
-            resources,
-            tools,
-        };
+    let mut row = IndexMap::new();
+    row.insert(
+        models::FieldName::new("content".into()),
+        models::RowFieldValue(content),
+    );
+    row.insert(
+        models::FieldName::new("text".into()),
+        models::RowFieldValue(text.map(Value::String).unwrap_or(Value::Null)),
+    );
+    row.insert(
+        models::FieldName::new("blob".into()),
+        models::RowFieldValue(blob.map(Value::String).unwrap_or(Value::Null)),
+    );
+    row.insert(
+        models::FieldName::new("json".into()),
+        models::RowFieldValue(json.unwrap_or(Value::Null)),
+    );
+    row.insert(
+        models::FieldName::new("name".into()),
+        models::RowFieldValue(Value::String(resource.name)),
+    );
+    row.insert(
+        models::FieldName::new("title".into()),
+        models::RowFieldValue(resource.title.map(Value::String).unwrap_or(Value::Null)),
+    );
+    row.insert(
+        models::FieldName::new("uri".into()),
+        models::RowFieldValue(Value::String(resource.uri)),
+    );
+    row.insert(
+        models::FieldName::new("mime_type".into()),
+        models::RowFieldValue(mime_type.map(Value::String).unwrap_or(Value::Null)),
+    );
+    row.insert(
+        models::FieldName::new("description".into()),
+        models::RowFieldValue(
+            resource
+                .description
+                .map(Value::String)
+                .unwrap_or(Value::Null),
+        ),
+    );
+    row.insert(
+        models::FieldName::new("size".into()),
+        models::RowFieldValue(resource.size.map(Value::from).unwrap_or(Value::Null)),
+    );
+    row.insert(
+        models::FieldName::new("audience".into()),
+        models::RowFieldValue(
+            annotations
+                .and_then(|annotations| annotations.audience.as_ref())
+                .and_then(|audience| serde_json::to_value(audience).ok())
+                .unwrap_or(Value::Null),
+        ),
+    );
+    row.insert(
+        models::FieldName::new("priority".into()),
+        models::RowFieldValue(
+            annotations
+                .and_then(|annotations| annotations.priority)
+                .map(Value::from)
+                .unwrap_or(Value::Null),
+        ),
+    );
+    Ok(row)
+}
 
-        // Add client to state
-        connector_state.add_client(server_name.clone(), client);
+/// Fill omitted arguments with the `default` value from the tool's input schema, and omitted
+/// or null arguments with the `const` value. An explicit null is passed on rather than
+/// replaced by the default.
+fn apply_default_arguments(tool: &Tool, arguments_map: &mut serde_json::Map<String, Value>) {
+    let input_schema = resolve_schema_refs(&tool.input_schema);
+    let Some(properties) = input_schema
+        .get("properties")
+        .and_then(|properties| properties.as_object())
+    else {
+        return;
+    };
+    for (argument_name, property) in properties {
+        let (value, fills_null) = match (property.get("const"), property.get("default")) {
+            (Some(constant), _) => (constant, true),
+            (None, Some(default)) => (default, false),
+            (None, None) => continue,
+        };
+        let fill = match arguments_map.get(argument_name) {
+            None => true,
+            Some(Value::Null) => fills_null,
+            Some(_) => false,
+        };
+        if fill {
+            arguments_map.insert(argument_name.clone(), value.clone());
+        }
     }
+}
 
-    Ok(connector_state)
+/// Populate arguments mapped from forwarded headers. The headers argument and any
+/// client-supplied values for mapped arguments are discarded.
+fn apply_forwarded_headers(
+    configuration: &ConnectorConfig,
+    tool: &Tool,
+    arguments_map: &mut serde_json::Map<String, Value>,
+) {
+    let Some(forwarded_headers) = &configuration.forwarded_headers else {
+        return;
+    };
+    let headers = arguments_map.remove(&forwarded_headers.argument);
+    let properties = tool
+        .input_schema
+        .get("properties")
+        .and_then(|properties| properties.as_object());
+
+    for (header_name, argument_name) in &forwarded_headers.mappings {
+        arguments_map.remove(argument_name);
+        if !properties.is_some_and(|properties| properties.contains_key(argument_name)) {
+            continue;
+        }
+        let header_value = headers
+            .as_ref()
+            .and_then(|headers| headers.as_object())
+            .and_then(|headers| {
+                headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(header_name))
+            })
+            .map(|(_, value)| value.clone());
+        if let Some(header_value) = header_value {
+            arguments_map.insert(argument_name.clone(), header_value);
+        }
+    }
 }
 
 /// Helper function to check if an error is a method not found error
@@ -154,12 +2010,16 @@ impl Connector for McpConnector {
         models::Capabilities {
             relationships: None,
             query: models::QueryCapabilities {
-                variables: None,
-                aggregates: None,
-                explain: None,
+                variables: Some(models::LeafCapability {}),
+                aggregates: Some(models::AggregateCapabilities {
+                    filter_by: None,
+                    group_by: None,
+                }),
+                explain: Some(models::LeafCapability {}),
                 nested_fields: models::NestedFieldCapabilities {
                     filter_by: None,
-                    order_by: None,
+                    // Rows are sorted by the connector, on nested fields of object columns too
+                    order_by: Some(models::LeafCapability {}),
                     aggregates: None,
                     nested_collections: None,
                 },
@@ -172,7 +2032,7 @@ impl Connector for McpConnector {
             },
             mutation: models::MutationCapabilities {
                 transactional: None,
-                explain: None,
+                explain: Some(models::LeafCapability {}),
             },
             relational_query: None,
             relational_mutation: None,
@@ -182,269 +2042,225 @@ impl Connector for McpConnector {
     async fn get_schema(
         configuration: &Self::Configuration,
     ) -> Result<JsonResponse<models::SchemaResponse>, ErrorResponse> {
-        // Initialize temporary state to introspect MCP servers and generate schema
-        let state = initialize_mcp_clients(configuration).await?;
+        // Build the schema from the introspection stored by the update command, or
+        // initialize temporary state to introspect the MCP servers
+        let state = if configuration.schema_from_stored_introspection() {
+            initialize_offline_state(configuration)?
+        } else {
+            initialize_mcp_clients(configuration).await?
+        };
 
         // Generate schema from the introspected state
-        let schema = generate_schema_from_state(&state);
-        Ok(schema.into())
+        Ok(generate_schema_from_state(&state, configuration).into())
     }
 
     async fn query_explain(
-        _configuration: &Self::Configuration,
-        _state: &Self::State,
-        _request: models::QueryRequest,
+        configuration: &Self::Configuration,
+        state: &Self::State,
+        request: models::QueryRequest,
     ) -> Result<JsonResponse<models::ExplainResponse>, ErrorResponse> {
-        Err(ErrorResponse::new(
-            StatusCode::NOT_IMPLEMENTED,
-            "Explain not supported".to_string(),
-            serde_json::Value::Null,
-        ))
+        Ok(models::ExplainResponse {
+            details: query_explain_details(configuration, state, &request),
+        }
+        .into())
     }
 
     async fn mutation_explain(
-        _configuration: &Self::Configuration,
-        _state: &Self::State,
-        _request: models::MutationRequest,
+        configuration: &Self::Configuration,
+        state: &Self::State,
+        request: models::MutationRequest,
     ) -> Result<JsonResponse<models::ExplainResponse>, ErrorResponse> {
-        Err(ErrorResponse::new(
-            StatusCode::NOT_IMPLEMENTED,
-            "Explain not supported".to_string(),
-            serde_json::Value::Null,
-        ))
+        Ok(models::ExplainResponse {
+            details: mutation_explain_details(configuration, state, &request),
+        }
+        .into())
     }
 
     async fn query(
-        _configuration: &Self::Configuration,
+        configuration: &Self::Configuration,
         state: &Self::State,
         request: models::QueryRequest,
     ) -> Result<JsonResponse<models::QueryResponse>, ErrorResponse> {
-        // Parse the collection or function name to extract server_name and resource/tool name
-        let name = request.collection.to_string();
-        let parts: Vec<&str> = name.split("__").collect();
-
-        if parts.len() != 2 {
-            return Err(ErrorResponse::new(
-                StatusCode::BAD_REQUEST,
-                format!("Invalid collection or function name: {}", name),
-                serde_json::Value::Null,
-            ));
-        }
-
-        let server_name = parts[0];
-        let resource_or_tool_name = parts[1];
-
-        // Find the client for this server
-        let client = state
-            .clients
-            .get(&McpServerName(server_name.to_string()))
-            .ok_or_else(|| {
-                ErrorResponse::new(
-                    StatusCode::NOT_FOUND,
-                    format!("Server not found: {}", server_name),
-                    serde_json::Value::Null,
-                )
+        if configuration.strict_requests {
+            validate_query_request(&state.schema(configuration), &request).map_err(|message| {
+                ErrorResponse::new(StatusCode::BAD_REQUEST, message, serde_json::Value::Null)
             })?;
+        }
 
-        // Check if this is a resource (collection) or a tool (function)
-        if let Some(resource) = client.resources.get(resource_or_tool_name) {
-            // This is a resource (collection)
-            // Read the resource
-            let read_request = rmcp::model::ReadResourceRequestParam {
-                uri: resource.raw.uri.clone(),
-            };
-
-            let result = client
-                .service
-                .read_resource(read_request)
-                .await
-                .map_err(|e| {
-                    ErrorResponse::new(
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Failed to read resource: {}", e),
-                        serde_json::Value::Null,
-                    )
-                })?;
-
-            // Convert content to a row
-            let content = serde_json::to_value(&result.contents).unwrap_or(Value::Null);
-
-            // Create a simple response with one row
-            let mut row = IndexMap::new();
-            row.insert(
-                models::FieldName::new("content".into()),
-                models::RowFieldValue(content),
+        // A variables query runs once per set of variables, up to `variable_set_concurrency`
+        // sets at a time. Sets resolving to the same arguments, as when joins fan out over
+        // repeated keys, share a single call.
+        let variable_sets = request
+            .variables
+            .clone()
+            .unwrap_or_else(|| vec![BTreeMap::new()]);
+        let mut unique_arguments = Vec::new();
+        let mut argument_indexes = HashMap::new();
+        let mut rowset_indexes = Vec::with_capacity(variable_sets.len());
+        for variables in &variable_sets {
+            let arguments = resolve_arguments(&request.arguments, variables)?;
+            let key = serde_json::to_string(&arguments).unwrap_or_default();
+            let index = *argument_indexes.entry(key).or_insert_with(|| {
+                unique_arguments.push(arguments);
+                unique_arguments.len() - 1
+            });
+            rowset_indexes.push(index);
+        }
+        if unique_arguments.len() < variable_sets.len() {
+            tracing::debug!(
+                "Deduplicated {} of {} variable sets of a query on {}",
+                variable_sets.len() - unique_arguments.len(),
+                variable_sets.len(),
+                request.collection
             );
-            let rowset = models::RowSet {
-                rows: Some(vec![row]),
-                aggregates: None,
-                groups: None,
-            };
-
-            // Return response with a single row
-            Ok(models::QueryResponse(vec![rowset]).into())
-        } else if let Some(tool) = client.tools.get(resource_or_tool_name) {
-            // Extract input from arguments if provided
-            let mut arguments_map = serde_json::Map::new();
-            for (argument_name, argument) in request.arguments {
-                if let models::Argument::Literal { value } = argument {
-                    arguments_map.insert(argument_name.to_string(), value);
-                }
-            }
-
-            // Execute the tool
-            let call_request = rmcp::model::CallToolRequestParam {
-                name: tool.name.clone(),
-                arguments: if arguments_map.is_empty() {
-                    None
-                } else {
-                    Some(arguments_map)
-                },
-            };
-
-            let result = client.service.call_tool(call_request).await.map_err(|e| {
-                ErrorResponse::new(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to execute tool: {}", e),
-                    serde_json::Value::Null,
-                )
-            })?;
+        }
 
-            let contents = result
-                .content
-                .into_iter()
-                .filter_map(|content| {
-                    if matches!(content.raw, rmcp::model::RawContent::Text { .. }) {
-                        Some(content.raw)
-                    } else {
-                        None
-                    }
+        let collection = &request.collection;
+        let query = &request.query;
+        let unique_rowsets = futures::stream::iter(unique_arguments)
+            .map(|arguments| async move {
+                let rowset =
+                    query_rowset(configuration, state, collection, query, arguments).await?;
+                select_rowset_fields(query, rowset).map_err(|message| {
+                    ErrorResponse::new(StatusCode::BAD_REQUEST, message, serde_json::Value::Null)
                 })
-                .collect::<Vec<_>>();
-
-            let structured_content = result
-                .structured_content
-                .and_then(|content| serde_json::to_string(&content).ok());
-
-            // Convert content to a row
-            let mut row = IndexMap::new();
-            row.insert(
-                "__value".into(),
-                models::RowFieldValue(serde_json::json!(
-                        {"content": contents, "structured_content": structured_content}
-                )),
-            );
-            let rowset = models::RowSet {
-                rows: Some(vec![row]),
-                aggregates: None,
-                groups: None,
-            };
+            })
+            .buffered(configuration.variable_set_concurrency.max(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+        let rowsets = rowset_indexes
+            .into_iter()
+            .map(|index| unique_rowsets[index].clone())
+            .collect();
 
-            // Return response with a single row
-            Ok(models::QueryResponse(vec![rowset]).into())
-        } else {
-            Err(ErrorResponse::new(
-                StatusCode::NOT_FOUND,
-                format!("Resource or tool not found: {}", resource_or_tool_name),
-                serde_json::Value::Null,
-            ))
-        }
+        Ok(models::QueryResponse(rowsets).into())
     }
 
     async fn mutation(
-        _configuration: &Self::Configuration,
+        configuration: &Self::Configuration,
         state: &Self::State,
         request: models::MutationRequest,
     ) -> Result<JsonResponse<models::MutationResponse>, ErrorResponse> {
-        // Process each mutation operation
-        let mut operation_results = Vec::new();
-
-        for operation in request.operations {
-            match operation {
-                models::MutationOperation::Procedure {
-                    name,
-                    arguments,
-                    fields: _,
-                } => {
-                    // Parse the procedure name to extract server_name and tool name
-                    let name_str = name.to_string();
-                    let parts: Vec<&str> = name_str.split("__").collect();
-
-                    if parts.len() != 2 {
-                        return Err(ErrorResponse::new(
-                            StatusCode::BAD_REQUEST,
-                            format!("Invalid procedure name: {}", name_str),
-                            serde_json::Value::Null,
-                        ));
-                    }
-
-                    let server_name = parts[0];
-                    let tool_name = parts[1];
-
-                    // Find the client for this server
-                    let client = state
-                        .clients
-                        .get(&McpServerName(server_name.to_string()))
-                        .ok_or_else(|| {
-                            ErrorResponse::new(
-                                StatusCode::NOT_FOUND,
-                                format!("Server not found: {}", server_name),
-                                serde_json::Value::Null,
-                            )
-                        })?;
-
-                    // Check if the tool exists
-                    if !client.tools.contains_key(tool_name) {
-                        return Err(ErrorResponse::new(
-                            StatusCode::NOT_FOUND,
-                            format!("Tool not found: {}", tool_name),
-                            serde_json::Value::Null,
-                        ));
-                    }
-
-                    // Extract input from arguments if provided
-                    let mut arguments_map = serde_json::Map::new();
-                    for (argument_name, value) in arguments {
-                        arguments_map.insert(argument_name.to_string(), value);
-                    }
-
-                    // Execute the tool
-                    let call_request = rmcp::model::CallToolRequestParam {
-                        name: tool_name.to_string().into(),
-                        arguments: if arguments_map.is_empty() {
-                            None
-                        } else {
-                            Some(arguments_map)
-                        },
-                    };
-
-                    let result = client.service.call_tool(call_request).await.map_err(|e| {
-                        ErrorResponse::new(
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            format!("Failed to execute tool: {}", e),
-                            serde_json::Value::Null,
-                        )
-                    })?;
+        if configuration.read_only {
+            return Err(ErrorResponse::new(
+                StatusCode::FORBIDDEN,
+                "The connector is in read-only mode, mutations are disabled".to_string(),
+                serde_json::Value::Null,
+            ));
+        }
+        if configuration.strict_requests {
+            validate_mutation_request(&state.schema(configuration), &request).map_err(
+                |message| {
+                    ErrorResponse::new(StatusCode::BAD_REQUEST, message, serde_json::Value::Null)
+                },
+            )?;
+        }
 
-                    let raw_contents = result
-                        .content
-                        .into_iter()
-                        .map(|content| content.raw)
-                        .collect::<Vec<_>>();
-                    let content = serde_json::to_value(&raw_contents).unwrap_or(Value::Null);
-
-                    // Convert the result to a JSON value
-                    operation_results.push(models::MutationOperationResults::Procedure {
-                        result: serde_json::json!({"content": content}),
-                    });
+        // Operations run one after the other by default, or concurrently with
+        // `parallel_mutations`, up to a limit per server
+        let operation_results = match &configuration.parallel_mutations {
+            None => {
+                let mut operation_results = Vec::new();
+                for operation in request.operations {
+                    operation_results
+                        .push(execute_mutation_operation(configuration, state, operation).await?);
                 }
+                operation_results
             }
-        }
+            Some(parallel_mutations) => {
+                // Operations calling the same server share its limit, across all mutations
+                let max_concurrency = parallel_mutations.max_concurrency_per_server.max(1);
+                let operations = request
+                    .operations
+                    .into_iter()
+                    .map(|operation| {
+                        let models::MutationOperation::Procedure { name, .. } = &operation;
+                        let permits =
+                            state
+                                .resolve_name(&name.to_string())
+                                .map(|(server_name, ..)| {
+                                    state.mutation_permits(server_name, max_concurrency)
+                                });
+                        (operation, permits)
+                    })
+                    .collect();
+                run_with_permits(operations, |operation| {
+                    execute_mutation_operation(configuration, state, operation)
+                })
+                .await?
+            }
+        };
 
         Ok(models::MutationResponse { operation_results }.into())
     }
 }
 
+/// Run operations concurrently, each holding a permit of its semaphore while it runs. Results
+/// keep the order of the operations. The first failure drops the operations still running:
+/// their tool calls are abandoned without knowing whether the server applied them.
+async fn run_with_permits<O, T, E, Fut>(
+    operations: Vec<(O, Option<Arc<Semaphore>>)>,
+    execute: impl Fn(O) -> Fut,
+) -> Result<Vec<T>, E>
+where
+    Fut: Future<Output = Result<T, E>>,
+{
+    let execute = &execute;
+    futures::future::try_join_all(
+        operations
+            .into_iter()
+            .map(|(operation, permits)| async move {
+                let _permit = match &permits {
+                    Some(permits) => permits.acquire().await.ok(),
+                    None => None,
+                };
+                execute(operation).await
+            }),
+    )
+    .await
+}
+
+/// Execute an operation of a mutation. In best-effort mode a failed operation returns its
+/// error instead of failing the whole mutation.
+async fn execute_mutation_operation(
+    configuration: &ConnectorConfig,
+    state: &ConnectorState,
+    operation: models::MutationOperation,
+) -> Result<models::MutationOperationResults, ErrorResponse> {
+    match operation {
+        models::MutationOperation::Procedure {
+            name,
+            arguments,
+            fields,
+        } => {
+            let result =
+                match execute_procedure(configuration, state, &name.to_string(), arguments).await {
+                    Ok(result) if configuration.best_effort_mutations => {
+                        serde_json::json!({ "result": result, "error": null })
+                    }
+                    Ok(result) => result,
+                    Err(err) if configuration.best_effort_mutations => {
+                        tracing::warn!(
+                            procedure = %name,
+                            error_class = ?err.error_class,
+                            "Procedure failed: {}",
+                            err.message
+                        );
+                        serde_json::json!({ "result": null, "error": err })
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+            let result = match &fields {
+                Some(fields) => select_nested_field(fields, result).map_err(|message| {
+                    ErrorResponse::new(StatusCode::BAD_REQUEST, message, serde_json::Value::Null)
+                })?,
+                None => result,
+            };
+            Ok(models::MutationOperationResults::Procedure { result })
+        }
+    }
+}
+
 #[async_trait]
 impl ConnectorSetup for McpConnectorSetup {
     type Connector = McpConnector;
@@ -455,40 +2271,214 @@ impl ConnectorSetup for McpConnectorSetup {
     ) -> Result<<Self::Connector as Connector>::Configuration, ErrorResponse> {
         // Load configuration from file
         let config_path = configuration_dir.join("configuration.json");
-        let config = ConnectorConfig::from_file(&config_path).map_err(|e| {
+        let mut config = ConnectorConfig::from_file(&config_path).map_err(|e| {
+            // Report where an invalid configuration is wrong
+            let details = e
+                .downcast_ref::<ConfigError>()
+                .and_then(|err| serde_json::to_value(err).ok())
+                .unwrap_or(serde_json::Value::Null);
             ErrorResponse::new(
                 StatusCode::BAD_REQUEST,
-                format!("Failed to load configuration: {}", e),
-                serde_json::Value::Null,
+                format!("Failed to load configuration: {:#}", e),
+                details,
             )
         })?;
 
         // Let's validate the env variables
-        for server_config in config.servers.values() {
+        for (server_name, server_config) in &config.servers {
             match server_config {
-                McpServerConfig::Stdio(StdioConfig { env, .. }) => {
-                    validate_env_variables(env)?;
+                McpServerConfig::Stdio(stdio_config) => {
+                    validate_env_variables(&stdio_config.env)?;
+                    stdio_config.resolve_command().map_err(|e| {
+                        ErrorResponse::new(
+                            StatusCode::BAD_REQUEST,
+                            format!(
+                                "Failed to resolve the command of server {}: {}",
+                                server_name.0, e
+                            ),
+                            serde_json::Value::Null,
+                        )
+                    })?;
                 }
                 McpServerConfig::Http(StreamableHttpConfig { headers, .. }) => {
                     validate_env_variables(headers)?;
                 }
-                _ => {}
+                McpServerConfig::Custom(custom_config) => {
+                    if transport_factory(&custom_config.transport).is_none() {
+                        return Err(ErrorResponse::new(
+                            StatusCode::BAD_REQUEST,
+                            format!(
+                                "Unknown transport {} of server {}",
+                                custom_config.transport, server_name.0
+                            ),
+                            serde_json::Value::Null,
+                        ));
+                    }
+                }
+                McpServerConfig::Sse(_) => {}
             }
         }
+        for tool_config in config.tools.values().flat_map(|tools| tools.values()) {
+            validate_env_variables(&tool_config.meta)?;
+        }
+        // Read-only mode can be forced by the deployment, whatever the configuration says
+        if std::env::var(READ_ONLY_ENV_VAR).is_ok_and(|value| is_truthy(&value)) {
+            config.read_only = true;
+        }
+        if let Some(flag) = unknown_feature_flags(&config.features).first() {
+            return Err(ErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                format!("Unknown feature flag: {}", flag),
+                serde_json::Value::Null,
+            ));
+        }
         Ok(config)
     }
 
     async fn try_init_state(
         &self,
         configuration: &<Self::Connector as Connector>::Configuration,
-        _metrics: &mut prometheus::Registry,
+        metrics: &mut prometheus::Registry,
     ) -> Result<<Self::Connector as Connector>::State, ErrorResponse> {
-        // Initialize MCP clients
-        let state = initialize_mcp_clients(configuration).await?;
+        // Initialize MCP clients, or only load the stored introspection when offline
+        let mut state = if configuration.offline {
+            initialize_offline_state(configuration)?
+        } else {
+            initialize_mcp_clients(configuration).await?
+        };
+
+        // The page arguments of paginated tools must not shadow arguments of the tools
+        for (server_name, client) in &state.clients {
+            for tool in client.tools.values() {
+                let conflict = configuration
+                    .tool_config(server_name, &tool.name)
+                    .and_then(|tool_config| tool_config.pagination.as_ref())
+                    .and_then(|pagination| conflicting_page_argument(tool, pagination));
+                if let Some(argument) = conflict {
+                    return Err(ErrorResponse::new(
+                        StatusCode::BAD_REQUEST,
+                        format!(
+                            "Tool {} of server {} already has an argument {}; rename the \
+                             pagination argument with page_argument or session_argument",
+                            tool.name, server_name.0, argument
+                        ),
+                        serde_json::Value::Null,
+                    ));
+                }
+            }
+        }
+
+        // Publish the schema hash, so automation can detect schema changes
+        let schema = generate_schema_from_state(&state, configuration);
+        state.schema_hash = schema_hash(&schema);
+        if !configuration.offline {
+            // Log when introspecting the servers changed the schema of the stored introspection
+            if let Ok(stored_state) = initialize_offline_state(configuration) {
+                let stored_hash =
+                    schema_hash(&generate_schema_from_state(&stored_state, configuration));
+                log_schema_change(&stored_hash, &state.schema_hash);
+            }
+        }
+        if let Err(err) = register_schema_hash_metric(metrics, &state.schema_hash) {
+            tracing::warn!("Failed to register the schema hash metric: {}", err);
+        }
+
+        // Keep the schema, so requests are validated without generating it again
+        state.schema = Some(schema);
+
+        // Set up the storage of cached tool responses
+        let cache_backend = create_cache_backend(&configuration.cache_backend)
+            .await
+            .map_err(|e| {
+                ErrorResponse::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Failed to set up the response cache: {}", e),
+                    serde_json::Value::Null,
+                )
+            })?;
+        state.response_cache = ResponseCache::new(cache_backend);
+        state.server_health = Arc::new(ServerHealth::new(configuration.circuit_breaker.clone()));
+        let server_retry_configs = configuration
+            .servers
+            .iter()
+            .filter_map(|(server_name, server_config)| {
+                Some((server_name.clone(), server_config.options().retry.clone()?))
+            })
+            .collect();
+        state.retry_budget = RetryBudget::new(configuration.retry.clone())
+            .with_server_configs(server_retry_configs)
+            .with_metrics(metrics);
+        state.sessions = SessionStats::default().with_metrics(metrics);
+
+        // Set up the decryptor for sealed arguments
+        if let Some(sealed_arguments) = &configuration.sealed_arguments {
+            let decryptor = self
+                .decryptor
+                .clone()
+                .or_else(|| sealed_arguments.decryptor.as_ref().map(create_decryptor))
+                .ok_or_else(|| {
+                    ErrorResponse::new(
+                        StatusCode::BAD_REQUEST,
+                        "No decryptor configured for sealed arguments".to_string(),
+                        serde_json::Value::Null,
+                    )
+                })?;
+            state.decryptor = Some(decryptor);
+        }
+
+        // Serve the connection events and failed calls for debugging
+        if let Some(debug_address) = &configuration.debug_address {
+            debug_endpoint::serve(
+                debug_address,
+                state.connection_events.clone(),
+                state.failed_calls.clone(),
+            )
+            .await
+            .map_err(|e| {
+                ErrorResponse::new(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Failed to serve the debug endpoint on {}: {}",
+                        debug_address, e
+                    ),
+                    serde_json::Value::Null,
+                )
+            })?;
+        }
+
+        state.startup_summary = StartupSummary::new(configuration, &state);
+        state.startup_summary.log();
         Ok(Arc::new(state))
     }
 }
 
+/// Register the `ndc_mcp_schema_info` gauge, set to 1 and labeled with the schema hash
+fn register_schema_hash_metric(
+    metrics: &mut prometheus::Registry,
+    schema_hash: &str,
+) -> prometheus::Result<()> {
+    let schema_info = prometheus::IntGaugeVec::new(
+        prometheus::Opts::new(
+            "ndc_mcp_schema_info",
+            "Hash of the schema served by the connector",
+        ),
+        &["schema_hash"],
+    )?;
+    schema_info.with_label_values(&[schema_hash]).set(1);
+    metrics.register(Box::new(schema_info))
+}
+
+/// Environment variable enabling read-only mode
+const READ_ONLY_ENV_VAR: &str = "NDC_MCP_READ_ONLY";
+
+/// Check if an environment variable value enables a flag
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
 fn validate_env_variables(env: &HashMap<String, EnvVariableValue>) -> Result<(), ErrorResponse> {
     for (key, value) in env {
         value.resolve().map_err(|e| {
@@ -501,3 +2491,443 @@ fn validate_env_variables(env: &HashMap<String, EnvVariableValue>) -> Result<(),
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Decrypts values sealed as `sealed:<plaintext>`
+    struct PrefixDecryptor;
+
+    #[async_trait]
+    impl ArgumentDecryptor for PrefixDecryptor {
+        async fn decrypt(&self, sealed_value: &str) -> anyhow::Result<String> {
+            sealed_value
+                .strip_prefix("sealed:")
+                .map(str::to_string)
+                .ok_or_else(|| anyhow::anyhow!("invalid ciphertext"))
+        }
+    }
+
+    fn sealed_configuration() -> ConnectorConfig {
+        serde_json::from_value(json!({
+            "servers": {},
+            "sealed_arguments": {"arguments": ["api_key"]}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_apply_default_arguments() {
+        let tool = Tool::new(
+            "search",
+            "Search",
+            Arc::new(
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "limit": {"type": ["integer", "null"], "default": 10},
+                        "sort": {"type": "string", "default": "relevance"},
+                        "version": {"const": "v2"}
+                    }
+                })
+                .as_object()
+                .unwrap()
+                .clone(),
+            ),
+        );
+        let mut arguments = json!({"limit": null, "version": null})
+            .as_object()
+            .unwrap()
+            .clone();
+        apply_default_arguments(&tool, &mut arguments);
+        assert_eq!(
+            Value::Object(arguments),
+            json!({"limit": null, "sort": "relevance", "version": "v2"})
+        );
+    }
+
+    #[test]
+    fn test_advertised_features() {
+        let capabilities: ServerCapabilities =
+            serde_json::from_value(json!({"prompts": {}})).unwrap();
+        assert_eq!(
+            AdvertisedFeatures::from_capabilities(Some(&capabilities)),
+            AdvertisedFeatures {
+                resources: false,
+                tools: false,
+                prompts: true,
+            }
+        );
+
+        let capabilities: ServerCapabilities =
+            serde_json::from_value(json!({"resources": {"subscribe": true}, "tools": {}})).unwrap();
+        assert_eq!(
+            AdvertisedFeatures::from_capabilities(Some(&capabilities)),
+            AdvertisedFeatures {
+                resources: true,
+                tools: true,
+                prompts: false,
+            }
+        );
+
+        // Servers that didn't report their capabilities are introspected fully
+        assert_eq!(
+            AdvertisedFeatures::from_capabilities(None),
+            AdvertisedFeatures {
+                resources: true,
+                tools: true,
+                prompts: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_resource_content_fields() {
+        let contents: Vec<ResourceContents> = serde_json::from_value(json!([
+            {"uri": "file:///logo.png", "blob": "iVBORw0K"},
+            {"uri": "file:///README.md", "mimeType": "text/markdown", "text": "# Logo"},
+            {"uri": "file:///LICENSE", "mimeType": "text/plain", "text": "MIT"}
+        ]))
+        .unwrap();
+        assert_eq!(
+            ResourceContentFields::from_contents(&contents),
+            ResourceContentFields {
+                text: Some("# Logo".to_string()),
+                blob: Some("iVBORw0K".to_string()),
+                mime_type: Some("text/markdown".to_string()),
+            }
+        );
+        assert_eq!(
+            ResourceContentFields::from_contents(&[]),
+            ResourceContentFields::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_json_contents() {
+        let text = Some(r#"[{"id": 1}]"#);
+        assert_eq!(
+            parse_json_contents(text, Some("application/json")),
+            Some(json!([{"id": 1}]))
+        );
+        assert_eq!(
+            parse_json_contents(text, Some("Application/GEO+JSON; charset=utf-8")),
+            Some(json!([{"id": 1}]))
+        );
+        assert_eq!(parse_json_contents(text, Some("text/plain")), None);
+        assert_eq!(parse_json_contents(text, None), None);
+        assert_eq!(
+            parse_json_contents(Some("{not json"), Some("application/json")),
+            None
+        );
+        assert_eq!(parse_json_contents(None, Some("application/json")), None);
+    }
+
+    #[test]
+    fn test_content_values() {
+        let content: Vec<RawContent> = serde_json::from_value(json!([
+            {"type": "text", "text": "chart"},
+            {"type": "image", "data": "iVBORw0K", "mimeType": "image/png"},
+            {"type": "audio", "data": "UklGRg", "mimeType": "audio/wav"},
+            {"type": "resource", "resource": {
+                "uri": "file:///chart.png", "mimeType": "image/png", "blob": "iVBORw0K"
+            }},
+            {"type": "resource", "resource": {"uri": "file:///notes.txt", "text": "notes"}},
+            {"type": "resource_link", "uri": "file:///data.csv", "name": "data.csv"}
+        ]))
+        .unwrap();
+        let values = content_values(&content);
+        assert_eq!(values[0]["type"], "text");
+        assert_eq!(values[0]["text"], "chart");
+        assert_eq!(values[1]["type"], "image");
+        assert_eq!(values[1]["data"], "iVBORw0K");
+        assert_eq!(values[1]["mimeType"], "image/png");
+        assert_eq!(values[2]["data"], "UklGRg");
+
+        // Embedded resources are flattened, with their blob as data
+        assert_eq!(values[3]["type"], "resource");
+        assert_eq!(values[3]["uri"], "file:///chart.png");
+        assert_eq!(values[3]["mimeType"], "image/png");
+        assert_eq!(values[3]["data"], "iVBORw0K");
+        assert!(values[3].get("resource").is_none() && values[3].get("blob").is_none());
+        assert_eq!(values[4]["text"], "notes");
+        assert_eq!(values[4]["uri"], "file:///notes.txt");
+        assert_eq!(values[5]["type"], "resource_link");
+        assert_eq!(values[5]["uri"], "file:///data.csv");
+    }
+
+    #[test]
+    fn test_tool_output() {
+        let content: Vec<RawContent> = serde_json::from_value(json!([
+            {"type": "text", "text": "sunny"},
+            {"type": "image", "data": "iVBORw0K", "mimeType": "image/png"}
+        ]))
+        .unwrap();
+        let configuration: ConnectorConfig =
+            serde_json::from_value(json!({"servers": {}})).unwrap();
+        let output = tool_output(&configuration, &content, json!({"temperature": 21}), None);
+        assert_eq!(output["content"], content_values(&content));
+        assert_eq!(output["structured_content"], json!({"temperature": 21}));
+        assert!(output.get("text").is_none() && output.get("tool_error").is_none());
+
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {},
+            "text_field": "first",
+            "tool_errors": "field"
+        }))
+        .unwrap();
+        let output = tool_output(&configuration, &content, Value::Null, None);
+        assert_eq!(output["text"], "sunny");
+        assert_eq!(output["tool_error"], Value::Null);
+    }
+
+    #[test]
+    fn test_function_rowset() {
+        let query: models::Query = serde_json::from_value(json!({
+            "fields": {"__value": {"type": "column", "column": "__value"}}
+        }))
+        .unwrap();
+        let rowset = function_rowset(&query, json!({"temperature": 21})).unwrap();
+        let rows = rowset.rows.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            rows[0].values().next().map(|value| &value.0),
+            Some(&json!({"temperature": 21}))
+        );
+
+        // Aggregates over the single value are rejected rather than counting it
+        let query: models::Query = serde_json::from_value(json!({
+            "aggregates": {"count": {"type": "star_count"}}
+        }))
+        .unwrap();
+        assert!(function_rowset(&query, json!({"temperature": 21})).is_err());
+    }
+
+    #[test]
+    fn test_prompt_message_values() {
+        let messages: Vec<PromptMessage> = serde_json::from_value(json!([
+            {"role": "user", "content": {"type": "text", "text": "Describe the chart"}},
+            {"role": "user", "content": {
+                "type": "image", "data": "iVBORw0K", "mimeType": "image/png"
+            }},
+            {"role": "assistant", "content": {"type": "resource", "resource": {
+                "resource": {"uri": "file:///notes.txt", "text": "notes"}
+            }}}
+        ]))
+        .unwrap();
+        let values = prompt_message_values(&messages);
+
+        // Every message is kept, with the same content shape as tool results
+        assert_eq!(values[0]["role"], "user");
+        assert_eq!(values[0]["content"]["text"], "Describe the chart");
+        assert_eq!(values[1]["content"]["type"], "image");
+        assert_eq!(values[1]["content"]["data"], "iVBORw0K");
+        assert_eq!(values[1]["content"]["mimeType"], "image/png");
+        assert_eq!(values[2]["role"], "assistant");
+        assert_eq!(values[2]["content"]["type"], "resource");
+        assert_eq!(values[2]["content"]["uri"], "file:///notes.txt");
+        assert_eq!(values[2]["content"]["text"], "notes");
+        assert!(values[2]["content"].get("resource").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_with_permits() {
+        let permits = Arc::new(Semaphore::new(2));
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let operations = (0..6u64)
+            .map(|index| (index, Some(permits.clone())))
+            .collect();
+        let results = run_with_permits(operations, |index| {
+            let (running, max_running) = (&running, &max_running);
+            async move {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+                // Later operations finish first
+                tokio::time::sleep(Duration::from_millis(60 - index * 10)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok::<_, ()>(index)
+            }
+        })
+        .await;
+
+        // Results keep the order of the operations, with at most two running at a time
+        assert_eq!(results, Ok(vec![0, 1, 2, 3, 4, 5]));
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+
+        // The limit of a server is shared by the operations of all mutations
+        let state = ConnectorState::new();
+        let server_name = McpServerName("crm".to_string());
+        assert!(Arc::ptr_eq(
+            &state.mutation_permits(&server_name, 2),
+            &state.mutation_permits(&server_name, 2)
+        ));
+    }
+
+    /// Serve a fake MCP server answering every tool call with `sunny`, counting the calls
+    async fn serve_fake_server(stream: tokio::io::DuplexStream, tool_calls: Arc<AtomicUsize>) {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut lines = tokio::io::BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let request: Value = serde_json::from_str(&line).unwrap();
+            let result = match request["method"].as_str() {
+                Some("initialize") => json!({
+                    "protocolVersion": "2025-03-26",
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {"name": "weather", "version": "1.0.0"}
+                }),
+                Some("tools/call") => {
+                    tool_calls.fetch_add(1, Ordering::SeqCst);
+                    json!({"content": [{"type": "text", "text": "sunny"}]})
+                }
+                // Notifications are not answered
+                _ => continue,
+            };
+            let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+            writer
+                .write_all(format!("{}\n", response).as_bytes())
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_function_calls_are_coalesced() {
+        use rmcp::ServiceExt;
+
+        let (client_stream, server_stream) = tokio::io::duplex(4096);
+        let tool_calls = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(serve_fake_server(server_stream, tool_calls.clone()));
+        let service = ().serve(tokio::io::split(client_stream)).await.unwrap();
+        let server_name = McpServerName("weather".to_string());
+        let mut state = ConnectorState::new();
+        state.add_client(
+            server_name.clone(),
+            McpClient {
+                service: Some(service),
+                resources: HashMap::new(),
+                resource_templates: HashMap::new(),
+                tools: HashMap::new(),
+                prompts: HashMap::new(),
+            },
+        );
+        let state = Arc::new(state);
+        let client = &state.clients[&server_name];
+        let call_request = |city: &str| CallToolRequestParam {
+            name: "forecast".into(),
+            arguments: json!({ "city": city }).as_object().cloned(),
+        };
+        let is_sunny = |result: Result<CallToolResult, ErrorResponse>| {
+            result.is_ok_and(|result| {
+                result.content[0]
+                    .raw
+                    .as_text()
+                    .is_some_and(|text| text.text == "sunny")
+            })
+        };
+
+        // Identical concurrent calls of a function share one tool call
+        let configuration: ConnectorConfig =
+            serde_json::from_value(json!({"servers": {}})).unwrap();
+        let (first, second) = tokio::join!(
+            call_read_only_tool(
+                &configuration,
+                &state,
+                &server_name,
+                client,
+                call_request("Paris")
+            ),
+            call_read_only_tool(
+                &configuration,
+                &state,
+                &server_name,
+                client,
+                call_request("Paris")
+            ),
+        );
+        assert!(is_sunny(first) && is_sunny(second));
+        assert_eq!(tool_calls.load(Ordering::SeqCst), 1);
+
+        // Without coalescing, each call is made
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {},
+            "features": {"request_coalescing": false}
+        }))
+        .unwrap();
+        let (first, second) = tokio::join!(
+            call_read_only_tool(
+                &configuration,
+                &state,
+                &server_name,
+                client,
+                call_request("Lyon")
+            ),
+            call_read_only_tool(
+                &configuration,
+                &state,
+                &server_name,
+                client,
+                call_request("Lyon")
+            ),
+        );
+        assert!(is_sunny(first) && is_sunny(second));
+        assert_eq!(tool_calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_decrypt_sealed_arguments() {
+        let configuration = sealed_configuration();
+        let mut state = ConnectorState::new();
+        state.decryptor = Some(Arc::new(PrefixDecryptor));
+
+        let mut arguments = json!({"api_key": "sealed:secret", "query": "sealed:public"})
+            .as_object()
+            .unwrap()
+            .clone();
+        decrypt_sealed_arguments(&configuration, &state, &mut arguments)
+            .await
+            .unwrap();
+        assert_eq!(
+            Value::Object(arguments),
+            json!({"api_key": "secret", "query": "sealed:public"})
+        );
+
+        let mut arguments = json!({"api_key": "tampered"}).as_object().unwrap().clone();
+        let err = decrypt_sealed_arguments(&configuration, &state, &mut arguments)
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_class, OperationErrorClass::InvalidArguments);
+        assert_eq!(
+            err.message,
+            "Failed to decrypt argument api_key: invalid ciphertext"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decrypt_sealed_arguments_without_decryptor() {
+        let configuration = sealed_configuration();
+        let state = ConnectorState::new();
+
+        let mut arguments = json!({"api_key": "sealed:secret"})
+            .as_object()
+            .unwrap()
+            .clone();
+        let err = decrypt_sealed_arguments(&configuration, &state, &mut arguments)
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_class, OperationErrorClass::Configuration);
+
+        // Calls without sealed values don't need a decryptor
+        let mut arguments = json!({"query": "rust"}).as_object().unwrap().clone();
+        assert!(
+            decrypt_sealed_arguments(&configuration, &state, &mut arguments)
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! HTTP debug endpoint serving the connection events of every server at
+//! `/debug/connection-events` and the most recent failed calls at `/debug/failed-calls`, when
+//! enabled with the `debug_address` configuration option. The NDC routes are served by
+//! ndc-sdk, so the endpoint listens on its own address.
+
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::events::ConnectionEventLog;
+use crate::failed_calls::FailedCallLog;
+
+/// Path of the connection events
+pub const CONNECTION_EVENTS_PATH: &str = "/debug/connection-events";
+
+/// Path of the failed calls
+pub const FAILED_CALLS_PATH: &str = "/debug/failed-calls";
+
+/// Listen on the address and serve the connection events and failed calls until the connector
+/// stops
+pub async fn serve(
+    address: &str,
+    connection_events: Arc<ConnectionEventLog>,
+    failed_calls: Arc<FailedCallLog>,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address).await?;
+    tracing::info!("Serving the debug endpoint on {}", listener.local_addr()?);
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::warn!("Failed to accept a debug endpoint connection: {}", err);
+                    continue;
+                }
+            };
+            let connection_events = connection_events.clone();
+            let failed_calls = failed_calls.clone();
+            tokio::spawn(async move {
+                if let Err(err) = respond(stream, &connection_events, &failed_calls).await {
+                    tracing::debug!("Failed to answer a debug endpoint request: {}", err);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Answer a single request, then close the connection
+async fn respond(
+    stream: TcpStream,
+    connection_events: &ConnectionEventLog,
+    failed_calls: &FailedCallLog,
+) -> std::io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut request_line = String::new();
+    stream.read_line(&mut request_line).await?;
+    let (status, body) = match route(&request_line) {
+        Some(CONNECTION_EVENTS_PATH) => ("200 OK", connection_events.to_json().to_string()),
+        Some(FAILED_CALLS_PATH) => ("200 OK", failed_calls.to_json().to_string()),
+        _ => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.get_mut().write_all(response.as_bytes()).await?;
+    stream.get_mut().shutdown().await
+}
+
+/// The path of a GET request line, without its query string
+fn route(request_line: &str) -> Option<&str> {
+    let mut parts = request_line.split_whitespace();
+    if parts.next() != Some("GET") {
+        return None;
+    }
+    parts
+        .next()
+        .map(|target| target.split('?').next().unwrap_or(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::McpServerName;
+    use crate::events::ConnectionEventKind;
+    use crate::failed_calls::FailedCall;
+    use crate::state::McpItemKind;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+
+    /// Send a GET request for the path and return the status line and body of the response
+    async fn get(address: std::net::SocketAddr, path: &str) -> (String, serde_json::Value) {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status_line = head.lines().next().unwrap_or_default().to_string();
+        (status_line, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn test_route() {
+        assert_eq!(
+            route("GET /debug/connection-events?pretty HTTP/1.1\r\n"),
+            Some(CONNECTION_EVENTS_PATH)
+        );
+        assert_eq!(route("POST /debug/connection-events HTTP/1.1\r\n"), None);
+        assert_eq!(route(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_serve_connection_events() {
+        let connection_events = Arc::new(ConnectionEventLog::default());
+        connection_events.record(
+            &McpServerName("weather".to_string()),
+            ConnectionEventKind::Connected,
+            None,
+        );
+        let failed_calls = Arc::new(FailedCallLog::default());
+        failed_calls.record(FailedCall::new(
+            &McpServerName("weather".to_string()),
+            McpItemKind::Tool,
+            "forecast",
+            serde_json::json!({"city": "Paris"}),
+            serde_json::json!("timeout"),
+            Duration::from_millis(20),
+        ));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        serve(&address.to_string(), connection_events, failed_calls)
+            .await
+            .unwrap();
+
+        let (status_line, events) = get(address, CONNECTION_EVENTS_PATH).await;
+        assert_eq!(status_line, "HTTP/1.1 200 OK");
+        assert_eq!(events["weather"][0]["kind"], "connected");
+
+        let (status_line, calls) = get(address, FAILED_CALLS_PATH).await;
+        assert_eq!(status_line, "HTTP/1.1 200 OK");
+        assert_eq!(calls[0]["name"], "forecast");
+        assert_eq!(calls[0]["error"], "timeout");
+
+        let (status_line, _) = get(address, "/debug/unknown").await;
+        assert_eq!(status_line, "HTTP/1.1 404 Not Found");
+    }
+}
@@ -0,0 +1,63 @@
+//! Hooks for decrypting sealed argument values (e.g. KMS-encrypted secrets passed by the
+//! engine) before they are forwarded to MCP servers.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::DecryptorConfig;
+
+/// Decrypts sealed argument values
+#[async_trait]
+pub trait ArgumentDecryptor: Send + Sync {
+    /// Decrypt a sealed value, returning the plaintext
+    async fn decrypt(&self, sealed_value: &str) -> Result<String>;
+}
+
+/// Decrypts values by piping them to an external command and reading the plaintext from
+/// its standard output
+pub struct CommandDecryptor {
+    command: String,
+    args: Vec<String>,
+}
+
+#[async_trait]
+impl ArgumentDecryptor for CommandDecryptor {
+    async fn decrypt(&self, sealed_value: &str) -> Result<String> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start decryption command: {}", e))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(sealed_value.as_bytes()).await?;
+        }
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Decryption command failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let plaintext = String::from_utf8(output.stdout)?;
+        Ok(plaintext.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// Create a decryptor from its configuration
+pub fn create_decryptor(config: &DecryptorConfig) -> Arc<dyn ArgumentDecryptor> {
+    match config {
+        DecryptorConfig::Command { command, args } => Arc::new(CommandDecryptor {
+            command: command.clone(),
+            args: args.clone(),
+        }),
+    }
+}
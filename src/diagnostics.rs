@@ -0,0 +1,39 @@
+//! The `_diagnostics` function exposes runtime diagnostics of the connector when enabled
+//! with the `diagnostics` configuration option.
+
+use ndc_sdk::models::{FunctionInfo, Type};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use crate::sessions::session_diagnostics;
+use crate::state::ConnectorState;
+
+/// Name of the diagnostics function
+pub const DIAGNOSTICS_FUNCTION_NAME: &str = "_diagnostics";
+
+/// Schema of the diagnostics function
+pub fn diagnostics_function_info() -> FunctionInfo {
+    FunctionInfo {
+        name: DIAGNOSTICS_FUNCTION_NAME.to_string().into(),
+        description: Some("Runtime diagnostics of the MCP connector".to_string()),
+        arguments: BTreeMap::new(),
+        result_type: Type::Named {
+            name: "JSON".to_string().into(),
+        },
+    }
+}
+
+/// Collect the diagnostics of the connector
+pub fn diagnostics(state: &ConnectorState) -> Value {
+    json!({
+        "startup_summary": state.startup_summary,
+        "schema_hash": state.schema_hash,
+        "features": state.startup_summary.features,
+        "inferred_output_schemas": state.output_schema_inference.json_schemas(),
+        "connection_events": state.connection_events.to_json(),
+        "degraded_servers": state.server_health.degraded_servers(),
+        "sessions": session_diagnostics(state),
+        "failed_calls": state.failed_calls.to_json(),
+        "name_collisions": state.name_collisions,
+    })
+}
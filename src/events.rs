@@ -0,0 +1,162 @@
+//! Bounded history of connection lifecycle events per server, exposed through the
+//! `_diagnostics` function and the debug endpoint so intermittent connection problems can be
+//! investigated after the fact.
+
+use rmcp::ServiceError;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::McpServerName;
+
+/// Number of events kept per server
+const MAX_EVENTS_PER_SERVER: usize = 100;
+
+/// Kind of a connection lifecycle event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionEventKind {
+    /// The server was connected and introspected
+    Connected,
+    /// The server could not be connected or introspected
+    Failed,
+    /// The connection to the server was lost while handling a request
+    Crashed,
+    /// A call to the server succeeded after its connection was lost
+    Reconnected,
+    /// The server's circuit opened after repeated failures, and calls to it fail fast
+    CircuitOpened,
+    /// A call to a server with an open circuit succeeded
+    Recovered,
+}
+
+/// A connection lifecycle event
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionEvent {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub kind: ConnectionEventKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Connection events per server, keeping the most recent events
+#[derive(Default)]
+pub struct ConnectionEventLog {
+    events: Mutex<HashMap<McpServerName, VecDeque<ConnectionEvent>>>,
+}
+
+impl ConnectionEventLog {
+    /// Record an event, dropping the oldest event of the server if its history is full
+    pub fn record(
+        &self,
+        server_name: &McpServerName,
+        kind: ConnectionEventKind,
+        message: Option<String>,
+    ) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0);
+        let mut events = self.events.lock().unwrap();
+        let server_events = events.entry(server_name.clone()).or_default();
+        if server_events.len() == MAX_EVENTS_PER_SERVER {
+            server_events.pop_front();
+        }
+        server_events.push_back(ConnectionEvent {
+            timestamp_ms,
+            kind,
+            message,
+        });
+    }
+
+    /// Record a crash if a request failed because the connection to the server was lost
+    pub fn record_service_error(&self, server_name: &McpServerName, err: &ServiceError) {
+        if matches!(
+            err,
+            ServiceError::TransportClosed | ServiceError::TransportSend(_)
+        ) {
+            self.record(
+                server_name,
+                ConnectionEventKind::Crashed,
+                Some(err.to_string()),
+            );
+        }
+    }
+
+    /// Record a reconnection if a call succeeded after the connection to the server was lost
+    pub fn record_success(&self, server_name: &McpServerName) {
+        let crashed = self
+            .events
+            .lock()
+            .unwrap()
+            .get(server_name)
+            .and_then(|server_events| server_events.back())
+            .is_some_and(|event| event.kind == ConnectionEventKind::Crashed);
+        if crashed {
+            self.record(server_name, ConnectionEventKind::Reconnected, None);
+        }
+    }
+
+    /// The events as `{server: [event]}`, oldest first
+    pub fn to_json(&self) -> Value {
+        let events = self.events.lock().unwrap();
+        let servers = events
+            .iter()
+            .map(|(server_name, server_events)| {
+                (
+                    server_name.0.clone(),
+                    serde_json::to_value(server_events).unwrap_or(Value::Null),
+                )
+            })
+            .collect::<Map<_, _>>();
+        Value::Object(servers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_history_is_bounded() {
+        let log = ConnectionEventLog::default();
+        let server_name = McpServerName("flaky".to_string());
+        log.record(&server_name, ConnectionEventKind::Connected, None);
+        for i in 0..MAX_EVENTS_PER_SERVER {
+            log.record(
+                &server_name,
+                ConnectionEventKind::Crashed,
+                Some(format!("crash {}", i)),
+            );
+        }
+
+        let events = log.to_json();
+        let events = events["flaky"].as_array().unwrap();
+        assert_eq!(events.len(), MAX_EVENTS_PER_SERVER);
+        assert_eq!(events[0]["kind"], "crashed");
+        assert_eq!(events[0]["message"], "crash 0");
+    }
+
+    #[test]
+    fn test_reconnection_is_recorded_after_crash() {
+        let log = ConnectionEventLog::default();
+        let server_name = McpServerName("flaky".to_string());
+        log.record(&server_name, ConnectionEventKind::Connected, None);
+        log.record_success(&server_name);
+        log.record_service_error(&server_name, &ServiceError::TransportClosed);
+        log.record_success(&server_name);
+        log.record_success(&server_name);
+
+        let events = log.to_json();
+        let kinds = events["flaky"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| event["kind"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, vec!["connected", "crashed", "reconnected"]);
+    }
+}
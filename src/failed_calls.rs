@@ -0,0 +1,144 @@
+//! Ring buffer of the most recent failed MCP calls, exposed through the `_diagnostics`
+//! function and the debug endpoint so failures triggered by agents can be reproduced from
+//! their payloads.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::{ConnectorConfig, McpServerName};
+use crate::state::McpItemKind;
+
+/// Number of failed calls kept
+const MAX_FAILED_CALLS: usize = 50;
+
+/// Replacement for redacted argument values
+const REDACTED: &str = "[REDACTED]";
+
+/// A failed call to an MCP server
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedCall {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub server: String,
+    pub kind: McpItemKind,
+    pub name: String,
+    /// The request parameters, with sensitive arguments redacted
+    pub request: Value,
+    /// The error, or the result of a tool call that returned an error
+    pub error: Value,
+    pub duration_ms: u64,
+    /// The JSON-RPC id of the request, if it was sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<Value>,
+}
+
+impl FailedCall {
+    pub fn new(
+        server_name: &McpServerName,
+        kind: McpItemKind,
+        name: &str,
+        request: Value,
+        error: Value,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or(0),
+            server: server_name.0.clone(),
+            kind,
+            name: name.to_string(),
+            request,
+            error,
+            duration_ms: duration.as_millis() as u64,
+            request_id: None,
+        }
+    }
+}
+
+/// The most recent failed calls
+#[derive(Default)]
+pub struct FailedCallLog {
+    calls: Mutex<VecDeque<FailedCall>>,
+}
+
+impl FailedCallLog {
+    /// Record a failed call, dropping the oldest one if the log is full
+    pub fn record(&self, call: FailedCall) {
+        let mut calls = self.calls.lock().unwrap();
+        if calls.len() == MAX_FAILED_CALLS {
+            calls.pop_front();
+        }
+        calls.push_back(call);
+    }
+
+    /// The failed calls, oldest first
+    pub fn to_json(&self) -> Value {
+        let calls = self.calls.lock().unwrap();
+        serde_json::to_value(&*calls).unwrap_or(Value::Null)
+    }
+}
+
+/// Redact sealed arguments and arguments populated from forwarded headers, which may hold
+/// secrets
+pub fn redact_arguments(configuration: &ConnectorConfig, arguments: &Map<String, Value>) -> Value {
+    let sealed = configuration
+        .sealed_arguments
+        .iter()
+        .flat_map(|sealed_arguments| sealed_arguments.arguments.iter());
+    let forwarded = configuration
+        .forwarded_headers
+        .iter()
+        .flat_map(|forwarded_headers| forwarded_headers.mappings.values());
+    let mut redacted = arguments.clone();
+    for argument_name in sealed.chain(forwarded) {
+        if let Some(value) = redacted.get_mut(argument_name) {
+            *value = Value::String(REDACTED.to_string());
+        }
+    }
+    Value::Object(redacted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_failed_call_log_is_bounded_and_redacted() {
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {},
+            "sealed_arguments": {"arguments": ["api_key"]},
+            "forwarded_headers": {"mappings": {"Authorization": "token"}}
+        }))
+        .unwrap();
+        let arguments = json!({"query": "weather", "api_key": "secret", "token": "Bearer abc"});
+        let request = redact_arguments(&configuration, arguments.as_object().unwrap());
+        assert_eq!(
+            request,
+            json!({"query": "weather", "api_key": REDACTED, "token": REDACTED})
+        );
+
+        let log = FailedCallLog::default();
+        let server_name = McpServerName("search".to_string());
+        for i in 0..=MAX_FAILED_CALLS {
+            log.record(FailedCall::new(
+                &server_name,
+                McpItemKind::Tool,
+                "search",
+                request.clone(),
+                json!(format!("failure {}", i)),
+                Duration::from_millis(5),
+            ));
+        }
+        let calls = log.to_json();
+        let calls = calls.as_array().unwrap();
+        assert_eq!(calls.len(), MAX_FAILED_CALLS);
+        assert_eq!(calls[0]["error"], "failure 1");
+        assert_eq!(calls[0]["kind"], "tool");
+    }
+}
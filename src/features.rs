@@ -0,0 +1,93 @@
+//! Feature flags toggling connector subsystems per deployment, so features can ship disabled
+//! and be enabled for individual deployments with the `features` configuration block.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A subsystem that can be toggled with a feature flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Caching of read-only tool responses
+    ResponseCache,
+    /// Sharing of one upstream call by identical concurrent read-only tool calls
+    RequestCoalescing,
+    /// Expansion of the results of tools with `result_mode: rows` into collection rows
+    RowExpansion,
+}
+
+impl Feature {
+    /// All features that have a flag
+    pub const ALL: &'static [Feature] = &[
+        Feature::ResponseCache,
+        Feature::RequestCoalescing,
+        Feature::RowExpansion,
+    ];
+
+    /// Name of the feature's flag in the configuration
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::ResponseCache => "response_cache",
+            Feature::RequestCoalescing => "request_coalescing",
+            Feature::RowExpansion => "row_expansion",
+        }
+    }
+
+    /// Whether the feature is enabled when its flag is not set
+    pub fn enabled_by_default(self) -> bool {
+        match self {
+            Feature::ResponseCache => true,
+            Feature::RequestCoalescing => true,
+            Feature::RowExpansion => true,
+        }
+    }
+
+    /// Check if a feature is enabled by the configured flags
+    pub fn is_enabled(self, flags: &BTreeMap<String, bool>) -> bool {
+        flags
+            .get(self.name())
+            .copied()
+            .unwrap_or_else(|| self.enabled_by_default())
+    }
+}
+
+/// Configured flags that don't toggle any feature
+pub fn unknown_feature_flags(flags: &BTreeMap<String, bool>) -> Vec<&str> {
+    flags
+        .keys()
+        .map(String::as_str)
+        .filter(|name| !Feature::ALL.iter().any(|feature| feature.name() == *name))
+        .collect()
+}
+
+/// Whether each feature is enabled, by flag name
+pub fn active_features(flags: &BTreeMap<String, bool>) -> BTreeMap<&'static str, bool> {
+    Feature::ALL
+        .iter()
+        .map(|feature| (feature.name(), feature.is_enabled(flags)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_flags() {
+        let flags = BTreeMap::from([
+            ("response_cache".to_string(), false),
+            ("sampling_proxy".to_string(), true),
+        ]);
+        assert!(!Feature::ResponseCache.is_enabled(&flags));
+        assert!(Feature::ResponseCache.is_enabled(&BTreeMap::new()));
+        assert_eq!(unknown_feature_flags(&flags), vec!["sampling_proxy"]);
+        assert_eq!(
+            active_features(&flags),
+            BTreeMap::from([
+                ("request_coalescing", true),
+                ("response_cache", false),
+                ("row_expansion", true)
+            ])
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Shaping of query and mutation responses by the requested fields. Rows are built with every
+//! column of their type, and function results in a single `__value` column; the requested
+//! columns are then selected under their aliases, descending into nested objects and arrays.
+
+use indexmap::IndexMap;
+use ndc_sdk::models;
+use serde_json::Value;
+
+/// Keep the requested fields of the rows of a row set, under their aliases
+pub fn select_rowset_fields(
+    query: &models::Query,
+    rowset: models::RowSet,
+) -> Result<models::RowSet, String> {
+    let rows = match (&query.fields, rowset.rows) {
+        (Some(fields), Some(rows)) => Some(
+            rows.iter()
+                .map(|row| select_row_fields(fields, row))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        (_, rows) => rows,
+    };
+    Ok(models::RowSet { rows, ..rowset })
+}
+
+/// Select the requested fields of a row
+fn select_row_fields(
+    fields: &IndexMap<models::FieldName, models::Field>,
+    row: &IndexMap<models::FieldName, models::RowFieldValue>,
+) -> Result<IndexMap<models::FieldName, models::RowFieldValue>, String> {
+    fields
+        .iter()
+        .map(|(alias, field)| {
+            let value = select_field(field, |column| row.get(column).map(|value| &value.0))?;
+            Ok((alias.clone(), models::RowFieldValue(value)))
+        })
+        .collect()
+}
+
+/// The value of a field, read from its column with `get`
+fn select_field<'a>(
+    field: &models::Field,
+    get: impl Fn(&models::FieldName) -> Option<&'a Value>,
+) -> Result<Value, String> {
+    match field {
+        models::Field::Column { column, fields, .. } => {
+            // Missing columns are null, as for optional fields of structured content
+            let value = get(column).cloned().unwrap_or(Value::Null);
+            match fields {
+                Some(nested_field) => select_nested_field(nested_field, value),
+                None => Ok(value),
+            }
+        }
+        models::Field::Relationship { relationship, .. } => {
+            Err(format!("Relationship {} is not supported", relationship))
+        }
+    }
+}
+
+/// Select the nested fields of an object or of the elements of an array, such as the fields
+/// requested from a procedure result
+pub fn select_nested_field(
+    nested_field: &models::NestedField,
+    value: Value,
+) -> Result<Value, String> {
+    match (nested_field, value) {
+        (_, Value::Null) => Ok(Value::Null),
+        (models::NestedField::Object(nested_object), Value::Object(object)) => {
+            let mut selected = serde_json::Map::new();
+            for (alias, field) in &nested_object.fields {
+                let value = select_field(field, |column| object.get(column.to_string().as_str()))?;
+                selected.insert(alias.to_string(), value);
+            }
+            Ok(Value::Object(selected))
+        }
+        (models::NestedField::Array(nested_array), Value::Array(elements)) => elements
+            .into_iter()
+            .map(|element| select_nested_field(&nested_array.fields, element))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        // Values that don't have the selected shape, such as JSON scalars, are returned whole
+        (_, value) => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_select_rowset_fields() {
+        let query: models::Query = serde_json::from_value(json!({
+            "fields": {
+                "result": {
+                    "type": "column",
+                    "column": "__value",
+                    "fields": {
+                        "type": "object",
+                        "fields": {
+                            "texts": {
+                                "type": "column",
+                                "column": "content",
+                                "fields": {
+                                    "type": "array",
+                                    "fields": {
+                                        "type": "object",
+                                        "fields": {"body": {"type": "column", "column": "text"}}
+                                    }
+                                }
+                            },
+                            "data": {"type": "column", "column": "structured_content"}
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let row = IndexMap::from([(
+            models::FieldName::new("__value".into()),
+            models::RowFieldValue(json!({
+                "content": [{"type": "text", "text": "hello"}],
+                "structured_content": null
+            })),
+        )]);
+        let rowset = models::RowSet {
+            rows: Some(vec![row]),
+            aggregates: None,
+            groups: None,
+        };
+
+        let rowset = select_rowset_fields(&query, rowset).unwrap();
+        let rows = rowset.rows.unwrap();
+        assert_eq!(
+            rows[0][&models::FieldName::new("result".into())].0,
+            json!({"texts": [{"body": "hello"}], "data": null})
+        );
+    }
+
+    #[test]
+    fn test_select_structured_content_fields() {
+        let fields: models::NestedField = serde_json::from_value(json!({
+            "type": "object",
+            "fields": {
+                "structured_content": {
+                    "type": "column",
+                    "column": "structured_content",
+                    "fields": {
+                        "type": "object",
+                        "fields": {
+                            "items": {
+                                "type": "column",
+                                "column": "items",
+                                "fields": {
+                                    "type": "array",
+                                    "fields": {
+                                        "type": "object",
+                                        "fields": {"name": {"type": "column", "column": "name"}}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let output = json!({
+            "content": [{"type": "text", "text": "2 items"}],
+            "structured_content": {
+                "total": 2,
+                "items": [{"name": "a", "size": 1}, {"name": "b", "size": 2}]
+            }
+        });
+        assert_eq!(
+            select_nested_field(&fields, output).unwrap(),
+            json!({"structured_content": {"items": [{"name": "a"}, {"name": "b"}]}})
+        );
+    }
+
+    #[test]
+    fn test_select_procedure_result_fields() {
+        let fields: models::NestedField = serde_json::from_value(json!({
+            "type": "object",
+            "fields": {"failure": {"type": "column", "column": "error"}}
+        }))
+        .unwrap();
+        let result = json!({"content": [{"type": "text", "text": "created"}], "error": null});
+        assert_eq!(
+            select_nested_field(&fields, result).unwrap(),
+            json!({"failure": null})
+        );
+    }
+}
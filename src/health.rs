@@ -0,0 +1,226 @@
+//! Per-server circuit breaking. After repeated failures a server is considered degraded and
+//! calls to its resources, tools and prompts fail fast instead of each timing out, until the
+//! cooldown passes and a single probe call succeeds again.
+
+use rmcp::ServiceError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::{CircuitBreakerConfig, McpServerName};
+
+/// Change of a server's circuit caused by a call outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitTransition {
+    /// The server reached the failure threshold and is now degraded
+    Opened,
+    /// A call to a degraded server succeeded
+    Closed,
+}
+
+#[derive(Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    /// When the call probing a server whose cooldown passed was let through
+    probe_started_at: Option<Instant>,
+}
+
+/// Circuit state of every server. Disabled unless a circuit breaker is configured.
+#[derive(Default)]
+pub struct ServerHealth {
+    config: Option<CircuitBreakerConfig>,
+    circuits: Mutex<HashMap<McpServerName, Circuit>>,
+}
+
+impl ServerHealth {
+    pub fn new(config: Option<CircuitBreakerConfig>) -> Self {
+        Self {
+            config,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How long calls to the server keep failing fast, if its circuit is open
+    pub fn open_for(&self, server_name: &McpServerName) -> Option<Duration> {
+        self.open_for_at(server_name, Instant::now())
+    }
+
+    fn open_for_at(&self, server_name: &McpServerName, now: Instant) -> Option<Duration> {
+        let circuits = self.circuits.lock().unwrap();
+        circuits
+            .get(server_name)
+            .and_then(|circuit| circuit.open_until)
+            .filter(|open_until| *open_until > now)
+            .map(|open_until| open_until - now)
+    }
+
+    /// Check if a call may be made to the server, or how long calls to it keep failing fast.
+    /// Once the cooldown of an open circuit passed, a single call is let through to probe the
+    /// server, and others fail fast until its outcome is recorded. A probe whose outcome is
+    /// never recorded, e.g. because it was cancelled, is replaced after another cooldown.
+    pub fn admit(&self, server_name: &McpServerName) -> Result<(), Duration> {
+        self.admit_at(server_name, Instant::now())
+    }
+
+    fn admit_at(&self, server_name: &McpServerName, now: Instant) -> Result<(), Duration> {
+        let Some(config) = &self.config else {
+            return Ok(());
+        };
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(server_name) else {
+            return Ok(());
+        };
+        let Some(open_until) = circuit.open_until else {
+            return Ok(());
+        };
+        if open_until > now {
+            return Err(open_until - now);
+        }
+        let cooldown = Duration::from_secs(config.cooldown_seconds);
+        if let Some(probe_started_at) = circuit.probe_started_at {
+            if probe_started_at + cooldown > now {
+                return Err(probe_started_at + cooldown - now);
+            }
+        }
+        circuit.probe_started_at = Some(now);
+        Ok(())
+    }
+
+    /// Record the outcome of a call. Errors returned by the server itself don't count as
+    /// failures, only errors reaching it.
+    pub fn record<T>(
+        &self,
+        server_name: &McpServerName,
+        result: &Result<T, ServiceError>,
+    ) -> Option<CircuitTransition> {
+        self.record_at(server_name, result, Instant::now())
+    }
+
+    fn record_at<T>(
+        &self,
+        server_name: &McpServerName,
+        result: &Result<T, ServiceError>,
+        now: Instant,
+    ) -> Option<CircuitTransition> {
+        let config = self.config.as_ref()?;
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(server_name.clone()).or_default();
+        match result {
+            Ok(_) | Err(ServiceError::McpError(_)) => {
+                let was_open = circuit.open_until.is_some();
+                *circuit = Circuit::default();
+                was_open.then_some(CircuitTransition::Closed)
+            }
+            Err(_) => {
+                circuit.consecutive_failures += 1;
+                if circuit.consecutive_failures < config.failure_threshold {
+                    return None;
+                }
+                let was_open = circuit.open_until.is_some();
+                circuit.open_until = Some(now + Duration::from_secs(config.cooldown_seconds));
+                circuit.probe_started_at = None;
+                (!was_open).then_some(CircuitTransition::Opened)
+            }
+        }
+    }
+
+    /// Servers whose circuit is currently open
+    pub fn degraded_servers(&self) -> Vec<String> {
+        let now = Instant::now();
+        let circuits = self.circuits.lock().unwrap();
+        let mut servers = circuits
+            .iter()
+            .filter(|(_, circuit)| {
+                circuit
+                    .open_until
+                    .is_some_and(|open_until| open_until > now)
+            })
+            .map(|(server_name, _)| server_name.0.clone())
+            .collect::<Vec<_>>();
+        servers.sort();
+        servers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_opens_and_recovers() {
+        let health = ServerHealth::new(Some(CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown_seconds: 30,
+        }));
+        let server_name = McpServerName("flaky".to_string());
+        let now = Instant::now();
+        let failure: Result<(), ServiceError> = Err(ServiceError::TransportClosed);
+
+        assert_eq!(health.record_at(&server_name, &failure, now), None);
+        assert_eq!(health.open_for_at(&server_name, now), None);
+        assert_eq!(
+            health.record_at(&server_name, &failure, now),
+            Some(CircuitTransition::Opened)
+        );
+        assert_eq!(
+            health.open_for_at(&server_name, now + Duration::from_secs(10)),
+            Some(Duration::from_secs(20))
+        );
+
+        // After the cooldown a call is let through, and its success closes the circuit
+        let later = now + Duration::from_secs(31);
+        assert_eq!(health.open_for_at(&server_name, later), None);
+        assert_eq!(
+            health.record_at(&server_name, &Ok(()), later),
+            Some(CircuitTransition::Closed)
+        );
+        assert_eq!(health.record_at(&server_name, &failure, later), None);
+    }
+
+    #[test]
+    fn test_single_probe_while_half_open() {
+        let health = ServerHealth::new(Some(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown_seconds: 30,
+        }));
+        let server_name = McpServerName("flaky".to_string());
+        let now = Instant::now();
+        let failure: Result<(), ServiceError> = Err(ServiceError::TransportClosed);
+
+        assert_eq!(health.admit_at(&server_name, now), Ok(()));
+        health.record_at(&server_name, &failure, now);
+        assert_eq!(
+            health.admit_at(&server_name, now + Duration::from_secs(10)),
+            Err(Duration::from_secs(20))
+        );
+
+        // Only one call probes the server once the cooldown passed
+        let later = now + Duration::from_secs(30);
+        assert_eq!(health.admit_at(&server_name, later), Ok(()));
+        assert!(health.admit_at(&server_name, later).is_err());
+
+        // A failed probe opens the circuit for another cooldown
+        health.record_at(&server_name, &failure, later);
+        assert_eq!(
+            health.admit_at(&server_name, later),
+            Err(Duration::from_secs(30))
+        );
+
+        // A probe whose outcome is never recorded is replaced after a cooldown
+        let probe = later + Duration::from_secs(30);
+        assert_eq!(health.admit_at(&server_name, probe), Ok(()));
+        assert!(health
+            .admit_at(&server_name, probe + Duration::from_secs(29))
+            .is_err());
+        assert_eq!(
+            health.admit_at(&server_name, probe + Duration::from_secs(30)),
+            Ok(())
+        );
+
+        // A successful probe closes the circuit
+        health.record_at(&server_name, &Ok(()), probe);
+        assert_eq!(health.admit_at(&server_name, probe), Ok(()));
+        assert_eq!(health.admit_at(&server_name, probe), Ok(()));
+    }
+}
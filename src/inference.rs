@@ -0,0 +1,232 @@
+//! Inference of output schemas for tools that don't declare an `outputSchema`. Every
+//! structured result observed widens the inferred type, so the schema converges on the
+//! shapes the tool actually returns.
+
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::config::McpServerName;
+
+/// A type inferred from observed JSON values
+#[derive(Debug, Clone, PartialEq)]
+pub enum InferredType {
+    /// No values observed yet, e.g. the elements of empty arrays
+    Unknown,
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array(Box<InferredType>),
+    Object {
+        /// Inferred fields with the number of objects they were present in
+        fields: BTreeMap<String, (InferredType, usize)>,
+        /// Number of objects observed
+        observations: usize,
+    },
+    Nullable(Box<InferredType>),
+    /// Values of incompatible types were observed
+    Any,
+}
+
+impl InferredType {
+    /// Infer the type of a single value
+    pub fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Null => InferredType::Null,
+            Value::Bool(_) => InferredType::Boolean,
+            Value::Number(number) if number.is_i64() || number.is_u64() => InferredType::Integer,
+            Value::Number(_) => InferredType::Number,
+            Value::String(_) => InferredType::String,
+            Value::Array(items) => InferredType::Array(Box::new(
+                items
+                    .iter()
+                    .map(InferredType::from_value)
+                    .fold(InferredType::Unknown, InferredType::widen),
+            )),
+            Value::Object(object) => InferredType::Object {
+                fields: object
+                    .iter()
+                    .map(|(name, value)| (name.clone(), (InferredType::from_value(value), 1)))
+                    .collect(),
+                observations: 1,
+            },
+        }
+    }
+
+    /// Widen two inferred types to a type accepting values of both
+    pub fn widen(self, other: Self) -> Self {
+        match (self, other) {
+            (InferredType::Unknown, other) | (other, InferredType::Unknown) => other,
+            (InferredType::Any, _) | (_, InferredType::Any) => InferredType::Any,
+            (InferredType::Null, InferredType::Null) => InferredType::Null,
+            (InferredType::Null, other) | (other, InferredType::Null) => other.into_nullable(),
+            (InferredType::Nullable(left), right) | (right, InferredType::Nullable(left)) => {
+                left.widen(right).into_nullable()
+            }
+            (InferredType::Integer, InferredType::Number)
+            | (InferredType::Number, InferredType::Integer) => InferredType::Number,
+            (InferredType::Array(left), InferredType::Array(right)) => {
+                InferredType::Array(Box::new(left.widen(*right)))
+            }
+            (
+                InferredType::Object {
+                    fields: mut left_fields,
+                    observations: left_observations,
+                },
+                InferredType::Object {
+                    fields: right_fields,
+                    observations: right_observations,
+                },
+            ) => {
+                for (name, (right_type, right_seen)) in right_fields {
+                    let field = match left_fields.remove(&name) {
+                        Some((left_type, left_seen)) => {
+                            (left_type.widen(right_type), left_seen + right_seen)
+                        }
+                        None => (right_type, right_seen),
+                    };
+                    left_fields.insert(name, field);
+                }
+                InferredType::Object {
+                    fields: left_fields,
+                    observations: left_observations + right_observations,
+                }
+            }
+            (left, right) if left == right => left,
+            _ => InferredType::Any,
+        }
+    }
+
+    fn into_nullable(self) -> Self {
+        match self {
+            InferredType::Nullable(_) | InferredType::Any | InferredType::Null => self,
+            other => InferredType::Nullable(Box::new(other)),
+        }
+    }
+
+    /// Convert the inferred type to a JSON schema. Object fields missing from some observed
+    /// objects are optional.
+    pub fn to_json_schema(&self) -> Value {
+        match self {
+            InferredType::Unknown | InferredType::Any => json!({}),
+            InferredType::Null => json!({"type": "null"}),
+            InferredType::Boolean => json!({"type": "boolean"}),
+            InferredType::Integer => json!({"type": "integer"}),
+            InferredType::Number => json!({"type": "number"}),
+            InferredType::String => json!({"type": "string"}),
+            InferredType::Array(element_type) => {
+                json!({"type": "array", "items": element_type.to_json_schema()})
+            }
+            InferredType::Object {
+                fields,
+                observations,
+            } => {
+                let properties = fields
+                    .iter()
+                    .map(|(name, (field_type, _))| (name.clone(), field_type.to_json_schema()))
+                    .collect::<Map<_, _>>();
+                let required = fields
+                    .iter()
+                    .filter(|(_, (_, seen))| seen == observations)
+                    .map(|(name, _)| name.clone())
+                    .collect::<Vec<_>>();
+                json!({"type": "object", "properties": properties, "required": required})
+            }
+            InferredType::Nullable(underlying_type) => {
+                json!({"anyOf": [underlying_type.to_json_schema(), {"type": "null"}]})
+            }
+        }
+    }
+}
+
+/// Inferred output types of the tools without an `outputSchema`, per server
+#[derive(Default)]
+pub struct OutputSchemaInference {
+    inferred: Mutex<HashMap<McpServerName, HashMap<String, InferredType>>>,
+}
+
+impl OutputSchemaInference {
+    /// Widen the inferred output type of a tool with an observed structured result
+    pub fn observe(&self, server_name: &McpServerName, tool_name: &str, value: &Value) {
+        let mut inferred = self.inferred.lock().unwrap();
+        let tools = inferred.entry(server_name.clone()).or_default();
+        let inferred_type = tools
+            .remove(tool_name)
+            .unwrap_or(InferredType::Unknown)
+            .widen(InferredType::from_value(value));
+        tools.insert(tool_name.to_string(), inferred_type);
+    }
+
+    /// The inferred output schemas as `{server: {tool: schema}}`
+    pub fn json_schemas(&self) -> Value {
+        let inferred = self.inferred.lock().unwrap();
+        let servers = inferred
+            .iter()
+            .map(|(server_name, tools)| {
+                let tools = tools
+                    .iter()
+                    .map(|(tool_name, inferred_type)| {
+                        (tool_name.clone(), inferred_type.to_json_schema())
+                    })
+                    .collect::<Map<_, _>>();
+                (server_name.0.clone(), Value::Object(tools))
+            })
+            .collect::<Map<_, _>>();
+        Value::Object(servers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(values: &[Value]) -> InferredType {
+        values
+            .iter()
+            .map(InferredType::from_value)
+            .fold(InferredType::Unknown, InferredType::widen)
+    }
+
+    #[test]
+    fn test_widen_primitives() {
+        assert_eq!(infer(&[json!(1), json!(2.5)]), InferredType::Number);
+        assert_eq!(
+            infer(&[json!("a"), json!(null)]),
+            InferredType::Nullable(Box::new(InferredType::String))
+        );
+        assert_eq!(infer(&[json!("a"), json!(1)]), InferredType::Any);
+    }
+
+    #[test]
+    fn test_widen_objects_and_arrays() {
+        let inferred = infer(&[
+            json!({"id": 1, "tags": [], "owner": {"name": "a"}}),
+            json!({"id": 2.5, "tags": ["x"], "owner": null}),
+            json!({"id": 3, "tags": ["y"], "owner": {"name": "b"}, "extra": true}),
+        ]);
+        assert_eq!(
+            inferred.to_json_schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "extra": {"type": "boolean"},
+                    "id": {"type": "number"},
+                    "owner": {
+                        "anyOf": [
+                            {
+                                "type": "object",
+                                "properties": {"name": {"type": "string"}},
+                                "required": ["name"]
+                            },
+                            {"type": "null"}
+                        ]
+                    },
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                },
+                "required": ["id", "owner", "tags"]
+            })
+        );
+    }
+}
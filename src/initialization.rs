@@ -0,0 +1,140 @@
+//! Reporting of MCP server initialization failures. Every server is initialized even if
+//! another one fails, so a single report lists everything that needs fixing.
+
+use http::StatusCode;
+use ndc_sdk::connector::ErrorResponse;
+use serde::Serialize;
+
+use crate::config::McpServerName;
+use crate::transport::InitializationTimeout;
+
+/// The initialization stage at which a server failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InitializationStage {
+    /// Starting the server process or connecting to it, and the MCP handshake
+    Connect,
+    /// Listing the resources, tools and prompts of the server
+    Introspect,
+}
+
+/// The kind of error a server failed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InitializationErrorClass {
+    /// The server did not complete the MCP handshake in time
+    Timeout,
+    /// The server could not be started or reached, or rejected the handshake
+    Transport,
+    /// The server returned an error for an MCP request
+    Protocol,
+}
+
+/// A server that failed to initialize
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInitializationFailure {
+    pub server: String,
+    pub stage: InitializationStage,
+    pub error_class: InitializationErrorClass,
+    pub message: String,
+}
+
+impl ServerInitializationFailure {
+    /// A failure to create the MCP client of a server
+    pub fn connect(server_name: &McpServerName, err: &anyhow::Error) -> Self {
+        let error_class = if err.downcast_ref::<InitializationTimeout>().is_some() {
+            InitializationErrorClass::Timeout
+        } else {
+            InitializationErrorClass::Transport
+        };
+        Self {
+            server: server_name.0.clone(),
+            stage: InitializationStage::Connect,
+            error_class,
+            message: err.to_string(),
+        }
+    }
+
+    /// A failure to introspect a connected server
+    pub fn introspect(server_name: &McpServerName, err: &ErrorResponse) -> Self {
+        Self {
+            server: server_name.0.clone(),
+            stage: InitializationStage::Introspect,
+            error_class: InitializationErrorClass::Protocol,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Failures collected while initializing all servers
+#[derive(Debug, Default, Serialize)]
+pub struct InitializationReport {
+    pub failures: Vec<ServerInitializationFailure>,
+}
+
+impl InitializationReport {
+    /// Record a server that failed to initialize
+    pub fn push(&mut self, failure: ServerInitializationFailure) {
+        tracing::error!(
+            server = %failure.server,
+            stage = ?failure.stage,
+            error_class = ?failure.error_class,
+            "Failed to initialize MCP server: {}",
+            failure.message
+        );
+        self.failures.push(failure);
+    }
+
+    /// Fail with an error listing every failed server, if any
+    pub fn into_result(self) -> Result<(), ErrorResponse> {
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        let servers = self
+            .failures
+            .iter()
+            .map(|failure| failure.server.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = format!(
+            "Failed to initialize {} MCP server(s): {}",
+            self.failures.len(),
+            servers
+        );
+        Err(ErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            message,
+            serde_json::to_value(&self).unwrap_or(serde_json::Value::Null),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use serde_json::json;
+
+    #[test]
+    fn test_connect_failure_error_class() {
+        let server_name = McpServerName("slow".to_string());
+        let failure =
+            ServerInitializationFailure::connect(&server_name, &anyhow!(InitializationTimeout));
+        assert_eq!(failure.error_class, InitializationErrorClass::Timeout);
+
+        let failure = ServerInitializationFailure::connect(
+            &server_name,
+            &anyhow!("Failed to start MCP server: No such file or directory"),
+        );
+        assert_eq!(failure.error_class, InitializationErrorClass::Transport);
+        assert_eq!(
+            serde_json::to_value(&failure).unwrap(),
+            json!({
+                "server": "slow",
+                "stage": "connect",
+                "error_class": "transport",
+                "message": "Failed to start MCP server: No such file or directory"
+            })
+        );
+    }
+}
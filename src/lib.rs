@@ -1,5 +1,40 @@
+pub mod aggregate;
+pub mod cache;
+pub mod call_sampling;
+pub mod chunking;
 pub mod config;
+pub mod config_error;
 pub mod connector;
+pub mod debug_endpoint;
+pub mod decrypt;
+pub mod diagnostics;
+pub mod events;
+pub mod explain;
+pub mod failed_calls;
+pub mod features;
+pub mod fields;
+pub mod health;
+pub mod inference;
+pub mod initialization;
+pub mod materialize;
+pub mod operation_error;
+pub mod ordering;
+pub mod pagination;
+pub mod predicate;
+pub mod propose_output_schemas;
+pub mod request_validation;
+pub mod response_limit;
+pub mod retry;
 pub mod schema;
+pub mod schema_export;
+pub mod schema_hash;
+pub mod server_info;
+pub mod sessions;
+pub mod single_flight;
+pub mod smoke_test;
+pub mod startup_summary;
 pub mod state;
+pub mod tool_error;
 pub mod transport;
+pub mod update;
+pub mod uri_template;
@@ -1,12 +1,24 @@
 use ndc_sdk::default_main::default_main_with;
 use std::process::ExitCode;
 
-use ndc_mcp_rs::connector;
+use ndc_mcp_rs::{connector, propose_output_schemas, schema_export, smoke_test, update};
 
 /// Run the NDC MCP connector using the default_main_with function from ndc-sdk
 #[tokio::main]
 async fn main() -> ExitCode {
-    match default_main_with(connector::McpConnectorSetup).await {
+    // Commands specific to this connector are handled here; everything else goes to the
+    // ndc-sdk CLI
+    match std::env::args().nth(1).as_deref() {
+        Some("smoke-test") => return smoke_test::run(std::env::args().skip(1)).await,
+        Some("update") => return update::run(std::env::args().skip(1)).await,
+        Some("schema") => return schema_export::run(std::env::args().skip(1)).await,
+        Some("propose-output-schemas") => {
+            return propose_output_schemas::run(std::env::args().skip(1)).await
+        }
+        _ => {}
+    }
+
+    match default_main_with(connector::McpConnectorSetup::default()).await {
         Ok(()) => ExitCode::SUCCESS,
         Err(err) => {
             eprintln!("Error: {err}");
@@ -0,0 +1,196 @@
+//! Materialization of resource content into typed rows. A resource configured with `rows` is
+//! parsed as a JSON array of objects or as CSV when it is read, and its rows are then filtered,
+//! ordered and paginated by the connector like those of any other collection.
+
+use indexmap::IndexMap;
+use ndc_sdk::models;
+use serde_json::Value;
+
+use crate::config::{ColumnType, ResourceRowsConfig, RowsFormat};
+
+/// Parse the text content of a resource into rows with the configured columns
+pub fn parse_rows(
+    rows_config: &ResourceRowsConfig,
+    text: &str,
+) -> Result<Vec<IndexMap<models::FieldName, models::RowFieldValue>>, String> {
+    let records = match rows_config.format {
+        RowsFormat::Json => json_records(text)?,
+        RowsFormat::Csv => csv_records(text)?,
+    };
+    records
+        .into_iter()
+        .enumerate()
+        .map(|(index, record)| {
+            rows_config
+                .columns
+                .iter()
+                .map(|(column, column_type)| {
+                    let value = record.get(column).cloned().unwrap_or(Value::Null);
+                    let value = column_value(*column_type, value).map_err(|value| {
+                        format!(
+                            "Column {} of row {} must be {}, got {}",
+                            column,
+                            index,
+                            column_type.scalar_type(),
+                            value
+                        )
+                    })?;
+                    Ok((
+                        models::FieldName::new(column.as_str().into()),
+                        models::RowFieldValue(value),
+                    ))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The objects of a JSON array
+fn json_records(text: &str) -> Result<Vec<serde_json::Map<String, Value>>, String> {
+    let value =
+        serde_json::from_str::<Value>(text).map_err(|err| format!("Invalid JSON: {}", err))?;
+    let Value::Array(elements) = value else {
+        return Err("The content must be a JSON array of objects".to_string());
+    };
+    elements
+        .into_iter()
+        .map(|element| match element {
+            Value::Object(object) => Ok(object),
+            _ => Err("The content must be a JSON array of objects".to_string()),
+        })
+        .collect()
+}
+
+/// The records of CSV with a header line, as string values keyed by column name. Empty
+/// fields are null.
+fn csv_records(text: &str) -> Result<Vec<serde_json::Map<String, Value>>, String> {
+    let mut lines = csv_lines(text)?.into_iter();
+    let Some(header) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    Ok(lines
+        .filter(|fields| fields.iter().any(|field| !field.is_empty()))
+        .map(|fields| {
+            header
+                .iter()
+                .zip(fields)
+                .filter(|(_, field)| !field.is_empty())
+                .map(|(column, field)| (column.clone(), Value::String(field)))
+                .collect()
+        })
+        .collect())
+}
+
+/// Split CSV into the fields of each line. Fields may be quoted, with doubled quotes for
+/// quotes and with commas and line breaks in them.
+fn csv_lines(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut lines = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                fields.push(std::mem::take(&mut field));
+                lines.push(std::mem::take(&mut fields));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unterminated quoted CSV field".to_string());
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        lines.push(fields);
+    }
+    Ok(lines)
+}
+
+/// Convert a value to the type of a column, accepting numbers and booleans written as
+/// strings. Returns the value back if it can't be converted.
+fn column_value(column_type: ColumnType, value: Value) -> Result<Value, Value> {
+    if value.is_null() {
+        return Ok(value);
+    }
+    match (column_type, value) {
+        (ColumnType::Json, value) => Ok(value),
+        (ColumnType::String, Value::String(text)) => Ok(Value::String(text)),
+        (ColumnType::String, value @ (Value::Number(_) | Value::Bool(_))) => {
+            Ok(Value::String(value.to_string()))
+        }
+        (ColumnType::Int, Value::Number(number)) if number.is_i64() => Ok(Value::Number(number)),
+        (ColumnType::Int, Value::String(text)) => match text.trim().parse::<i64>() {
+            Ok(number) => Ok(Value::from(number)),
+            Err(_) => Err(Value::String(text)),
+        },
+        (ColumnType::Float, Value::Number(number)) => Ok(Value::Number(number)),
+        (ColumnType::Float, Value::String(text)) => {
+            match text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+            {
+                Some(number) => Ok(Value::Number(number)),
+                None => Err(Value::String(text)),
+            }
+        }
+        (ColumnType::Boolean, Value::Bool(value)) => Ok(Value::Bool(value)),
+        (ColumnType::Boolean, Value::String(text)) => {
+            match text.trim().to_ascii_lowercase().as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => Err(Value::String(text)),
+            }
+        }
+        (_, value) => Err(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_csv_rows() {
+        let rows_config: ResourceRowsConfig = serde_json::from_value(json!({
+            "format": "csv",
+            "columns": {"city": "string", "population": "int", "capital": "boolean"}
+        }))
+        .unwrap();
+        let text = "city,population,capital\r\n\"Paris, FR\",2102650,true\nLyon,,false\n";
+        let rows = parse_rows(&rows_config, text).unwrap();
+        let values = rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(column, value)| (column.to_string(), value.0))
+                    .collect::<serde_json::Map<_, _>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            Value::from(values),
+            json!([
+                {"capital": true, "city": "Paris, FR", "population": 2102650},
+                {"capital": false, "city": "Lyon", "population": null}
+            ])
+        );
+
+        assert_eq!(
+            parse_rows(&rows_config, "city,population\nLyon,many\n"),
+            Err("Column population of row 0 must be Int, got \"many\"".to_string())
+        );
+    }
+}
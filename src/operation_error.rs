@@ -0,0 +1,234 @@
+//! Errors of individual operations. In best-effort mutation mode a failed procedure returns
+//! its error in the `error` field of its result instead of failing the whole mutation, so
+//! batch automations can act on each item.
+
+use http::StatusCode;
+use ndc_sdk::connector::ErrorResponse;
+use rmcp::{
+    model::{ErrorData, RequestId},
+    ServiceError,
+};
+use serde::Serialize;
+
+use crate::tool_error::ToolError;
+
+/// The kind of error an operation failed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationErrorClass {
+    /// The procedure or tool doesn't exist
+    NotFound,
+    /// The arguments could not be prepared, e.g. a sealed argument could not be decrypted
+    InvalidArguments,
+    /// The connector is offline
+    Unavailable,
+    /// The connector configuration for the tool is invalid
+    Configuration,
+    /// The connection to the MCP server was lost
+    Transport,
+    /// The MCP server did not respond within the configured timeout
+    Timeout,
+    /// The MCP server returned an error
+    Protocol,
+    /// The tool returned an error result
+    Tool,
+    /// The tool result exceeded the response limit of the server
+    ResponseTooLarge,
+}
+
+/// A failed operation
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationError {
+    pub error_class: OperationErrorClass,
+    /// The JSON-RPC error code returned by the MCP server, or the numeric code of a tool error,
+    /// if any
+    pub code: Option<i32>,
+    pub message: String,
+    /// The JSON-RPC id of the failed request, to correlate the failure with the server logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<serde_json::Value>,
+    /// The error id in the `data` of the error returned by the MCP server, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_error_id: Option<serde_json::Value>,
+    /// The data of the error returned by the tool, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    #[serde(skip)]
+    status: StatusCode,
+}
+
+impl OperationError {
+    pub fn new(status: StatusCode, error_class: OperationErrorClass, message: String) -> Self {
+        Self {
+            error_class,
+            code: None,
+            message,
+            request_id: None,
+            server_error_id: None,
+            data: None,
+            status,
+        }
+    }
+
+    /// Set the JSON-RPC id of the failed request
+    pub fn with_request_id(mut self, request_id: Option<RequestId>) -> Self {
+        self.request_id = request_id.map(RequestId::into_json_value);
+        self
+    }
+
+    /// The procedure or tool doesn't exist
+    pub fn not_found(message: String) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            OperationErrorClass::NotFound,
+            message,
+        )
+    }
+
+    /// A request to the MCP server failed. `context` describes the request.
+    pub fn from_service_error(context: &str, err: &ServiceError) -> Self {
+        let message = format!("{}: {}", context, err);
+        match err {
+            ServiceError::TransportClosed | ServiceError::TransportSend(_) => Self::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                OperationErrorClass::Transport,
+                message,
+            ),
+            ServiceError::Timeout { .. } => Self::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                OperationErrorClass::Timeout,
+                message,
+            ),
+            ServiceError::McpError(ErrorData { code, data, .. }) => Self {
+                code: Some(code.0),
+                server_error_id: data.as_ref().and_then(server_error_id),
+                ..Self::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    OperationErrorClass::Protocol,
+                    message,
+                )
+            },
+            _ => Self::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                OperationErrorClass::Protocol,
+                message,
+            ),
+        }
+    }
+}
+
+impl From<ToolError> for OperationError {
+    fn from(err: ToolError) -> Self {
+        Self {
+            code: err.code.as_deref().and_then(|code| code.parse().ok()),
+            data: err.data,
+            ..Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                OperationErrorClass::Tool,
+                err.message,
+            )
+        }
+    }
+}
+
+/// The error id servers commonly include in the `data` of errors
+fn server_error_id(data: &serde_json::Value) -> Option<serde_json::Value> {
+    [
+        "errorId",
+        "error_id",
+        "requestId",
+        "request_id",
+        "traceId",
+        "trace_id",
+    ]
+    .into_iter()
+    .find_map(|key| data.get(key))
+    .filter(|id| id.is_string() || id.is_number())
+    .cloned()
+}
+
+impl From<OperationError> for ErrorResponse {
+    fn from(err: OperationError) -> Self {
+        let details = serde_json::to_value(&err).unwrap_or(serde_json::Value::Null);
+        ErrorResponse::new(err.status, err.message, details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::ErrorCode;
+    use serde_json::json;
+
+    #[test]
+    fn test_operation_error_from_service_error() {
+        let err = ServiceError::McpError(ErrorData::new(
+            ErrorCode::INVALID_PARAMS,
+            "missing field `path`",
+            None,
+        ));
+        let operation_error = OperationError::from_service_error("Failed to execute tool", &err);
+        assert_eq!(
+            serde_json::to_value(&operation_error).unwrap(),
+            json!({
+                "error_class": "protocol",
+                "code": -32602,
+                "message": "Failed to execute tool: Mcp error: -32602: missing field `path`"
+            })
+        );
+
+        let operation_error = OperationError::from_service_error(
+            "Failed to execute tool",
+            &ServiceError::TransportClosed,
+        );
+        assert_eq!(operation_error.error_class, OperationErrorClass::Transport);
+        assert_eq!(operation_error.code, None);
+
+        let operation_error = OperationError::from_service_error(
+            "Failed to execute tool",
+            &ServiceError::Timeout {
+                timeout: std::time::Duration::from_secs(30),
+            },
+        );
+        assert_eq!(operation_error.error_class, OperationErrorClass::Timeout);
+        assert_eq!(operation_error.status, StatusCode::GATEWAY_TIMEOUT);
+
+        // Failures can be correlated with the request and the server's error
+        let err = ServiceError::McpError(ErrorData::new(
+            ErrorCode::INTERNAL_ERROR,
+            "database unavailable",
+            Some(json!({"errorId": "err-7f3a"})),
+        ));
+        let operation_error = OperationError::from_service_error("Failed to execute tool", &err)
+            .with_request_id(Some(RequestId::Number(42)));
+        assert_eq!(operation_error.request_id, Some(json!(42)));
+        assert_eq!(operation_error.server_error_id, Some(json!("err-7f3a")));
+    }
+
+    #[test]
+    fn test_operation_error_from_tool_error() {
+        let operation_error = OperationError::from(ToolError {
+            code: Some("404".to_string()),
+            message: "Repository not found".to_string(),
+            data: Some(json!({"owner": "hasura"})),
+        });
+        assert_eq!(operation_error.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            serde_json::to_value(&operation_error).unwrap(),
+            json!({
+                "error_class": "tool",
+                "code": 404,
+                "message": "Repository not found",
+                "data": {"owner": "hasura"}
+            })
+        );
+
+        // Codes that aren't numbers are only kept by the tool_error field
+        let operation_error = OperationError::from(ToolError {
+            code: Some("NOT_FOUND".to_string()),
+            message: "Repository not found".to_string(),
+            data: None,
+        });
+        assert_eq!(operation_error.code, None);
+    }
+}
@@ -0,0 +1,159 @@
+//! Client-side ordering and pagination of collection rows, after they are filtered by the
+//! query predicate.
+
+use indexmap::IndexMap;
+use ndc_sdk::models;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+use crate::predicate::{order_values, row_value};
+
+type Row = IndexMap<models::FieldName, models::RowFieldValue>;
+
+/// Sort rows by the columns of an `order_by`. Nulls sort after other values in ascending
+/// order, and values that can't be ordered are equal.
+pub fn order_rows(order_by: &models::OrderBy, rows: Vec<Row>) -> Result<Vec<Row>, String> {
+    let mut keyed_rows = rows
+        .into_iter()
+        .map(|row| {
+            let keys = order_by
+                .elements
+                .iter()
+                .map(|element| {
+                    let models::OrderByTarget::Column {
+                        name,
+                        field_path,
+                        path,
+                        ..
+                    } = &element.target
+                    else {
+                        return Err("Only ordering by columns is supported".to_string());
+                    };
+                    if !path.is_empty() {
+                        return Err("Ordering by related columns is not supported".to_string());
+                    }
+                    row_value(&row, name, field_path.as_deref()).cloned()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((keys, row))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    keyed_rows.sort_by(|(left, _), (right, _)| {
+        order_by
+            .elements
+            .iter()
+            .zip(left.iter().zip(right))
+            .map(|(element, (left, right))| {
+                let ordering = order_nullable(left, right);
+                match element.order_direction {
+                    models::OrderDirection::Asc => ordering,
+                    models::OrderDirection::Desc => ordering.reverse(),
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    Ok(keyed_rows.into_iter().map(|(_, row)| row).collect())
+}
+
+/// Skip `offset` rows and keep at most `limit` rows
+pub fn paginate_rows(offset: Option<u32>, limit: Option<u32>, rows: Vec<Row>) -> Vec<Row> {
+    rows.into_iter()
+        .skip(offset.unwrap_or(0) as usize)
+        .take(limit.map_or(usize::MAX, |limit| limit as usize))
+        .collect()
+}
+
+fn order_nullable(left: &Value, right: &Value) -> Ordering {
+    match (left.is_null(), right.is_null()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => order_values(left, right).unwrap_or(Ordering::Equal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_order_and_paginate_rows() {
+        let rows = [json!(3), json!(null), json!(1), json!(2)]
+            .into_iter()
+            .map(|value| {
+                IndexMap::from([(
+                    models::FieldName::new("rank".into()),
+                    models::RowFieldValue(value),
+                )])
+            })
+            .collect::<Vec<_>>();
+        let order_by: models::OrderBy = serde_json::from_value(json!({
+            "elements": [{
+                "order_direction": "desc",
+                "target": {"type": "column", "name": "rank", "path": []}
+            }]
+        }))
+        .unwrap();
+        let rows = order_rows(&order_by, rows).unwrap();
+        let rows = paginate_rows(Some(1), Some(2), rows);
+        let ranks = rows
+            .iter()
+            .map(|row| row[&models::FieldName::new("rank".into())].0.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ranks, vec![json!(3), json!(2)]);
+
+        assert!(paginate_rows(Some(5), None, rows).is_empty());
+    }
+
+    #[test]
+    fn test_order_rows_by_nested_fields() {
+        let rows = [
+            json!({"name": "b", "owner": {"login": "ann"}}),
+            json!({"name": "a", "owner": {"login": "bob"}}),
+            json!({"name": "c", "owner": {"login": "ann"}}),
+            json!({"name": "d", "owner": null}),
+        ]
+        .into_iter()
+        .map(|value| {
+            value
+                .as_object()
+                .unwrap()
+                .iter()
+                .map(|(column, value)| {
+                    (
+                        models::FieldName::new(column.as_str().into()),
+                        models::RowFieldValue(value.clone()),
+                    )
+                })
+                .collect::<Row>()
+        })
+        .collect::<Vec<_>>();
+        let order_by: models::OrderBy = serde_json::from_value(json!({
+            "elements": [
+                {
+                    "order_direction": "asc",
+                    "target": {
+                        "type": "column",
+                        "name": "owner",
+                        "field_path": ["login"],
+                        "path": []
+                    }
+                },
+                {
+                    "order_direction": "desc",
+                    "target": {"type": "column", "name": "name", "path": []}
+                }
+            ]
+        }))
+        .unwrap();
+        let names = order_rows(&order_by, rows)
+            .unwrap()
+            .iter()
+            .map(|row| row[&models::FieldName::new("name".into())].0.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![json!("c"), json!("b"), json!("a"), json!("d")]);
+    }
+}
@@ -0,0 +1,288 @@
+//! Page numbers for tools paginated by opaque cursors. Functions of tools configured with
+//! `pagination` take `page` and `pagination_session` arguments instead of the cursor. The
+//! connector walks the pages up to the requested one, remembering the cursor of each page per
+//! session, so that later requests of the session continue from the closest known page.
+
+use indexmap::IndexMap;
+use rmcp::model::{CallToolRequestParam, CallToolResult, Tool};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
+use std::sync::Mutex;
+
+use crate::config::{McpServerName, PaginationConfig};
+
+/// Sessions whose cursors are remembered. The least recently used are forgotten first.
+const MAX_SESSIONS: usize = 1000;
+
+/// A requested page of a paginated tool
+#[derive(Debug, PartialEq)]
+pub struct PageRequest {
+    pub page: u64,
+    pub session: Option<String>,
+}
+
+impl PageRequest {
+    /// Take the page arguments out of the arguments of a tool call
+    pub fn take(
+        arguments: &mut serde_json::Map<String, Value>,
+        pagination: &PaginationConfig,
+    ) -> Result<Self, String> {
+        let page_argument = &pagination.page_argument;
+        let page = match arguments.remove(page_argument) {
+            None | Some(Value::Null) => 1,
+            Some(page) => page
+                .as_u64()
+                .filter(|page| *page >= 1)
+                .ok_or_else(|| format!("Argument {} must be a positive integer", page_argument))?,
+        };
+        if page > pagination.max_pages {
+            return Err(format!(
+                "Argument {} must be at most {}",
+                page_argument, pagination.max_pages
+            ));
+        }
+        let session = match arguments.remove(&pagination.session_argument) {
+            None | Some(Value::Null) => None,
+            Some(Value::String(session)) => Some(session),
+            Some(_) => {
+                return Err(format!(
+                    "Argument {} must be a string",
+                    pagination.session_argument
+                ))
+            }
+        };
+        Ok(Self { page, session })
+    }
+}
+
+/// The page or pagination session argument of a paginated tool that the tool already has an
+/// argument of the same name for. The tool's own argument would be shadowed.
+pub fn conflicting_page_argument<'a>(
+    tool: &Tool,
+    pagination: &'a PaginationConfig,
+) -> Option<&'a str> {
+    let properties = tool.input_schema.get("properties")?.as_object()?;
+    [&pagination.page_argument, &pagination.session_argument]
+        .into_iter()
+        .find(|argument| properties.contains_key(argument.as_str()))
+        .map(String::as_str)
+}
+
+/// Why the requested page of a paginated tool could not be returned
+#[derive(Debug)]
+pub enum PageError<E> {
+    /// A call for a page failed
+    Call(E),
+    /// The tool has fewer pages than requested
+    PastLastPage { last_page: u64 },
+    /// The tool returned the cursor of an earlier page, and would be called forever
+    RepeatedCursor { page: u64 },
+}
+
+/// Call a paginated tool page by page from the closest page whose cursor the session
+/// remembers, up to the requested page, and return the result of that page. `call_page` is
+/// called with the cursor of each page, `None` for the first one. Error results are returned
+/// as they are.
+pub async fn follow_pages<E, F, Fut>(
+    memory: &PaginationMemory,
+    key: Option<&str>,
+    requested_page: u64,
+    mut call_page: F,
+) -> Result<CallToolResult, PageError<E>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<CallToolResult, E>>,
+{
+    let (mut page, mut cursor) = memory.closest_page(key, requested_page);
+    let mut seen_cursors = cursor.iter().cloned().collect::<HashSet<_>>();
+    loop {
+        let result = call_page(cursor).await.map_err(PageError::Call)?;
+        if page >= requested_page || result.is_error == Some(true) {
+            return Ok(result);
+        }
+        let Some(next_cursor) = next_cursor(&result) else {
+            return Err(PageError::PastLastPage { last_page: page });
+        };
+        page += 1;
+        if !seen_cursors.insert(next_cursor.clone()) {
+            return Err(PageError::RepeatedCursor { page });
+        }
+        if let Some(key) = key {
+            memory.remember(key, page, next_cursor.clone());
+        }
+        cursor = Some(next_cursor);
+    }
+}
+
+/// The cursors of the pages of each pagination session
+#[derive(Default)]
+pub struct PaginationMemory {
+    sessions: Mutex<IndexMap<String, BTreeMap<u64, String>>>,
+}
+
+impl PaginationMemory {
+    /// The highest page up to `page` whose cursor is known, with its cursor. The first page
+    /// needs no cursor.
+    pub fn closest_page(&self, key: Option<&str>, page: u64) -> (u64, Option<String>) {
+        key.and_then(|key| {
+            let sessions = self.sessions.lock().unwrap();
+            let (page, cursor) = sessions.get(key)?.range(..=page).next_back()?;
+            Some((*page, Some(cursor.clone())))
+        })
+        .unwrap_or((1, None))
+    }
+
+    /// Remember the cursor of a page of a session
+    pub fn remember(&self, key: &str, page: u64, cursor: String) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut cursors = sessions.shift_remove(key).unwrap_or_default();
+        cursors.insert(page, cursor);
+        sessions.insert(key.to_string(), cursors);
+        while sessions.len() > MAX_SESSIONS {
+            sessions.shift_remove_index(0);
+        }
+    }
+}
+
+/// The key of a pagination session of a tool called with the given arguments, so a session
+/// doesn't mix up the pages of different calls
+pub fn session_key(
+    server_name: &McpServerName,
+    call_request: &CallToolRequestParam,
+    session: &str,
+) -> String {
+    serde_json::json!([
+        server_name.0,
+        call_request.name,
+        session,
+        call_request.arguments
+    ])
+    .to_string()
+}
+
+/// The cursor of the page following a tool result, if there is one
+pub fn next_cursor(result: &CallToolResult) -> Option<String> {
+    result
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.get("nextCursor"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Meta;
+    use serde_json::json;
+
+    #[test]
+    fn test_pagination_memory() {
+        let mut arguments = json!({"query": "rust", "page": 3, "pagination_session": "s1"})
+            .as_object()
+            .unwrap()
+            .clone();
+        let pagination: PaginationConfig = serde_json::from_value(json!({})).unwrap();
+        let page_request = PageRequest::take(&mut arguments, &pagination).unwrap();
+        assert_eq!(
+            page_request,
+            PageRequest {
+                page: 3,
+                session: Some("s1".to_string())
+            }
+        );
+        assert_eq!(Value::Object(arguments), json!({"query": "rust"}));
+
+        let memory = PaginationMemory::default();
+        assert_eq!(memory.closest_page(Some("s1"), 3), (1, None));
+        memory.remember("s1", 2, "c2".to_string());
+        memory.remember("s1", 4, "c4".to_string());
+        assert_eq!(
+            memory.closest_page(Some("s1"), 3),
+            (2, Some("c2".to_string()))
+        );
+        assert_eq!(memory.closest_page(None, 3), (1, None));
+
+        let mut arguments = json!({"page": 101}).as_object().unwrap().clone();
+        assert_eq!(
+            PageRequest::take(&mut arguments, &pagination).unwrap_err(),
+            "Argument page must be at most 100"
+        );
+    }
+
+    #[test]
+    fn test_conflicting_page_argument() {
+        let tool = Tool::new(
+            "search",
+            "",
+            std::sync::Arc::new(
+                json!({"properties": {"query": {}, "page": {}, "cursor": {}}})
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            ),
+        );
+        let pagination: PaginationConfig = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(conflicting_page_argument(&tool, &pagination), Some("page"));
+        let pagination: PaginationConfig =
+            serde_json::from_value(json!({"page_argument": "page_number"})).unwrap();
+        assert_eq!(conflicting_page_argument(&tool, &pagination), None);
+    }
+
+    /// A page whose result links to the given cursor
+    fn page_result(next: Option<&str>) -> CallToolResult {
+        let mut result = CallToolResult::success(Vec::new());
+        result.meta =
+            next.map(|next| Meta(json!({ "nextCursor": next }).as_object().unwrap().clone()));
+        result
+    }
+
+    #[tokio::test]
+    async fn test_follow_pages() {
+        let memory = PaginationMemory::default();
+        let calls = Mutex::new(Vec::new());
+        let call_page = |links: &'static [(Option<&'static str>, Option<&'static str>)]| {
+            let calls = &calls;
+            move |cursor: Option<String>| {
+                calls.lock().unwrap().push(cursor.clone());
+                let next = links
+                    .iter()
+                    .find(|(from, _)| from.map(str::to_string) == cursor)
+                    .and_then(|(_, next)| *next);
+                async move { Ok::<_, ()>(page_result(next)) }
+            }
+        };
+        let links: &[_] = &[
+            (None, Some("c2")),
+            (Some("c2"), Some("c3")),
+            (Some("c3"), None),
+        ];
+
+        // Walks the pages and remembers their cursors
+        let result = follow_pages(&memory, Some("s1"), 3, call_page(links)).await;
+        assert!(result.is_ok_and(|result| next_cursor(&result).is_none()));
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [None, Some("c2".to_string()), Some("c3".to_string())]
+        );
+
+        // Continues from the closest remembered page
+        calls.lock().unwrap().clear();
+        let result = follow_pages(&memory, Some("s1"), 3, call_page(links)).await;
+        assert!(result.is_ok());
+        assert_eq!(*calls.lock().unwrap(), [Some("c3".to_string())]);
+
+        assert!(matches!(
+            follow_pages(&memory, None, 4, call_page(links)).await,
+            Err(PageError::PastLastPage { last_page: 3 })
+        ));
+
+        // A tool linking back to an earlier page is not called forever
+        let cycle: &[_] = &[(None, Some("c2")), (Some("c2"), Some("c2"))];
+        assert!(matches!(
+            follow_pages(&memory, None, 10, call_page(cycle)).await,
+            Err(PageError::RepeatedCursor { page: 3 })
+        ));
+    }
+}
@@ -0,0 +1,210 @@
+//! Client-side evaluation of query predicates on collection rows, of resources and of tools
+//! returning rows. MCP servers can't filter their results, so rows are read and then filtered
+//! by the connector.
+
+use indexmap::IndexMap;
+use ndc_sdk::models;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// Evaluate a predicate on a row. Comparisons on null values are false, as in SQL.
+pub fn evaluate_predicate(
+    expression: &models::Expression,
+    row: &IndexMap<models::FieldName, models::RowFieldValue>,
+) -> Result<bool, String> {
+    match expression {
+        models::Expression::And { expressions } => {
+            for expression in expressions {
+                if !evaluate_predicate(expression, row)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        models::Expression::Or { expressions } => {
+            for expression in expressions {
+                if evaluate_predicate(expression, row)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        models::Expression::Not { expression } => Ok(!evaluate_predicate(expression, row)?),
+        models::Expression::UnaryComparisonOperator { column, operator } => match operator {
+            models::UnaryComparisonOperator::IsNull => Ok(column_value(column, row)?.is_null()),
+        },
+        models::Expression::BinaryComparisonOperator {
+            column,
+            operator,
+            value,
+        } => {
+            let column_value = column_value(column, row)?;
+            let value = match value {
+                models::ComparisonValue::Scalar { value } => value,
+                _ => return Err("Only comparisons with literal values are supported".to_string()),
+            };
+            if column_value.is_null() {
+                return Ok(false);
+            }
+            compare(operator.to_string().as_str(), column_value, value)
+        }
+        _ => Err("Only comparison, and, or and not predicates are supported".to_string()),
+    }
+}
+
+/// The value of the compared column, or of a field nested in it
+fn column_value<'a>(
+    column: &models::ComparisonTarget,
+    row: &'a IndexMap<models::FieldName, models::RowFieldValue>,
+) -> Result<&'a Value, String> {
+    let models::ComparisonTarget::Column {
+        name, field_path, ..
+    } = column
+    else {
+        return Err("Only comparisons on columns are supported".to_string());
+    };
+    row_value(row, name, field_path.as_deref())
+}
+
+/// The value of a column of a row, or of a field nested in it
+pub(crate) fn row_value<'a>(
+    row: &'a IndexMap<models::FieldName, models::RowFieldValue>,
+    name: &models::FieldName,
+    field_path: Option<&[models::FieldName]>,
+) -> Result<&'a Value, String> {
+    let mut value = row
+        .get(name)
+        .map(|value| &value.0)
+        .ok_or_else(|| format!("Unknown column: {}", name))?;
+    for field in field_path.into_iter().flatten() {
+        value = value.get(field.to_string()).unwrap_or(&Value::Null);
+    }
+    Ok(value)
+}
+
+/// Apply a binary comparison operator
+fn compare(operator: &str, left: &Value, right: &Value) -> Result<bool, String> {
+    match operator {
+        "_eq" => Ok(values_equal(left, right)),
+        "_neq" => Ok(!values_equal(left, right)),
+        "_in" => {
+            let values = right
+                .as_array()
+                .ok_or_else(|| "The _in operator takes an array of values".to_string())?;
+            Ok(values.iter().any(|value| values_equal(left, value)))
+        }
+        "_lt" | "_lte" | "_gt" | "_gte" => {
+            let Some(ordering) = order_values(left, right) else {
+                return Ok(false);
+            };
+            Ok(match operator {
+                "_lt" => ordering == Ordering::Less,
+                "_lte" => ordering != Ordering::Greater,
+                "_gt" => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            })
+        }
+        operator => Err(format!("Unsupported comparison operator: {}", operator)),
+    }
+}
+
+/// Compare values, treating integers and floats alike
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+        (left, right) => left == right,
+    }
+}
+
+/// Order numbers and strings. Other values are not ordered.
+pub(crate) fn order_values(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64()?.partial_cmp(&right.as_f64()?),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(fields: Value) -> IndexMap<models::FieldName, models::RowFieldValue> {
+        fields
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    models::FieldName::new(name.as_str().into()),
+                    models::RowFieldValue(value.clone()),
+                )
+            })
+            .collect()
+    }
+
+    fn comparison(column: &str, operator: &str, value: Value) -> Value {
+        json!({
+            "type": "binary_comparison_operator",
+            "column": {"type": "column", "name": column},
+            "operator": operator,
+            "value": {"type": "scalar", "value": value}
+        })
+    }
+
+    #[test]
+    fn test_evaluate_predicate() {
+        let row = row(json!({"mime_type": "text/plain", "size": 120, "priority": null}));
+        let evaluate = |expression: Value| {
+            let expression: models::Expression = serde_json::from_value(expression).unwrap();
+            evaluate_predicate(&expression, &row).unwrap()
+        };
+
+        assert!(evaluate(comparison(
+            "mime_type",
+            "_eq",
+            json!("text/plain")
+        )));
+        assert!(evaluate(comparison("size", "_gte", json!(120.0))));
+        assert!(evaluate(comparison("size", "_in", json!([1, 120]))));
+        assert!(!evaluate(comparison("priority", "_neq", json!(0.5))));
+        assert!(!evaluate(json!({
+            "type": "and",
+            "expressions": [
+                comparison("size", "_lt", json!(1000)),
+                {
+                    "type": "not",
+                    "expression": {
+                        "type": "unary_comparison_operator",
+                        "column": {"type": "column", "name": "priority"},
+                        "operator": "is_null"
+                    }
+                }
+            ]
+        })));
+    }
+
+    #[test]
+    fn test_evaluate_or_predicate() {
+        let rows = [
+            row(json!({"name": "ndc-mcp-rs", "stars": 40})),
+            row(json!({"name": "rmcp", "stars": 2000})),
+            row(json!({"name": "ndc-spec", "stars": null})),
+        ];
+        let expression: models::Expression = serde_json::from_value(json!({
+            "type": "or",
+            "expressions": [
+                comparison("stars", "_gt", json!(1000)),
+                comparison("name", "_in", json!(["ndc-spec"]))
+            ]
+        }))
+        .unwrap();
+        let matching = rows
+            .iter()
+            .filter(|row| evaluate_predicate(&expression, row).unwrap())
+            .map(|row| row[&models::FieldName::new("name".into())].0.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(matching, vec![json!("rmcp"), json!("ndc-spec")]);
+    }
+}
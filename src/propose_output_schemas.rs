@@ -0,0 +1,85 @@
+//! The `propose-output-schemas` command reads the output schemas a running connector has
+//! inferred for tools without an `outputSchema`, and prints them as a configuration fragment
+//! that can be merged into `configuration.json`.
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use serde_json::{json, Map, Value};
+use std::process::ExitCode;
+
+use crate::diagnostics::DIAGNOSTICS_FUNCTION_NAME;
+
+/// Arguments for the `propose-output-schemas` command
+#[derive(Debug, Parser)]
+#[command(name = "propose-output-schemas")]
+pub struct ProposeOutputSchemasArgs {
+    /// URL of the running connector, which must have `diagnostics` enabled
+    #[arg(long, default_value = "http://localhost:8080")]
+    endpoint: String,
+
+    /// Service token of the running connector
+    #[arg(long, env = "HASURA_SERVICE_TOKEN_SECRET")]
+    service_token_secret: Option<String>,
+}
+
+/// Run the `propose-output-schemas` command with the given command line arguments
+pub async fn run(args: impl IntoIterator<Item = String>) -> ExitCode {
+    let args = ProposeOutputSchemasArgs::parse_from(args);
+    match propose_output_schemas(&args).await {
+        Ok(proposal) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&proposal).unwrap_or_default()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Query the diagnostics function and build the configuration fragment
+async fn propose_output_schemas(args: &ProposeOutputSchemasArgs) -> Result<Value> {
+    let query_request = json!({
+        "collection": DIAGNOSTICS_FUNCTION_NAME,
+        "query": {
+            "fields": {
+                "__value": {"type": "column", "column": "__value"}
+            }
+        },
+        "arguments": {},
+        "collection_relationships": {}
+    });
+
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/query", args.endpoint.trim_end_matches('/')))
+        .json(&query_request);
+    if let Some(service_token_secret) = &args.service_token_secret {
+        request = request.bearer_auth(service_token_secret);
+    }
+    let response = request.send().await?.error_for_status()?;
+    let query_response: Value = response.json().await?;
+
+    let inferred_output_schemas = query_response
+        .pointer("/0/rows/0/__value/inferred_output_schemas")
+        .and_then(|schemas| schemas.as_object())
+        .ok_or_else(|| anyhow!("Unexpected diagnostics response: {}", query_response))?;
+
+    let mut tools = Map::new();
+    for (server_name, server_tools) in inferred_output_schemas {
+        let Some(server_tools) = server_tools.as_object() else {
+            continue;
+        };
+        let server_tools = server_tools
+            .iter()
+            .map(|(tool_name, output_schema)| {
+                (tool_name.clone(), json!({"output_schema": output_schema}))
+            })
+            .collect::<Map<_, _>>();
+        tools.insert(server_name.clone(), Value::Object(server_tools));
+    }
+
+    Ok(json!({"tools": tools}))
+}
@@ -0,0 +1,344 @@
+//! Strict validation of query and mutation requests against the schema, enabled with the
+//! `strict_requests` option. Requests naming unknown collections, functions, procedures,
+//! arguments or fields, with missing or mistyped arguments, or using query features the
+//! connector doesn't support for them are rejected instead of being partially ignored.
+
+use ndc_sdk::models::{self, Type};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Validate a query request
+pub fn validate_query_request(
+    schema: &models::SchemaResponse,
+    request: &models::QueryRequest,
+) -> Result<(), String> {
+    let name = request.collection.to_string();
+    if !request.collection_relationships.is_empty() {
+        return Err("Relationships are not supported".to_string());
+    }
+    let (arguments, row_type) = if let Some(collection) = schema
+        .collections
+        .iter()
+        .find(|collection| collection.name.to_string() == name)
+    {
+        let arguments = collection
+            .arguments
+            .iter()
+            .map(|(name, argument)| (name.to_string(), &argument.argument_type))
+            .collect::<BTreeMap<_, _>>();
+        (arguments, Some(collection.collection_type.to_string()))
+    } else if let Some(function) = schema
+        .functions
+        .iter()
+        .find(|function| function.name.to_string() == name)
+    {
+        let arguments = function
+            .arguments
+            .iter()
+            .map(|(name, argument)| (name.to_string(), &argument.argument_type))
+            .collect::<BTreeMap<_, _>>();
+        (arguments, None)
+    } else {
+        return Err(format!("Unknown collection or function {}", name));
+    };
+
+    // Variables are checked in every set, as each set is a separate call
+    let variable_sets = request
+        .variables
+        .clone()
+        .unwrap_or_else(|| vec![BTreeMap::new()]);
+    for variables in &variable_sets {
+        let mut values = serde_json::Map::new();
+        for (argument_name, argument) in &request.arguments {
+            let value = match argument {
+                models::Argument::Literal { value } => value.clone(),
+                models::Argument::Variable { name } => variables
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("Variable {} is not defined", name))?,
+            };
+            values.insert(argument_name.to_string(), value);
+        }
+        check_arguments(schema, &name, &arguments, &values)?;
+    }
+
+    check_query(schema, &name, row_type.as_deref(), &request.query)
+}
+
+/// Validate a mutation request
+pub fn validate_mutation_request(
+    schema: &models::SchemaResponse,
+    request: &models::MutationRequest,
+) -> Result<(), String> {
+    if !request.collection_relationships.is_empty() {
+        return Err("Relationships are not supported".to_string());
+    }
+    for operation in &request.operations {
+        let models::MutationOperation::Procedure {
+            name, arguments, ..
+        } = operation;
+        let name = name.to_string();
+        let procedure = schema
+            .procedures
+            .iter()
+            .find(|procedure| procedure.name.to_string() == name)
+            .ok_or_else(|| format!("Unknown procedure {}", name))?;
+        let declared = procedure
+            .arguments
+            .iter()
+            .map(|(name, argument)| (name.to_string(), &argument.argument_type))
+            .collect::<BTreeMap<_, _>>();
+        let values = arguments
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect::<serde_json::Map<_, _>>();
+        check_arguments(schema, &name, &declared, &values)?;
+    }
+    Ok(())
+}
+
+/// Check that the arguments of a collection, function or procedure are declared, that
+/// required ones are present and that their values have the declared types
+fn check_arguments(
+    schema: &models::SchemaResponse,
+    name: &str,
+    declared: &BTreeMap<String, &Type>,
+    values: &serde_json::Map<String, Value>,
+) -> Result<(), String> {
+    if let Some(unknown) = values.keys().find(|key| !declared.contains_key(*key)) {
+        return Err(format!("Unknown argument {} of {}", unknown, name));
+    }
+    for (argument_name, argument_type) in declared {
+        let value = values.get(argument_name).unwrap_or(&Value::Null);
+        if value.is_null() && !matches!(argument_type, Type::Nullable { .. }) {
+            return Err(format!(
+                "Missing required argument {} of {}",
+                argument_name, name
+            ));
+        }
+        check_value(schema, argument_type, value, argument_name)?;
+    }
+    Ok(())
+}
+
+/// Check that a value has a type of the schema, reporting the path of mismatches
+fn check_value(
+    schema: &models::SchemaResponse,
+    value_type: &Type,
+    value: &Value,
+    path: &str,
+) -> Result<(), String> {
+    let mismatch = |expected: &str| {
+        Err(format!(
+            "Argument {} must be {}, got {}",
+            path, expected, value
+        ))
+    };
+    match value_type {
+        Type::Nullable { underlying_type } => {
+            if value.is_null() {
+                Ok(())
+            } else {
+                check_value(schema, underlying_type, value, path)
+            }
+        }
+        Type::Array { element_type } => match value {
+            Value::Array(elements) => elements.iter().enumerate().try_for_each(|(i, element)| {
+                check_value(schema, element_type, element, &format!("{}[{}]", path, i))
+            }),
+            _ => mismatch("an array"),
+        },
+        Type::Named { name } => {
+            let name = name.to_string();
+            if let Some(object_type) = schema
+                .object_types
+                .get(&models::ObjectTypeName::from(name.clone()))
+            {
+                let Value::Object(object) = value else {
+                    return mismatch(&format!("an object of type {}", name));
+                };
+                if let Some(unknown) = object.keys().find(|key| {
+                    !object_type
+                        .fields
+                        .contains_key(&models::FieldName::from(key.to_string()))
+                }) {
+                    return Err(format!("Unknown field {} in argument {}", unknown, path));
+                }
+                for (field_name, field) in &object_type.fields {
+                    let field_value = object.get(&field_name.to_string()).unwrap_or(&Value::Null);
+                    if field_value.is_null() && !matches!(field.r#type, Type::Nullable { .. }) {
+                        return Err(format!(
+                            "Missing required field {} in argument {}",
+                            field_name, path
+                        ));
+                    }
+                    check_value(
+                        schema,
+                        &field.r#type,
+                        field_value,
+                        &format!("{}.{}", path, field_name),
+                    )?;
+                }
+                return Ok(());
+            }
+            let Some(scalar_type) = schema
+                .scalar_types
+                .get(&models::ScalarTypeName::from(name.clone()))
+            else {
+                return Ok(());
+            };
+            let valid = match &scalar_type.representation {
+                models::TypeRepresentation::String
+                | models::TypeRepresentation::Date
+                | models::TypeRepresentation::TimestampTZ
+                | models::TypeRepresentation::UUID
+                | models::TypeRepresentation::Bytes => value.is_string(),
+                models::TypeRepresentation::Boolean => value.is_boolean(),
+                models::TypeRepresentation::Int32 => {
+                    value.as_i64().is_some_and(|n| i32::try_from(n).is_ok())
+                }
+                // 64-bit integers may be sent as strings, to avoid loss of precision
+                models::TypeRepresentation::Int64 => {
+                    value.is_i64() || value.as_str().is_some_and(|s| s.parse::<i64>().is_ok())
+                }
+                models::TypeRepresentation::BigInteger => {
+                    value.is_i64()
+                        || value.is_u64()
+                        || value
+                            .as_str()
+                            .is_some_and(|s| s.parse::<i64>().is_ok() || s.parse::<u64>().is_ok())
+                }
+                models::TypeRepresentation::Float64 => value.is_number(),
+                models::TypeRepresentation::Enum { one_of } => value
+                    .as_str()
+                    .is_some_and(|s| one_of.iter().any(|variant| variant == s)),
+                _ => true,
+            };
+            if valid {
+                Ok(())
+            } else {
+                mismatch(&format!("of type {}", name))
+            }
+        }
+        Type::Predicate { .. } => Ok(()),
+    }
+}
+
+/// Check that a query only uses supported features and selects declared fields
+fn check_query(
+    schema: &models::SchemaResponse,
+    name: &str,
+    row_type: Option<&str>,
+    query: &models::Query,
+) -> Result<(), String> {
+    // Rows of collections are ordered and paginated by the connector, but functions return a
+    // single row
+    if row_type.is_none() && query.order_by.is_some() {
+        return Err("Sorting with order_by is only supported on collections".to_string());
+    }
+    if row_type.is_none() && (query.limit.is_some() || query.offset.is_some()) {
+        return Err(
+            "Pagination with limit and offset is only supported on collections".to_string(),
+        );
+    }
+    if query.groups.is_some() {
+        return Err("Grouping is not supported".to_string());
+    }
+
+    let object_type = row_type.and_then(|row_type| {
+        schema
+            .object_types
+            .get(&models::ObjectTypeName::from(row_type.to_string()))
+    });
+    for (alias, field) in query.fields.iter().flatten() {
+        let models::Field::Column { column, .. } = field else {
+            return Err(format!(
+                "Field {} selects a relationship, which is not supported",
+                alias
+            ));
+        };
+        let declared = match object_type {
+            Some(object_type) => object_type.fields.contains_key(column),
+            // Function results are returned in a single `__value` column
+            None => column.to_string() == "__value",
+        };
+        if !declared {
+            return Err(format!("Unknown field {} of {}", column, name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConnectorConfig, McpServerName};
+    use crate::schema::generate_schema_from_state;
+    use crate::state::{ConnectorState, McpClient};
+    use rmcp::model::Tool;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_validate_query_request() {
+        let input_schema = json!({
+            "type": "object",
+            "properties": {"number": {"type": "integer"}, "repo": {"type": "string"}},
+            "required": ["number"]
+        });
+        let configuration: ConnectorConfig =
+            serde_json::from_value(json!({"servers": {}})).unwrap();
+        let mut state = ConnectorState::new();
+        state.add_client(
+            McpServerName("github".to_string()),
+            McpClient {
+                service: None,
+                resources: HashMap::new(),
+                resource_templates: HashMap::new(),
+                tools: HashMap::from([(
+                    "get_issue".to_string(),
+                    Tool::new(
+                        "get_issue",
+                        "",
+                        Arc::new(input_schema.as_object().unwrap().clone()),
+                    ),
+                )]),
+                prompts: HashMap::new(),
+            },
+        );
+        state.assign_ndc_names(&configuration.naming, &configuration.servers);
+        let schema = generate_schema_from_state(&state, &configuration);
+
+        let request = |arguments: Value| -> models::QueryRequest {
+            serde_json::from_value(json!({
+                "collection": "github__get_issue",
+                "query": {"fields": {"__value": {"type": "column", "column": "__value"}}},
+                "arguments": arguments,
+                "collection_relationships": {}
+            }))
+            .unwrap()
+        };
+        let literal = |value: Value| json!({"type": "literal", "value": value});
+
+        assert_eq!(
+            validate_query_request(&schema, &request(json!({"number": literal(json!(7))}))),
+            Ok(())
+        );
+        assert_eq!(
+            validate_query_request(&schema, &request(json!({}))),
+            Err("Missing required argument number of github__get_issue".to_string())
+        );
+        assert_eq!(
+            validate_query_request(&schema, &request(json!({"number": literal(json!("7"))}))),
+            Err("Argument number must be of type Int, got \"7\"".to_string())
+        );
+        assert_eq!(
+            validate_query_request(
+                &schema,
+                &request(json!({"number": literal(json!(7)), "owner": literal(json!("x"))}))
+            ),
+            Err("Unknown argument owner of github__get_issue".to_string())
+        );
+    }
+}
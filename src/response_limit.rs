@@ -0,0 +1,257 @@
+//! Limits on the size of tool results and resource contents. A misbehaving tool returning tens
+//! of megabytes of text would otherwise be passed on in full to the GraphQL response and the
+//! response cache. The limit is applied once the result was received from the server, so it
+//! does not bound the memory used while receiving it. Results of servers configured with
+//! `response_limit` larger than `max_response_bytes`, measured as JSON, fail the call, have
+//! their text content cut, or have their binary content removed.
+
+use rmcp::model::{
+    CallToolResult, RawContent, RawEmbeddedResource, ReadResourceResult, ResourceContents,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::io;
+
+use crate::config::{ResponseLimitAction, ResponseLimitConfig};
+
+/// Appended to text content that was cut to fit the response limit
+pub const TRUNCATION_MARKER: &str = "[truncated]";
+
+/// A result of an MCP server that a response limit applies to
+pub trait LimitedResponse: Serialize {
+    /// The text of the result that can be cut, in order
+    fn texts(&mut self) -> Vec<&mut String>;
+
+    /// Remove images, audio and binary resources
+    fn drop_binary(&mut self);
+}
+
+impl LimitedResponse for CallToolResult {
+    fn texts(&mut self) -> Vec<&mut String> {
+        let mut texts = Vec::new();
+        for content in &mut self.content {
+            match &mut content.raw {
+                RawContent::Text(text) => texts.push(&mut text.text),
+                RawContent::Resource(RawEmbeddedResource { resource, .. }) => {
+                    texts.extend(resource_text(resource))
+                }
+                _ => {}
+            }
+        }
+        if let Some(structured_content) = &mut self.structured_content {
+            json_strings(structured_content, &mut texts);
+        }
+        texts
+    }
+
+    fn drop_binary(&mut self) {
+        self.content.retain(|content| !is_binary(&content.raw))
+    }
+}
+
+impl LimitedResponse for ReadResourceResult {
+    fn texts(&mut self) -> Vec<&mut String> {
+        self.contents.iter_mut().filter_map(resource_text).collect()
+    }
+
+    fn drop_binary(&mut self) {
+        self.contents
+            .retain(|contents| !matches!(contents, ResourceContents::BlobResourceContents { .. }))
+    }
+}
+
+/// Apply a response limit to a result, failing if the result can't be made to fit
+pub fn limit_response<R: LimitedResponse>(
+    limit: &ResponseLimitConfig,
+    mut result: R,
+) -> Result<R, String> {
+    let max_bytes = limit.max_response_bytes;
+    let bytes = response_bytes(&result);
+    if bytes <= max_bytes {
+        return Ok(result);
+    }
+    match limit.on_exceeded {
+        ResponseLimitAction::Error => {}
+        ResponseLimitAction::Truncate => truncate_texts(result.texts(), bytes - max_bytes),
+        ResponseLimitAction::DropBinary => result.drop_binary(),
+    }
+    let bytes = response_bytes(&result);
+    if bytes > max_bytes {
+        return Err(format!(
+            "The result of {} bytes exceeds the response limit of {} bytes",
+            bytes, max_bytes
+        ));
+    }
+    Ok(result)
+}
+
+/// Cut texts from the last one until `excess` bytes were removed. Escaping makes text at least
+/// as long in JSON, so removing this many bytes of text is enough.
+fn truncate_texts(texts: Vec<&mut String>, mut excess: usize) {
+    for text in texts.into_iter().rev() {
+        if excess == 0 {
+            return;
+        }
+        // Texts no longer than the marker can't be made shorter
+        if text.len() <= TRUNCATION_MARKER.len() {
+            continue;
+        }
+        let mut keep = text.len().saturating_sub(excess + TRUNCATION_MARKER.len());
+        while !text.is_char_boundary(keep) {
+            keep -= 1;
+        }
+        excess = excess.saturating_sub(text.len() - keep - TRUNCATION_MARKER.len());
+        text.truncate(keep);
+        text.push_str(TRUNCATION_MARKER);
+    }
+}
+
+/// The text of resource contents, unless they are binary
+fn resource_text(resource: &mut ResourceContents) -> Option<&mut String> {
+    match resource {
+        ResourceContents::TextResourceContents { text, .. } => Some(text),
+        ResourceContents::BlobResourceContents { .. } => None,
+    }
+}
+
+/// Collect the strings of a JSON value, in order
+fn json_strings<'a>(value: &'a mut Value, strings: &mut Vec<&'a mut String>) {
+    match value {
+        Value::String(string) => strings.push(string),
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| json_strings(value, strings)),
+        Value::Object(object) => object
+            .values_mut()
+            .for_each(|value| json_strings(value, strings)),
+        _ => {}
+    }
+}
+
+/// Whether content is binary: images, audio and blob resources
+fn is_binary(content: &RawContent) -> bool {
+    matches!(
+        content,
+        RawContent::Image(_)
+            | RawContent::Audio(_)
+            | RawContent::Resource(RawEmbeddedResource {
+                resource: ResourceContents::BlobResourceContents { .. },
+                ..
+            })
+    )
+}
+
+/// The size of a result as JSON, counted without serializing it into memory
+fn response_bytes(result: &impl Serialize) -> usize {
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, result).map_or(usize::MAX, |_| counter.0)
+}
+
+/// Writer counting the bytes written to it
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+    use serde_json::json;
+
+    #[test]
+    fn test_limit_response() {
+        let limit = |on_exceeded| ResponseLimitConfig {
+            max_response_bytes: 300,
+            on_exceeded,
+        };
+        let result = CallToolResult::success(vec![
+            Content::text("a".repeat(100)),
+            Content::image("b".repeat(400), "image/png"),
+            Content::text("c".repeat(400)),
+        ]);
+
+        let err = limit_response(&limit(ResponseLimitAction::Error), result.clone()).unwrap_err();
+        assert!(err.contains("exceeds the response limit of 300 bytes"));
+
+        // The text can't make up for the image
+        assert!(limit_response(&limit(ResponseLimitAction::Truncate), result.clone()).is_err());
+
+        let small_result = CallToolResult::success(vec![
+            Content::text("a".repeat(100)),
+            Content::text("c".repeat(400)),
+        ]);
+        let truncated =
+            limit_response(&limit(ResponseLimitAction::Truncate), small_result).unwrap();
+        assert!(response_bytes(&truncated) <= 300);
+        let texts = truncated
+            .content
+            .iter()
+            .filter_map(|content| content.raw.as_text())
+            .map(|text| text.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts[0], "a".repeat(100));
+        assert!(texts[1].starts_with('c') && texts[1].ends_with(TRUNCATION_MARKER));
+
+        // Without the image the text alone is still too large
+        assert!(limit_response(&limit(ResponseLimitAction::DropBinary), result).is_err());
+        let result = CallToolResult::success(vec![
+            Content::text("a".repeat(100)),
+            Content::image("b".repeat(400), "image/png"),
+        ]);
+        let dropped = limit_response(&limit(ResponseLimitAction::DropBinary), result).unwrap();
+        assert_eq!(dropped.content.len(), 1);
+    }
+
+    #[test]
+    fn test_truncate_embedded_and_structured_content() {
+        let limit = ResponseLimitConfig {
+            max_response_bytes: 300,
+            on_exceeded: ResponseLimitAction::Truncate,
+        };
+        let result: CallToolResult = serde_json::from_value(json!({
+            "content": [{"type": "resource", "resource": {
+                "uri": "file:///log.txt", "text": "a".repeat(300)
+            }}],
+            "structuredContent": {"log": "b".repeat(300), "summary": "ok"}
+        }))
+        .unwrap();
+        let mut truncated = limit_response(&limit, result).unwrap();
+        assert!(response_bytes(&truncated) <= 300);
+
+        // Strings too short to be cut are kept
+        let texts = truncated.texts();
+        assert!(texts[0].starts_with('a') && texts[0].ends_with(TRUNCATION_MARKER));
+        assert_eq!(*texts[1], TRUNCATION_MARKER);
+        assert_eq!(*texts[2], "ok");
+    }
+
+    #[test]
+    fn test_limit_resource_contents() {
+        let limit = |on_exceeded| ResponseLimitConfig {
+            max_response_bytes: 500,
+            on_exceeded,
+        };
+        let result: ReadResourceResult = serde_json::from_value(json!({"contents": [
+            {"uri": "file:///notes.txt", "text": "a".repeat(400)},
+            {"uri": "file:///chart.png", "mimeType": "image/png", "blob": "b".repeat(400)}
+        ]}))
+        .unwrap();
+
+        assert!(limit_response(&limit(ResponseLimitAction::Error), result.clone()).is_err());
+
+        // The text can't make up for the blob
+        assert!(limit_response(&limit(ResponseLimitAction::Truncate), result.clone()).is_err());
+
+        let dropped = limit_response(&limit(ResponseLimitAction::DropBinary), result).unwrap();
+        assert_eq!(dropped.contents.len(), 1);
+    }
+}
@@ -0,0 +1,221 @@
+//! Retries of calls that failed with transient errors, limited by a budget per server so that
+//! retries during an outage don't multiply the load on the server.
+
+use rmcp::ServiceError;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::{McpServerName, RetryConfig};
+
+/// Number of retries a server's budget can accumulate, allowing occasional retries for
+/// servers with few calls
+const MAX_RETRY_BALANCE: f64 = 10.0;
+
+/// Counters of retries and of retries denied by the budget, per server
+struct RetryMetrics {
+    retries: prometheus::IntCounterVec,
+    budget_exhausted: prometheus::IntCounterVec,
+}
+
+impl RetryMetrics {
+    fn register(registry: &mut prometheus::Registry) -> prometheus::Result<Self> {
+        let retries = prometheus::IntCounterVec::new(
+            prometheus::Opts::new("ndc_mcp_retries_total", "Retried calls to MCP servers"),
+            &["server"],
+        )?;
+        let budget_exhausted = prometheus::IntCounterVec::new(
+            prometheus::Opts::new(
+                "ndc_mcp_retry_budget_exhausted_total",
+                "Calls to MCP servers that were not retried because the retry budget was exhausted",
+            ),
+            &["server"],
+        )?;
+        registry.register(Box::new(retries.clone()))?;
+        registry.register(Box::new(budget_exhausted.clone()))?;
+        Ok(Self {
+            retries,
+            budget_exhausted,
+        })
+    }
+}
+
+/// Retry budgets of every server. Every call adds `budget_ratio` to the server's balance and
+/// every retry takes 1 from it, so retries are at most about `budget_ratio` of the calls.
+/// Disabled unless retries are configured, for all servers or for the server.
+#[derive(Default)]
+pub struct RetryBudget {
+    config: Option<RetryConfig>,
+    server_configs: HashMap<McpServerName, RetryConfig>,
+    balances: Mutex<HashMap<McpServerName, f64>>,
+    metrics: Option<RetryMetrics>,
+}
+
+impl RetryBudget {
+    pub fn new(config: Option<RetryConfig>) -> Self {
+        Self {
+            config,
+            server_configs: HashMap::new(),
+            balances: Mutex::new(HashMap::new()),
+            metrics: None,
+        }
+    }
+
+    /// Use the retries configured for individual servers instead of the default
+    pub fn with_server_configs(
+        mut self,
+        server_configs: HashMap<McpServerName, RetryConfig>,
+    ) -> Self {
+        self.server_configs = server_configs;
+        self
+    }
+
+    /// The retries of calls to a server, if enabled
+    fn config(&self, server_name: &McpServerName) -> Option<&RetryConfig> {
+        self.server_configs
+            .get(server_name)
+            .or(self.config.as_ref())
+    }
+
+    /// Export the retry counters in the given registry
+    pub fn with_metrics(mut self, registry: &mut prometheus::Registry) -> Self {
+        match RetryMetrics::register(registry) {
+            Ok(metrics) => self.metrics = Some(metrics),
+            Err(err) => tracing::warn!("Failed to register the retry metrics: {}", err),
+        }
+        self
+    }
+
+    /// Record a call, adding to the server's budget
+    pub fn record_call(&self, server_name: &McpServerName) {
+        let Some(config) = self.config(server_name) else {
+            return;
+        };
+        let mut balances = self.balances.lock().unwrap();
+        let balance = balances
+            .entry(server_name.clone())
+            .or_insert(MAX_RETRY_BALANCE);
+        *balance = (*balance + config.budget_ratio).min(MAX_RETRY_BALANCE);
+    }
+
+    /// Decide whether a failed call is retried, taking the retry from the server's budget.
+    /// Returns the delay before the retry.
+    pub fn retry_after(
+        &self,
+        server_name: &McpServerName,
+        err: &ServiceError,
+        retries: u32,
+    ) -> Option<Duration> {
+        let config = self.config(server_name)?;
+        if retries >= config.max_retries || !is_transient(err) {
+            return None;
+        }
+
+        let mut balances = self.balances.lock().unwrap();
+        let balance = balances
+            .entry(server_name.clone())
+            .or_insert(MAX_RETRY_BALANCE);
+        if *balance < 1.0 {
+            if let Some(metrics) = &self.metrics {
+                metrics
+                    .budget_exhausted
+                    .with_label_values(&[&server_name.0])
+                    .inc();
+            }
+            tracing::debug!("Retry budget of MCP server {} is exhausted", server_name.0);
+            return None;
+        }
+        *balance -= 1.0;
+        if let Some(metrics) = &self.metrics {
+            metrics.retries.with_label_values(&[&server_name.0]).inc();
+        }
+
+        // Back off exponentially between retries, with jitter
+        let delay = Duration::from_millis(config.backoff_ms) * 2u32.saturating_pow(retries);
+        Some(delay.mul_f64(jitter_factor(config.jitter)))
+    }
+}
+
+/// A random factor between `1 - jitter` and `1 + jitter`
+fn jitter_factor(jitter: f64) -> f64 {
+    let jitter = jitter.clamp(0.0, 1.0);
+    if jitter == 0.0 {
+        return 1.0;
+    }
+    // Randomly seeded hashers are the standard library's only source of randomness
+    let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+    1.0 + jitter * (2.0 * random - 1.0)
+}
+
+/// Check if a call failed in a way a retry may fix. Errors returned by the server, and lost
+/// connections, are not retried.
+fn is_transient(err: &ServiceError) -> bool {
+    matches!(
+        err,
+        ServiceError::TransportSend(_) | ServiceError::Timeout { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_budget() {
+        let budget = RetryBudget::new(Some(RetryConfig {
+            max_retries: 2,
+            budget_ratio: 0.5,
+            backoff_ms: 100,
+            jitter: 0.0,
+        }));
+        let server_name = McpServerName("flaky".to_string());
+        let timeout = ServiceError::Timeout {
+            timeout: Duration::from_secs(30),
+        };
+
+        assert_eq!(
+            budget.retry_after(&server_name, &timeout, 0),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            budget.retry_after(&server_name, &timeout, 1),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(budget.retry_after(&server_name, &timeout, 2), None);
+        assert_eq!(
+            budget.retry_after(&server_name, &ServiceError::TransportClosed, 0),
+            None
+        );
+
+        // Exhaust the budget, which is refilled by calls
+        while budget.retry_after(&server_name, &timeout, 0).is_some() {}
+        budget.record_call(&server_name);
+        assert_eq!(budget.retry_after(&server_name, &timeout, 0), None);
+        budget.record_call(&server_name);
+        assert!(budget.retry_after(&server_name, &timeout, 0).is_some());
+    }
+
+    #[test]
+    fn test_retry_jitter_and_server_configs() {
+        let server_name = McpServerName("flaky".to_string());
+        let budget = RetryBudget::new(None).with_server_configs(HashMap::from([(
+            server_name.clone(),
+            RetryConfig {
+                max_retries: 1,
+                budget_ratio: 0.1,
+                backoff_ms: 1000,
+                jitter: 0.5,
+            },
+        )]));
+        let timeout = ServiceError::Timeout {
+            timeout: Duration::from_secs(30),
+        };
+
+        let delay = budget.retry_after(&server_name, &timeout, 0).unwrap();
+        assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1500));
+        let other = McpServerName("stable".to_string());
+        assert_eq!(budget.retry_after(&other, &timeout, 0), None);
+    }
+}
@@ -1,29 +1,179 @@
+use base64::prelude::{Engine, BASE64_STANDARD};
 use ndc_sdk::models::{
     self, ArgumentInfo, ArgumentName, CollectionInfo, FunctionInfo, ObjectField, ObjectType,
     ProcedureInfo, Type,
 };
-use rmcp::model::{Resource, Tool};
-use schemars::schema::{InstanceType, ObjectValidation, Schema, SingleOrVec};
-use std::collections::{BTreeMap, HashMap};
+use regex::Regex;
+use rmcp::model::{Annotations, Prompt, Resource, ResourceTemplate, Role, Tool};
+use schemars::schema::{InstanceType, ObjectValidation, Schema, SchemaObject, SingleOrVec};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
 
-use crate::config::McpServerName;
-use crate::state::ConnectorState;
+use crate::config::{
+    ConnectorConfig, ForwardedHeadersConfig, McpServerName, PaginationConfig, ResourceRowsConfig,
+    ResultMode, ToolErrors, ToolExposure,
+};
+use crate::diagnostics::diagnostics_function_info;
+use crate::features::Feature;
+use crate::server_info::{mcp_server_object_type, mcp_servers_collection_info};
+use crate::state::{ConnectorState, McpItemKind, TOOL_CATALOG_NAME};
+use crate::uri_template::template_variables;
+
+/// Check if a tool is read-only, and so exposed as a function rather than a procedure.
+///
+/// A configured `read_only` override takes precedence. Otherwise the tool's `readOnlyHint`
+/// annotation is used, falling back to a name-based heuristic unless `trust_read_only_hint`
+/// is set.
+pub(crate) fn is_read_only_tool(
+    server_name: &McpServerName,
+    tool: &Tool,
+    configuration: &ConnectorConfig,
+) -> bool {
+    if let Some(read_only) = configuration
+        .tool_config(server_name, &tool.name)
+        .and_then(|tool_config| tool_config.read_only)
+    {
+        return read_only;
+    }
 
-/// Check if a tool is read-only based on annotations
-fn is_read_only_tool(tool: &Tool) -> bool {
-    // For now, we'll use a simple heuristic: if the tool name starts with "get" or "list",
-    // we'll consider it read-only
+    let read_only_hint = tool
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.read_only_hint);
+    if configuration.trust_read_only_hint {
+        return read_only_hint.unwrap_or(false);
+    }
+
+    // Guess from the name when the tool is not annotated as read-only
     let name = tool.name.to_string().to_lowercase();
-    name.starts_with("get")
+    read_only_hint.unwrap_or(false)
+        || name.starts_with("get")
         || name.starts_with("list")
         || name.starts_with("find")
         || name.starts_with("search")
-        // Check if the tool has annotations and if the read_only_hint is true
-        || tool
-            .annotations
-            .as_ref()
-            .and_then(|annotations| annotations.read_only_hint)
-            .unwrap_or(false)
+}
+
+/// Check if a tool is exposed as a function, by the `expose_tools_as` setting of its server
+/// or otherwise by whether it is read-only. In read-only mode only read-only tools are.
+pub(crate) fn is_function_tool(
+    server_name: &McpServerName,
+    tool: &Tool,
+    configuration: &ConnectorConfig,
+) -> bool {
+    let read_only = is_read_only_tool(server_name, tool, configuration);
+    match configuration.tool_exposure(server_name) {
+        Some(ToolExposure::Functions | ToolExposure::Both) => read_only || !configuration.read_only,
+        Some(ToolExposure::Procedures) => false,
+        None => read_only,
+    }
+}
+
+/// Check if a function tool is exposed as a collection of the rows of its results, with
+/// `result_mode: rows` and an output schema with an array of objects, unless the
+/// `row_expansion` feature is disabled
+pub(crate) fn is_rows_tool(
+    server_name: &McpServerName,
+    tool: &Tool,
+    configuration: &ConnectorConfig,
+) -> bool {
+    configuration.feature_enabled(Feature::RowExpansion)
+        && configuration
+            .tool_config(server_name, &tool.name)
+            .is_some_and(|tool_config| tool_config.result_mode == Some(ResultMode::Rows))
+        && is_function_tool(server_name, tool, configuration)
+        && tool_rows_schema(tool).is_some()
+}
+
+/// The rows of the results of a tool: the property holding them, unless the structured
+/// content is the array itself, and the schema of the rows. Rows are the items of the output
+/// schema if it is an array of objects, or else of its first property that is one.
+pub(crate) fn tool_rows_schema(tool: &Tool) -> Option<(Option<String>, serde_json::Value)> {
+    let output_schema = resolve_schema_refs(tool.output_schema.as_ref()?);
+    let object_items = |schema: &serde_json::Value| {
+        Some(schema)
+            .filter(|schema| schema.get("type").and_then(|t| t.as_str()) == Some("array"))
+            .and_then(|schema| schema.get("items"))
+            .filter(|items| items.get("properties").is_some_and(|p| p.is_object()))
+            .cloned()
+    };
+    if let Some(items) = object_items(&output_schema) {
+        return Some((None, items));
+    }
+    output_schema
+        .get("properties")?
+        .as_object()?
+        .iter()
+        .find_map(|(name, property)| Some((Some(name.clone()), object_items(property)?)))
+}
+
+/// Check if a tool is exposed as a procedure, by the `expose_tools_as` setting of its server
+/// or otherwise by whether it is not read-only
+fn is_procedure_tool(
+    server_name: &McpServerName,
+    tool: &Tool,
+    configuration: &ConnectorConfig,
+) -> bool {
+    match configuration.tool_exposure(server_name) {
+        Some(ToolExposure::Procedures | ToolExposure::Both) => true,
+        Some(ToolExposure::Functions) => false,
+        None => !is_read_only_tool(server_name, tool, configuration),
+    }
+}
+
+/// The kind of the item the procedure of a tool of a server is named by
+pub(crate) fn tool_procedure_kind(
+    server_name: &McpServerName,
+    configuration: &ConnectorConfig,
+) -> McpItemKind {
+    if configuration.tool_exposure(server_name) == Some(ToolExposure::Both) {
+        McpItemKind::ToolProcedure
+    } else {
+        McpItemKind::Tool
+    }
+}
+
+/// Sanitize a name for use in NDC and GraphQL identifiers. Characters other than ASCII
+/// letters, digits and underscores are replaced with underscores, runs of underscores are
+/// collapsed so that `__` only separates server and tool names, and trailing underscores are
+/// removed.
+pub(crate) fn sanitize_name(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        let c = if c.is_ascii_alphanumeric() { c } else { '_' };
+        if c == '_' && sanitized.ends_with('_') {
+            continue;
+        }
+        sanitized.push(c);
+    }
+    let mut sanitized = sanitized.trim_end_matches('_').to_string();
+    if sanitized.is_empty() {
+        sanitized.push_str("unnamed");
+    } else if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// Map names to unique sanitized names. Names that are already valid keep their name, and
+/// colliding names get a numeric suffix in the order of the original names.
+pub(crate) fn sanitized_names<'a>(names: impl Iterator<Item = &'a str>) -> HashMap<String, String> {
+    let mut names = names.collect::<Vec<_>>();
+    names.sort_by_key(|name| (sanitize_name(name) != *name, *name));
+    names.dedup();
+
+    let mut sanitized_names = HashMap::new();
+    let mut used = HashSet::new();
+    for name in names {
+        let base = sanitize_name(name);
+        let mut sanitized = base.clone();
+        let mut suffix = 2;
+        while !used.insert(sanitized.clone()) {
+            sanitized = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        sanitized_names.insert(name.to_string(), sanitized);
+    }
+    sanitized_names
 }
 
 /// Create a named type with the given type name
@@ -33,14 +183,51 @@ fn create_named_type(type_name: &str) -> Type {
     }
 }
 
-/// Map a single instance type to NDC type
-fn map_instance_type_to_ndc(instance_type: &InstanceType) -> Type {
+/// Map a string `format` hint to a scalar type name, if it has a richer representation
+fn map_string_format_to_scalar(format: &str) -> Option<&'static str> {
+    match format {
+        "date-time" => Some("TimestampTZ"),
+        "date" => Some("Date"),
+        "uuid" => Some("UUID"),
+        "uri" | "uri-reference" | "url" => Some("URI"),
+        "email" | "idn-email" => Some("Email"),
+        "byte" | "binary" => Some("Bytes"),
+        _ => None,
+    }
+}
+
+/// The `format` hint of a schema. Strings with `contentEncoding: base64` have the `byte`
+/// format.
+fn schema_format(schema_obj: &SchemaObject) -> Option<&str> {
+    let base64_encoded = schema_obj
+        .extensions
+        .get("contentEncoding")
+        .and_then(|encoding| encoding.as_str())
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("base64"));
+    if base64_encoded {
+        Some("byte")
+    } else {
+        schema_obj.format.as_deref()
+    }
+}
+
+/// Map a single instance type to NDC type, using the `format` hint when present
+fn map_instance_type_to_ndc(instance_type: &InstanceType, format: Option<&str>) -> Type {
     match instance_type {
-        InstanceType::String => create_named_type("String"),
+        InstanceType::String => create_named_type(
+            format
+                .and_then(map_string_format_to_scalar)
+                .unwrap_or("String"),
+        ),
         InstanceType::Number => create_named_type("Float"),
-        InstanceType::Integer => create_named_type("Int"),
+        InstanceType::Integer => match format {
+            Some("int64") => create_named_type("BigInt"),
+            // Unsigned 64-bit integers may not fit an Int64, so they are sent as strings
+            Some("uint64") => create_named_type("BigInteger"),
+            _ => create_named_type("Int"),
+        },
         InstanceType::Boolean => create_named_type("Boolean"),
-        _ => create_named_type("String"), // Fallback to String for Object, Null, etc.
+        _ => create_named_type("JSON"), // Fallback to JSON for Object, Null, etc.
     }
 }
 
@@ -56,7 +243,7 @@ fn map_array_type(schema_obj: &schemars::schema::SchemaObject) -> Type {
                     }
                 }
                 schemars::schema::SingleOrVec::Vec(item_schemas) => {
-                    // For multiple item schemas, use first one or fallback to String
+                    // For multiple item schemas, use first one or fallback to JSON
                     if item_schemas.len() == 1 {
                         let element_type = map_schema_to_ndc_type(&item_schemas[0]);
                         Type::Array {
@@ -64,21 +251,21 @@ fn map_array_type(schema_obj: &schemars::schema::SchemaObject) -> Type {
                         }
                     } else {
                         Type::Array {
-                            element_type: Box::new(create_named_type("String")),
+                            element_type: Box::new(create_named_type("JSON")),
                         }
                     }
                 }
             }
         } else {
-            // No items schema specified, use String array
+            // No items schema specified, use JSON array
             Type::Array {
-                element_type: Box::new(create_named_type("String")),
+                element_type: Box::new(create_named_type("JSON")),
             }
         }
     } else {
-        // No array validation specified, use String array
+        // No array validation specified, use JSON array
         Type::Array {
-            element_type: Box::new(create_named_type("String")),
+            element_type: Box::new(create_named_type("JSON")),
         }
     }
 }
@@ -86,57 +273,598 @@ fn map_array_type(schema_obj: &schemars::schema::SchemaObject) -> Type {
 /// Map JSON schema type to NDC type
 fn map_schema_to_ndc_type(schema: &Schema) -> Type {
     match schema {
-        Schema::Bool(_) => create_named_type("String"), // Fallback to String
+        Schema::Bool(_) => create_named_type("JSON"), // Fallback to JSON
         Schema::Object(schema_obj) => {
             if let Some(instance_type) = &schema_obj.instance_type {
                 match instance_type {
                     SingleOrVec::Single(instance_type) => match instance_type.as_ref() {
                         InstanceType::Array => map_array_type(schema_obj),
-                        other => map_instance_type_to_ndc(other),
+                        other => map_instance_type_to_ndc(other, schema_format(schema_obj)),
                     },
                     SingleOrVec::Vec(types) => {
-                        // For multiple types, use first one or fallback to String
-                        if types.len() == 1 {
-                            match &types[0] {
-                                InstanceType::Array => map_array_type(schema_obj),
-                                other => map_instance_type_to_ndc(other),
+                        // A `null` member makes the type nullable, e.g. ["string", "null"]
+                        let nullable = types.contains(&InstanceType::Null);
+                        let non_null_types = types
+                            .iter()
+                            .copied()
+                            .filter(|instance_type| *instance_type != InstanceType::Null)
+                            .collect::<Vec<_>>();
+                        let underlying_type = match non_null_types.as_slice() {
+                            [InstanceType::Array] => map_array_type(schema_obj),
+                            [other] => map_instance_type_to_ndc(other, schema_format(schema_obj)),
+                            // For multiple types, fallback to JSON
+                            _ => create_named_type("JSON"),
+                        };
+                        if nullable {
+                            Type::Nullable {
+                                underlying_type: Box::new(underlying_type),
                             }
                         } else {
-                            create_named_type("String")
+                            underlying_type
                         }
                     }
                 }
+            } else if let Some(variants) = schema_obj
+                .subschemas
+                .as_ref()
+                .and_then(|subschemas| subschemas.one_of.as_ref().or(subschemas.any_of.as_ref()))
+            {
+                map_union_type(variants)
+            } else {
+                // No instance type specified, default to JSON
+                create_named_type("JSON")
+            }
+        }
+    }
+}
+
+/// Check if a schema only accepts `null`
+fn is_null_schema(schema: &Schema) -> bool {
+    match schema {
+        Schema::Object(schema_obj) => matches!(
+            &schema_obj.instance_type,
+            Some(SingleOrVec::Single(instance_type)) if **instance_type == InstanceType::Null
+        ),
+        Schema::Bool(_) => false,
+    }
+}
+
+/// Map a `oneOf`/`anyOf` union to the common supertype of its variants.
+/// `null` variants make the result nullable, `Int` and `Float` widen to `Float`, and arrays
+/// unify element-wise. Unions without a common supertype fall back to JSON.
+fn map_union_type(variants: &[Schema]) -> Type {
+    let mut nullable = false;
+    let mut unified: Option<Type> = None;
+
+    for variant in variants {
+        if is_null_schema(variant) {
+            nullable = true;
+            continue;
+        }
+        let variant_type = match map_schema_to_ndc_type(variant) {
+            Type::Nullable { underlying_type } => {
+                nullable = true;
+                *underlying_type
+            }
+            other => other,
+        };
+        unified = match unified {
+            None => Some(variant_type),
+            Some(existing) => match unify_types(existing, variant_type) {
+                Some(common_type) => Some(common_type),
+                None => return create_named_type("JSON"),
+            },
+        };
+    }
+
+    let underlying_type = unified.unwrap_or_else(|| create_named_type("JSON"));
+    if nullable {
+        Type::Nullable {
+            underlying_type: Box::new(underlying_type),
+        }
+    } else {
+        underlying_type
+    }
+}
+
+/// Find the common supertype of two NDC types, if there is one
+fn unify_types(left: Type, right: Type) -> Option<Type> {
+    match (left, right) {
+        (Type::Named { name: left }, Type::Named { name: right }) => {
+            let is_numeric = |name: &str| name == "Int" || name == "Float";
+            if left.as_str() == right.as_str() {
+                Some(Type::Named { name: left })
+            } else if is_numeric(left.as_str()) && is_numeric(right.as_str()) {
+                Some(create_named_type("Float"))
+            } else {
+                None
+            }
+        }
+        (
+            Type::Array { element_type: left },
+            Type::Array {
+                element_type: right,
+            },
+        ) => unify_types(*left, *right).map(|element_type| Type::Array {
+            element_type: Box::new(element_type),
+        }),
+        _ => None,
+    }
+}
+
+/// Build an argument description from its schema, marking deprecated arguments and
+/// appending any default value, validation constraints and example values
+fn argument_description(schema: &SchemaObject) -> Option<String> {
+    let mut parts = Vec::new();
+    let metadata = schema.metadata.as_deref();
+    if metadata.is_some_and(|metadata| metadata.deprecated) {
+        parts.push("DEPRECATED.".to_string());
+    }
+    if let Some(description) = metadata.and_then(|metadata| metadata.description.as_ref()) {
+        parts.push(description.clone());
+    }
+    if let Some(default) = metadata.and_then(|metadata| metadata.default.as_ref()) {
+        parts.push(format!("Defaults to {}.", default));
+    }
+    let constraints = argument_constraints(schema);
+    if !constraints.is_empty() {
+        parts.push(format!("Constraints: {}.", constraints.join(", ")));
+    }
+    // Some schemas, such as those generated from OpenAPI, use the singular `example`
+    let examples = metadata
+        .map(|metadata| metadata.examples.iter())
+        .into_iter()
+        .flatten()
+        .chain(schema.extensions.get("example"))
+        .map(|example| example.to_string())
+        .collect::<Vec<_>>();
+    if !examples.is_empty() {
+        parts.push(format!("Examples: {}", examples.join(", ")));
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+/// Render the numeric, string length, pattern and item count constraints of a schema
+fn argument_constraints(schema: &SchemaObject) -> Vec<String> {
+    let mut constraints = Vec::new();
+    if let Some(number) = &schema.number {
+        if let Some(minimum) = number.minimum {
+            constraints.push(format!("minimum {}", minimum));
+        }
+        if let Some(minimum) = number.exclusive_minimum {
+            constraints.push(format!("greater than {}", minimum));
+        }
+        if let Some(maximum) = number.maximum {
+            constraints.push(format!("maximum {}", maximum));
+        }
+        if let Some(maximum) = number.exclusive_maximum {
+            constraints.push(format!("less than {}", maximum));
+        }
+        if let Some(multiple_of) = number.multiple_of {
+            constraints.push(format!("multiple of {}", multiple_of));
+        }
+    }
+    if let Some(string) = &schema.string {
+        if let Some(min_length) = string.min_length {
+            constraints.push(format!("at least {} characters", min_length));
+        }
+        if let Some(max_length) = string.max_length {
+            constraints.push(format!("at most {} characters", max_length));
+        }
+        if let Some(pattern) = &string.pattern {
+            constraints.push(format!("matches pattern {}", pattern));
+        }
+    }
+    if let Some(array) = &schema.array {
+        if let Some(min_items) = array.min_items {
+            constraints.push(format!("at least {} items", min_items));
+        }
+        if let Some(max_items) = array.max_items {
+            constraints.push(format!("at most {} items", max_items));
+        }
+        if array.unique_items == Some(true) {
+            constraints.push("unique items".to_string());
+        }
+    }
+    constraints
+}
+
+/// Check a value against the numeric, string length, pattern and item count constraints of
+/// a schema, returning the first violated constraint
+fn constraint_violation(schema: &SchemaObject, value: &serde_json::Value) -> Option<String> {
+    use serde_json::Value;
+
+    if let (Some(number), Some(validation)) = (value.as_f64(), &schema.number) {
+        if let Some(minimum) = validation.minimum.filter(|minimum| number < *minimum) {
+            return Some(format!("must be at least {}", minimum));
+        }
+        if let Some(minimum) = validation
+            .exclusive_minimum
+            .filter(|minimum| number <= *minimum)
+        {
+            return Some(format!("must be greater than {}", minimum));
+        }
+        if let Some(maximum) = validation.maximum.filter(|maximum| number > *maximum) {
+            return Some(format!("must be at most {}", maximum));
+        }
+        if let Some(maximum) = validation
+            .exclusive_maximum
+            .filter(|maximum| number >= *maximum)
+        {
+            return Some(format!("must be less than {}", maximum));
+        }
+        if let Some(multiple_of) = validation
+            .multiple_of
+            .filter(|multiple_of| *multiple_of > 0.0 && (number / multiple_of).fract() != 0.0)
+        {
+            return Some(format!("must be a multiple of {}", multiple_of));
+        }
+    }
+    if let (Value::String(string), Some(validation)) = (value, &schema.string) {
+        let length = string.chars().count() as u32;
+        if let Some(min_length) = validation
+            .min_length
+            .filter(|min_length| length < *min_length)
+        {
+            return Some(format!("must be at least {} characters long", min_length));
+        }
+        if let Some(max_length) = validation
+            .max_length
+            .filter(|max_length| length > *max_length)
+        {
+            return Some(format!("must be at most {} characters long", max_length));
+        }
+        // Patterns that aren't supported by the regex engine are not enforced
+        if let Some(pattern) = &validation.pattern {
+            if Regex::new(pattern).is_ok_and(|regex| !regex.is_match(string)) {
+                return Some(format!("must match pattern {}", pattern));
+            }
+        }
+    }
+    if let (Value::Array(items), Some(validation)) = (value, &schema.array) {
+        let count = items.len() as u32;
+        if let Some(min_items) = validation.min_items.filter(|min_items| count < *min_items) {
+            return Some(format!("must have at least {} items", min_items));
+        }
+        if let Some(max_items) = validation.max_items.filter(|max_items| count > *max_items) {
+            return Some(format!("must have at most {} items", max_items));
+        }
+        if validation.unique_items == Some(true)
+            && items
+                .iter()
+                .enumerate()
+                .any(|(i, item)| items[..i].contains(item))
+        {
+            return Some("must have unique items".to_string());
+        }
+    }
+    None
+}
+
+/// Check arguments against the constraints of the tool's input schema, so invalid calls
+/// fail before reaching the MCP server
+pub(crate) fn check_argument_constraints(
+    tool: &Tool,
+    arguments: &serde_json::Map<String, serde_json::Value>,
+) -> Result<(), String> {
+    let Ok(input_schema) =
+        serde_json::from_value::<ObjectValidation>(resolve_schema_refs(&tool.input_schema))
+    else {
+        return Ok(());
+    };
+    for (argument_name, value) in arguments {
+        let Some(Schema::Object(schema)) = input_schema.properties.get(argument_name) else {
+            continue;
+        };
+        if let Some(violation) = constraint_violation(schema, value) {
+            return Err(format!("Argument {} {}", argument_name, violation));
+        }
+    }
+    Ok(())
+}
+
+/// Check that binary arguments, which are exposed as `Bytes`, are valid base64 and within the
+/// size limit
+pub(crate) fn check_binary_arguments(
+    tool: &Tool,
+    arguments: &serde_json::Map<String, serde_json::Value>,
+    max_bytes: Option<usize>,
+) -> Result<(), String> {
+    let Ok(input_schema) =
+        serde_json::from_value::<ObjectValidation>(resolve_schema_refs(&tool.input_schema))
+    else {
+        return Ok(());
+    };
+    for (argument_name, value) in arguments {
+        if let Some(Schema::Object(schema)) = input_schema.properties.get(argument_name) {
+            check_binary_value(argument_name, schema, value, max_bytes)?;
+        }
+    }
+    Ok(())
+}
+
+/// Check a binary value, or the binary fields nested in an object or array value. `path`
+/// names the value in errors.
+fn check_binary_value(
+    path: &str,
+    schema: &SchemaObject,
+    value: &serde_json::Value,
+    max_bytes: Option<usize>,
+) -> Result<(), String> {
+    match value {
+        serde_json::Value::String(value)
+            if matches!(schema_format(schema), Some("byte" | "binary")) =>
+        {
+            let bytes = BASE64_STANDARD
+                .decode(value)
+                .map_err(|err| format!("Argument {} is not valid base64: {}", path, err))?;
+            if let Some(max_bytes) = max_bytes.filter(|max_bytes| bytes.len() > *max_bytes) {
+                return Err(format!(
+                    "Argument {} is {} bytes, more than the limit of {} bytes",
+                    path,
+                    bytes.len(),
+                    max_bytes
+                ));
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            let Some(object) = &schema.object else {
+                return Ok(());
+            };
+            for (field_name, value) in fields {
+                if let Some(Schema::Object(schema)) = object.properties.get(field_name) {
+                    check_binary_value(
+                        &format!("{}.{}", path, field_name),
+                        schema,
+                        value,
+                        max_bytes,
+                    )?;
+                }
+            }
+        }
+        serde_json::Value::Array(elements) => {
+            let Some(SingleOrVec::Single(items)) =
+                schema.array.as_ref().and_then(|array| array.items.as_ref())
+            else {
+                return Ok(());
+            };
+            let Schema::Object(items) = items.as_ref() else {
+                return Ok(());
+            };
+            for (index, value) in elements.iter().enumerate() {
+                check_binary_value(&format!("{}[{}]", path, index), items, value, max_bytes)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Check if a tool is marked `deprecated: true` in its input schema
+pub(crate) fn is_deprecated_tool(tool: &Tool) -> bool {
+    tool.input_schema.get("deprecated") == Some(&serde_json::Value::Bool(true))
+}
+
+/// Remove optional arguments marked `deprecated: true` from a tool's input schema.
+/// Required arguments are kept since the tool cannot be called without them.
+pub(crate) fn remove_deprecated_arguments(tool: &mut Tool) {
+    let input_schema = Arc::make_mut(&mut tool.input_schema);
+    let required = input_schema
+        .get("required")
+        .and_then(|required| required.as_array())
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(|name| name.as_str().map(str::to_string))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if let Some(serde_json::Value::Object(properties)) = input_schema.get_mut("properties") {
+        properties.retain(|name, property| {
+            required.contains(name)
+                || property.get("deprecated") != Some(&serde_json::Value::Bool(true))
+        });
+    }
+}
+
+/// Check if a tool may be destructive. As in the MCP specification, tools that are not
+/// annotated read-only are destructive unless annotated `destructiveHint: false`.
+pub(crate) fn is_destructive_tool(tool: &Tool) -> bool {
+    let annotations = tool.annotations.as_ref();
+    let read_only = annotations
+        .and_then(|annotations| annotations.read_only_hint)
+        .unwrap_or(false);
+    let destructive = annotations
+        .and_then(|annotations| annotations.destructive_hint)
+        .unwrap_or(true);
+    !read_only && destructive
+}
+
+/// Get the description of a procedure, describing the tool's destructive and idempotent hints
+fn procedure_description(tool: &Tool) -> Option<String> {
+    let mut parts: Vec<String> = tool_description(tool).into_iter().collect();
+    if let Some(annotations) = &tool.annotations {
+        match annotations.destructive_hint {
+            Some(true) => parts.push("Destructive: may delete or overwrite data.".to_string()),
+            Some(false) => parts.push("Non-destructive: only adds data.".to_string()),
+            None => {}
+        }
+        match annotations.idempotent_hint {
+            Some(true) => parts.push(
+                "Idempotent: repeated calls with the same arguments have no additional effect."
+                    .to_string(),
+            ),
+            Some(false) => parts.push("Not idempotent.".to_string()),
+            None => {}
+        }
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+/// The human-readable title of a tool, from its `title` or else its `title` annotation. Titles
+/// that only repeat the name are ignored.
+pub(crate) fn tool_title(tool: &Tool) -> Option<&str> {
+    tool.title
+        .as_deref()
+        .or_else(|| {
+            tool.annotations
+                .as_ref()
+                .and_then(|annotations| annotations.title.as_deref())
+        })
+        .map(str::trim)
+        .filter(|title| !title.is_empty() && *title != tool.name)
+}
+
+/// The description of a tool, prefixed with its title
+fn tool_description(tool: &Tool) -> Option<String> {
+    let description = tool.description.as_ref().map(|d| d.to_string());
+    let description = match (tool_title(tool), description) {
+        (Some(title), Some(description)) => {
+            let separator = if title.ends_with(['.', '!', '?', ':']) {
+                " "
             } else {
-                // No instance type specified, default to String
-                create_named_type("String")
+                ". "
+            };
+            Some(format!("{}{}{}", title, separator, description))
+        }
+        (Some(title), None) => Some(title.to_string()),
+        (None, description) => description,
+    };
+    if !is_deprecated_tool(tool) {
+        return description;
+    }
+    match description {
+        Some(description) => Some(format!("DEPRECATED. {}", description)),
+        None => Some("DEPRECATED.".to_string()),
+    }
+}
+
+/// Append the routing hints configured for a tool to its description, as JSON agents can parse
+fn with_routing_hints(
+    description: Option<String>,
+    server_name: &McpServerName,
+    tool: &Tool,
+    configuration: &ConnectorConfig,
+) -> Option<String> {
+    let Some(routing_hints) = configuration
+        .tool_config(server_name, &tool.name)
+        .and_then(|tool_config| tool_config.routing_hints.as_ref())
+    else {
+        return description;
+    };
+    let hints = format!(
+        "Routing hints: {}",
+        serde_json::to_string(routing_hints).unwrap_or_default()
+    );
+    match description {
+        Some(description) => Some(format!("{} {}", description, hints)),
+        None => Some(hints),
+    }
+}
+
+/// Inline local `$ref` references (e.g. `#/$defs/Address` or `#/definitions/Address`) in a
+/// tool input schema so referenced types are expanded before NDC type mapping
+pub(crate) fn resolve_schema_refs(input_schema: &rmcp::model::JsonObject) -> serde_json::Value {
+    let root = serde_json::Value::Object(input_schema.clone());
+    resolve_refs_in_value(&root, &root, &mut Vec::new())
+}
+
+/// Recursively inline `$ref`s in `value`, looking them up in `root`.
+/// Keywords next to a `$ref` (such as `description`) take precedence over the referenced
+/// schema. Recursive and unresolvable references are dropped, leaving an untyped schema.
+fn resolve_refs_in_value(
+    value: &serde_json::Value,
+    root: &serde_json::Value,
+    visiting: &mut Vec<String>,
+) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(object) => {
+            let mut resolved = serde_json::Map::new();
+            if let Some(serde_json::Value::String(reference)) = object.get("$ref") {
+                let target = reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| root.pointer(pointer));
+                if let Some(target) = target {
+                    if !visiting.contains(reference) {
+                        visiting.push(reference.clone());
+                        if let serde_json::Value::Object(target_object) =
+                            resolve_refs_in_value(target, root, visiting)
+                        {
+                            resolved = target_object;
+                        }
+                        visiting.pop();
+                    }
+                }
+            }
+            for (key, value) in object {
+                if key != "$ref" {
+                    resolved.insert(key.clone(), resolve_refs_in_value(value, root, visiting));
+                }
             }
+            serde_json::Value::Object(resolved)
         }
+        serde_json::Value::Array(items) => serde_json::Value::Array(
+            items
+                .iter()
+                .map(|item| resolve_refs_in_value(item, root, visiting))
+                .collect(),
+        ),
+        other => other.clone(),
     }
 }
 
+/// Map the input schema of a tool to NDC arguments. Arguments with object schemas get
+/// object types, and arguments with a `const` string value single-value enum scalar types,
+/// named `{type_name}_{argument_name}`.
 fn tool_arguments_schema(
+    type_name: &str,
     input_schema: &rmcp::model::JsonObject,
+    object_types: &mut BTreeMap<String, ObjectType>,
+    scalar_types: &mut BTreeMap<models::ScalarTypeName, models::ScalarType>,
 ) -> BTreeMap<ArgumentName, ArgumentInfo> {
-    // Parse input schema as ObjectValidation
+    // Parse input schema as ObjectValidation, with references resolved
     let input_schema: ObjectValidation =
-        serde_json::from_value(serde_json::Value::Object(input_schema.clone())).unwrap();
+        serde_json::from_value(resolve_schema_refs(input_schema)).unwrap();
     let mut arguments = BTreeMap::new();
     // Iterate over properties
     for (property_name, property) in input_schema.properties {
         // Build argument name
         let argument_name = ArgumentName::new(property_name.as_str().into());
         // Map JSON schema type to NDC type
-        let mut argument_type = map_schema_to_ndc_type(&property);
-        if !input_schema.required.contains(&property_name) {
+        let argument_type_name = format!("{}_{}", type_name, property_name);
+        let const_value = match &property {
+            Schema::Object(schema_obj) => schema_obj.const_value.as_ref(),
+            Schema::Bool(_) => None,
+        };
+        let mut argument_type = if let Some(serde_json::Value::String(value)) = const_value {
+            scalar_types.insert(
+                argument_type_name.clone().into(),
+                create_scalar_type(models::TypeRepresentation::Enum {
+                    one_of: vec![value.clone()],
+                }),
+            );
+            create_named_type(&argument_type_name)
+        } else {
+            map_structured_schema_to_ndc_type(&argument_type_name, &property, object_types)
+        };
+        // Constant arguments are filled in when omitted, as callers have no choice anyway
+        if (!input_schema.required.contains(&property_name) || const_value.is_some())
+            && !matches!(argument_type, Type::Nullable { .. })
+        {
             argument_type = Type::Nullable {
                 underlying_type: Box::new(argument_type),
             };
         }
         let argument_info = ArgumentInfo {
-            description: property
-                .into_object()
-                .metadata
-                .and_then(|m| m.description.clone()),
+            description: argument_description(&property.into_object()),
             argument_type,
         };
         // Insert argument info into arguments
@@ -145,22 +873,215 @@ fn tool_arguments_schema(
     arguments
 }
 
-/// Map MCP resources to NDC collections
+/// Replace arguments populated from forwarded headers with the headers argument, so
+/// clients cannot supply them directly
+fn hide_forwarded_header_arguments(
+    arguments: &mut BTreeMap<ArgumentName, ArgumentInfo>,
+    forwarded_headers: &ForwardedHeadersConfig,
+) {
+    let mut hidden = false;
+    for argument_name in forwarded_headers.mappings.values() {
+        hidden |= arguments
+            .remove(&ArgumentName::new(argument_name.as_str().into()))
+            .is_some();
+    }
+    if hidden {
+        arguments.insert(
+            ArgumentName::new(forwarded_headers.argument.as_str().into()),
+            ArgumentInfo {
+                description: Some("HTTP headers forwarded by the engine".to_string()),
+                argument_type: create_named_type("HeaderMap"),
+            },
+        );
+    }
+}
+
+/// Replace the cursor argument of a paginated tool with the page number and pagination
+/// session arguments
+fn replace_cursor_argument(
+    arguments: &mut BTreeMap<ArgumentName, ArgumentInfo>,
+    pagination: &PaginationConfig,
+) {
+    arguments.remove(&ArgumentName::new(
+        pagination.cursor_argument.as_str().into(),
+    ));
+    arguments.insert(
+        ArgumentName::new(pagination.page_argument.as_str().into()),
+        ArgumentInfo {
+            description: Some("The page to return, from 1".to_string()),
+            argument_type: Type::Nullable {
+                underlying_type: Box::new(create_named_type("Int")),
+            },
+        },
+    );
+    arguments.insert(
+        ArgumentName::new(pagination.session_argument.as_str().into()),
+        ArgumentInfo {
+            description: Some(
+                "A name for the pages being browsed, to continue from the pages already \
+                 returned instead of the first one"
+                    .to_string(),
+            ),
+            argument_type: Type::Nullable {
+                underlying_type: Box::new(create_named_type("String")),
+            },
+        },
+    );
+}
+
+/// Map MCP resources to NDC collections. Resources configured with rows get a collection of
+/// their parsed rows, typed by a `{collection}_Row` object type.
 fn map_resources_to_collections(
     server_name: &McpServerName,
     resources: &HashMap<String, Resource>,
+    state: &ConnectorState,
+    configuration: &ConnectorConfig,
+    object_types: &mut BTreeMap<String, ObjectType>,
 ) -> Vec<CollectionInfo> {
     let mut collections = Vec::new();
 
     for (resource_id, resource) in resources {
         // Create collection info with server_name prefix
-        let description = resource.description.clone().map(|d| d.to_string());
+        let name = state.ndc_name(server_name, McpItemKind::Resource, resource_id);
+        let description = resource_description(
+            resource.description.as_deref(),
+            resource.annotations.as_ref(),
+        );
+        let (collection_type, uniqueness_constraints) =
+            match configuration.resource_rows(server_name, &resource.name) {
+                Some(rows_config) => {
+                    let row_type = format!("{}_Row", name);
+                    object_types.insert(row_type.clone(), resource_row_type(rows_config));
+                    (row_type, BTreeMap::new())
+                }
+                None => ("ResourceOutput".to_string(), uri_uniqueness_constraint()),
+            };
         let collection = CollectionInfo {
-            name: format!("{}__{}", server_name.0, resource_id).into(),
+            name: name.into(),
             description,
             arguments: BTreeMap::new(), // No arguments for collections
+            collection_type: collection_type.into(),
+            uniqueness_constraints,
+            relational_mutations: None,
+        };
+
+        collections.push(collection);
+    }
+
+    collections
+}
+
+/// The object type of the rows parsed from a resource, with a nullable field per column
+fn resource_row_type(rows_config: &ResourceRowsConfig) -> ObjectType {
+    let fields = rows_config
+        .columns
+        .iter()
+        .map(|(column, column_type)| {
+            (
+                column.as_str().into(),
+                ObjectField {
+                    description: None,
+                    r#type: Type::Nullable {
+                        underlying_type: Box::new(create_named_type(column_type.scalar_type())),
+                    },
+                    arguments: BTreeMap::new(),
+                },
+            )
+        })
+        .collect();
+    ObjectType {
+        description: Some("A row parsed from the content of a resource".to_string()),
+        fields,
+        foreign_keys: BTreeMap::new(),
+    }
+}
+
+/// Describe a resource collection, including the intended audience and priority from the
+/// resource annotations
+fn resource_description(
+    description: Option<&str>,
+    annotations: Option<&Annotations>,
+) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(description) = description {
+        parts.push(description.to_string());
+    }
+    if let Some(annotations) = annotations {
+        if let Some(audience) = annotations
+            .audience
+            .as_ref()
+            .filter(|audience| !audience.is_empty())
+        {
+            let audience = audience
+                .iter()
+                .map(|role| match role {
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            parts.push(format!("Audience: {}.", audience));
+        }
+        if let Some(priority) = annotations.priority {
+            parts.push(format!("Priority: {}.", priority));
+        }
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+/// Resource rows are unique by `uri`, which lets them be modeled as entities
+fn uri_uniqueness_constraint() -> BTreeMap<String, models::UniquenessConstraint> {
+    BTreeMap::from([(
+        "ResourceByUri".to_string(),
+        models::UniquenessConstraint {
+            unique_columns: vec![models::FieldName::new("uri".into())],
+        },
+    )])
+}
+
+/// Map MCP resource templates to NDC collections with an argument per URI template variable
+fn map_resource_templates_to_collections(
+    server_name: &McpServerName,
+    resource_templates: &HashMap<String, ResourceTemplate>,
+    state: &ConnectorState,
+) -> Vec<CollectionInfo> {
+    let mut collections = Vec::new();
+
+    for (template_id, template) in resource_templates {
+        let mut arguments = BTreeMap::new();
+        for variable in template_variables(&template.raw.uri_template) {
+            let string_type = create_named_type("String");
+            let argument_type = if variable.optional {
+                Type::Nullable {
+                    underlying_type: Box::new(string_type),
+                }
+            } else {
+                string_type
+            };
+            arguments.insert(
+                ArgumentName::new(variable.name.into()),
+                ArgumentInfo {
+                    description: None,
+                    argument_type,
+                },
+            );
+        }
+
+        let collection = CollectionInfo {
+            name: state
+                .ndc_name(server_name, McpItemKind::ResourceTemplate, template_id)
+                .into(),
+            description: resource_description(
+                template.raw.description.as_deref(),
+                template.annotations.as_ref(),
+            ),
+            arguments,
             collection_type: "ResourceOutput".to_string().into(),
-            uniqueness_constraints: BTreeMap::new(),
+            uniqueness_constraints: uri_uniqueness_constraint(),
             relational_mutations: None,
         };
 
@@ -170,27 +1091,45 @@ fn map_resources_to_collections(
     collections
 }
 
-/// Map read-only MCP tools to NDC functions
+/// Map read-only MCP tools, or all tools of servers exposing them as functions, to NDC
+/// functions
 fn map_tools_to_functions(
     server_name: &McpServerName,
     tools: &HashMap<String, Tool>,
+    state: &ConnectorState,
+    configuration: &ConnectorConfig,
+    object_types: &mut BTreeMap<String, ObjectType>,
+    scalar_types: &mut BTreeMap<models::ScalarTypeName, models::ScalarType>,
 ) -> Vec<FunctionInfo> {
     let mut functions = Vec::new();
 
     for (tool_id, tool) in tools {
-        // Check if tool is read-only based on annotations
-        if is_read_only_tool(tool) {
-            // Convert arguments to BTreeMap with ArgumentInfo
-            let arguments = tool_arguments_schema(&tool.input_schema);
-
+        // Check if tool is exposed as a function, and not as a collection of rows
+        if is_function_tool(server_name, tool, configuration)
+            && !is_rows_tool(server_name, tool, configuration)
+        {
             // Create function info with server_name prefix
+            let function_name = state.ndc_name(server_name, McpItemKind::Tool, tool_id);
+            let arguments = function_tool_arguments(
+                server_name,
+                &function_name,
+                tool,
+                configuration,
+                object_types,
+                scalar_types,
+            );
+
+            let result_type = tool_result_type(&function_name, tool, configuration, object_types);
             let function = FunctionInfo {
-                name: format!("{}__{}", server_name.0, tool_id).into(),
-                description: tool.description.as_ref().map(|d| d.to_string()),
+                name: function_name.into(),
+                description: with_routing_hints(
+                    tool_description(tool),
+                    server_name,
+                    tool,
+                    configuration,
+                ),
                 arguments,
-                result_type: Type::Named {
-                    name: "ToolOutput".to_string().into(),
-                },
+                result_type,
             };
 
             functions.push(function);
@@ -200,115 +1139,659 @@ fn map_tools_to_functions(
     functions
 }
 
-/// Map mutable MCP tools to NDC procedures
-fn map_tools_to_procedures(
+/// Map tools returning their results as rows to NDC collections, with the arguments of the
+/// tool and a `{collection}_Row` type from the items of the output schema
+fn map_rows_tools_to_collections(
     server_name: &McpServerName,
     tools: &HashMap<String, Tool>,
-) -> Vec<ProcedureInfo> {
-    let mut procedures = Vec::new();
+    state: &ConnectorState,
+    configuration: &ConnectorConfig,
+    object_types: &mut BTreeMap<String, ObjectType>,
+    scalar_types: &mut BTreeMap<models::ScalarTypeName, models::ScalarType>,
+) -> Vec<CollectionInfo> {
+    let mut collections = Vec::new();
 
     for (tool_id, tool) in tools {
-        // Check if tool is mutable (not read-only) based on annotations
-        if !is_read_only_tool(tool) {
-            // Convert arguments to BTreeMap with ArgumentInfo
-            let arguments = tool_arguments_schema(&tool.input_schema);
-
-            // Create procedure info with server_name prefix
-            let procedure = ProcedureInfo {
-                name: format!("{}__{}", server_name.0, tool_id).into(),
-                description: tool.description.as_ref().map(|d| d.to_string()),
-                arguments,
-                result_type: Type::Named {
-                    name: "ToolOutput".to_string().into(),
-                },
-            };
-
-            procedures.push(procedure);
+        if !is_rows_tool(server_name, tool, configuration) {
+            continue;
         }
-    }
-
+        let Some(row_schema) = tool_rows_schema(tool)
+            .and_then(|(_, items)| serde_json::from_value::<Schema>(items).ok())
+        else {
+            continue;
+        };
+        let collection_name = state.ndc_name(server_name, McpItemKind::Tool, tool_id);
+        let arguments = function_tool_arguments(
+            server_name,
+            &collection_name,
+            tool,
+            configuration,
+            object_types,
+            scalar_types,
+        );
+        let row_type_name = format!("{}_Row", collection_name);
+        map_structured_schema_to_ndc_type(&row_type_name, &row_schema, object_types);
+        collections.push(CollectionInfo {
+            name: collection_name.into(),
+            description: with_routing_hints(
+                tool_description(tool),
+                server_name,
+                tool,
+                configuration,
+            ),
+            arguments,
+            collection_type: row_type_name.into(),
+            uniqueness_constraints: BTreeMap::new(),
+            relational_mutations: None,
+        });
+    }
+
+    collections
+}
+
+/// The arguments of the function or collection of a tool
+fn function_tool_arguments(
+    server_name: &McpServerName,
+    function_name: &str,
+    tool: &Tool,
+    configuration: &ConnectorConfig,
+    object_types: &mut BTreeMap<String, ObjectType>,
+    scalar_types: &mut BTreeMap<models::ScalarTypeName, models::ScalarType>,
+) -> BTreeMap<ArgumentName, ArgumentInfo> {
+    // Convert arguments to BTreeMap with ArgumentInfo
+    let mut arguments = tool_arguments_schema(
+        &format!("{}_Input", function_name),
+        &tool.input_schema,
+        object_types,
+        scalar_types,
+    );
+    if let Some(forwarded_headers) = &configuration.forwarded_headers {
+        hide_forwarded_header_arguments(&mut arguments, forwarded_headers);
+    }
+    if let Some(pagination) = configuration
+        .tool_config(server_name, &tool.name)
+        .and_then(|tool_config| tool_config.pagination.as_ref())
+    {
+        replace_cursor_argument(&mut arguments, pagination);
+    }
+    arguments
+}
+
+/// Map mutable MCP tools, or all tools of servers exposing them as procedures, to NDC
+/// procedures
+fn map_tools_to_procedures(
+    server_name: &McpServerName,
+    tools: &HashMap<String, Tool>,
+    state: &ConnectorState,
+    configuration: &ConnectorConfig,
+    object_types: &mut BTreeMap<String, ObjectType>,
+    scalar_types: &mut BTreeMap<models::ScalarTypeName, models::ScalarType>,
+) -> Vec<ProcedureInfo> {
+    let mut procedures = Vec::new();
+
+    for (tool_id, tool) in tools {
+        // Check if tool is exposed as a procedure
+        if is_procedure_tool(server_name, tool, configuration) {
+            // Create procedure info with server_name prefix
+            let procedure_name = state.ndc_name(
+                server_name,
+                tool_procedure_kind(server_name, configuration),
+                tool_id,
+            );
+
+            // Convert arguments to BTreeMap with ArgumentInfo
+            let mut arguments = tool_arguments_schema(
+                &format!("{}_Input", procedure_name),
+                &tool.input_schema,
+                object_types,
+                scalar_types,
+            );
+            if let Some(forwarded_headers) = &configuration.forwarded_headers {
+                hide_forwarded_header_arguments(&mut arguments, forwarded_headers);
+            }
+
+            let result_type = tool_result_type(&procedure_name, tool, configuration, object_types);
+            let procedure = ProcedureInfo {
+                name: procedure_name.into(),
+                description: with_routing_hints(
+                    procedure_description(tool),
+                    server_name,
+                    tool,
+                    configuration,
+                ),
+                arguments,
+                result_type,
+            };
+
+            procedures.push(procedure);
+        }
+    }
+
     procedures
 }
 
-/// Create object types for resources and tools
-fn create_object_types() -> BTreeMap<String, ObjectType> {
-    let mut object_types = BTreeMap::new();
+/// Map MCP prompts to NDC functions returning the prompt messages
+fn map_prompts_to_functions(
+    server_name: &McpServerName,
+    prompts: &HashMap<String, Prompt>,
+    state: &ConnectorState,
+) -> Vec<FunctionInfo> {
+    let mut functions = Vec::new();
 
-    // Create ResourceOutput type
-    let mut resource_fields = BTreeMap::new();
-    resource_fields.insert(
+    for (prompt_id, prompt) in prompts {
+        // Prompt arguments are always strings
+        let mut arguments = BTreeMap::new();
+        for argument in prompt.arguments.iter().flatten() {
+            let string_type = create_named_type("String");
+            let argument_type = if argument.required.unwrap_or(false) {
+                string_type
+            } else {
+                Type::Nullable {
+                    underlying_type: Box::new(string_type),
+                }
+            };
+            arguments.insert(
+                ArgumentName::new(argument.name.clone().into()),
+                ArgumentInfo {
+                    description: argument.description.clone(),
+                    argument_type,
+                },
+            );
+        }
+
+        let function = FunctionInfo {
+            name: state
+                .ndc_name(server_name, McpItemKind::Prompt, prompt_id)
+                .into(),
+            description: prompt.description.clone(),
+            arguments,
+            result_type: Type::Array {
+                element_type: Box::new(create_named_type("PromptMessage")),
+            },
+        };
+
+        functions.push(function);
+    }
+
+    functions
+}
+
+/// Get the description from a schema's metadata
+fn schema_description(schema: &Schema) -> Option<String> {
+    match schema {
+        Schema::Object(schema_obj) => schema_obj
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.description.clone()),
+        Schema::Bool(_) => None,
+    }
+}
+
+/// Map a tool input or output schema to an NDC type, generating object types for objects
+/// with declared properties. Nested object types are named `{type_name}_{field_name}`, and
+/// their fields are nullable unless listed as `required` at their level of the schema.
+fn map_structured_schema_to_ndc_type(
+    type_name: &str,
+    schema: &Schema,
+    object_types: &mut BTreeMap<String, ObjectType>,
+) -> Type {
+    let Schema::Object(schema_obj) = schema else {
+        return map_schema_to_ndc_type(schema);
+    };
+    let instance_type = match &schema_obj.instance_type {
+        Some(SingleOrVec::Single(instance_type)) => Some(**instance_type),
+        _ => None,
+    };
+    let properties = schema_obj
+        .object
+        .as_deref()
+        .filter(|object| !object.properties.is_empty());
+
+    match (instance_type, properties) {
+        (Some(InstanceType::Object) | None, Some(object)) => {
+            let mut fields = BTreeMap::new();
+            for (property_name, property) in &object.properties {
+                let mut field_type = map_structured_schema_to_ndc_type(
+                    &format!("{}_{}", type_name, property_name),
+                    property,
+                    object_types,
+                );
+                if !object.required.contains(property_name)
+                    && !matches!(field_type, Type::Nullable { .. })
+                {
+                    field_type = Type::Nullable {
+                        underlying_type: Box::new(field_type),
+                    };
+                }
+                fields.insert(
+                    property_name.as_str().into(),
+                    ObjectField {
+                        description: schema_description(property),
+                        r#type: field_type,
+                        arguments: BTreeMap::new(),
+                    },
+                );
+            }
+            object_types.insert(
+                type_name.to_string(),
+                ObjectType {
+                    description: schema_description(schema),
+                    fields,
+                    foreign_keys: BTreeMap::new(),
+                },
+            );
+            create_named_type(type_name)
+        }
+        (Some(InstanceType::Array), _) => match schema_obj
+            .array
+            .as_ref()
+            .and_then(|array| array.items.as_ref())
+        {
+            Some(SingleOrVec::Single(item_schema)) => Type::Array {
+                element_type: Box::new(map_structured_schema_to_ndc_type(
+                    type_name,
+                    item_schema,
+                    object_types,
+                )),
+            },
+            _ => map_schema_to_ndc_type(schema),
+        },
+        _ => map_schema_to_ndc_type(schema),
+    }
+}
+
+/// Get the result type of a tool. Tools declaring an `outputSchema` get their own
+/// `{name}_Output` object type whose `structured_content` field is typed from the schema;
+/// all other tools use the generic `ToolOutput` type.
+fn tool_result_type(
+    name: &str,
+    tool: &Tool,
+    configuration: &ConnectorConfig,
+    object_types: &mut BTreeMap<String, ObjectType>,
+) -> Type {
+    let output_schema = tool.output_schema.as_ref().and_then(|output_schema| {
+        serde_json::from_value::<Schema>(resolve_schema_refs(output_schema)).ok()
+    });
+    let Some(output_schema) = output_schema else {
+        return create_named_type("ToolOutput");
+    };
+
+    let structured_content_type = map_structured_schema_to_ndc_type(
+        &format!("{}_StructuredContent", name),
+        &output_schema,
+        object_types,
+    );
+
+    let mut fields = BTreeMap::new();
+    fields.insert(
         "content".into(),
         ObjectField {
-            description: Some("The content of the resource".to_string()),
-            r#type: Type::Named {
-                name: "String".to_string().into(),
+            description: Some("The content items of the tool result".to_string()),
+            r#type: Type::Array {
+                element_type: Box::new(create_named_type("Content")),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+    fields.insert(
+        "structured_content".into(),
+        ObjectField {
+            description: Some("The structured output of the tool".to_string()),
+            r#type: match structured_content_type {
+                Type::Nullable { .. } => structured_content_type,
+                underlying_type => Type::Nullable {
+                    underlying_type: Box::new(underlying_type),
+                },
             },
             arguments: BTreeMap::new(),
         },
     );
+    if configuration.text_field.is_some() {
+        fields.insert("text".into(), text_output_field());
+    }
+    if configuration.tool_errors == Some(ToolErrors::Field) {
+        fields.insert("tool_error".into(), tool_error_output_field());
+    }
 
+    let output_type_name = format!("{}_Output", name);
     object_types.insert(
-        "ResourceOutput".to_string(),
+        output_type_name.clone(),
         ObjectType {
-            description: Some("Output type for MCP resources".to_string()),
-            fields: resource_fields,
+            description: Some(format!("Output type for the {} tool", tool.name)),
+            fields,
             foreign_keys: BTreeMap::new(),
         },
     );
+    create_named_type(&output_type_name)
+}
 
-    // Create Content Object
-    let mut content_fields = BTreeMap::new();
-    content_fields.insert(
-        "type".into(),
+/// The `text` field of tool outputs, with the text content of the result
+fn text_output_field() -> ObjectField {
+    ObjectField {
+        description: Some("The text content of the tool output as a single string".to_string()),
+        r#type: Type::Nullable {
+            underlying_type: Box::new(create_named_type("String")),
+        },
+        arguments: BTreeMap::new(),
+    }
+}
+
+/// The `tool_error` field of tool outputs, with the error of error results
+fn tool_error_output_field() -> ObjectField {
+    ObjectField {
+        description: Some("The error returned by the tool, if it failed".to_string()),
+        r#type: Type::Nullable {
+            underlying_type: Box::new(create_named_type("ToolError")),
+        },
+        arguments: BTreeMap::new(),
+    }
+}
+
+/// The `ToolError` type of the errors returned by tools
+fn create_tool_error_type() -> ObjectType {
+    let nullable = |name: &str| Type::Nullable {
+        underlying_type: Box::new(create_named_type(name)),
+    };
+    let field = |description: &str, r#type: Type| ObjectField {
+        description: Some(description.to_string()),
+        r#type,
+        arguments: BTreeMap::new(),
+    };
+    ObjectType {
+        description: Some("Error returned by a tool".to_string()),
+        fields: BTreeMap::from([
+            (
+                "code".into(),
+                field(
+                    "The error code, if the tool returned one",
+                    nullable("String"),
+                ),
+            ),
+            (
+                "message".into(),
+                field("The error message", create_named_type("String")),
+            ),
+            (
+                "data".into(),
+                field("Additional data about the error", nullable("JSON")),
+            ),
+        ]),
+        foreign_keys: BTreeMap::new(),
+    }
+}
+
+/// Project structured content onto the output schema of a tool, keeping the declared
+/// properties of objects, so results fit the typed result object
+pub(crate) fn project_structured_content(
+    output_schema: &rmcp::model::JsonObject,
+    structured_content: serde_json::Value,
+) -> serde_json::Value {
+    project_value(&resolve_schema_refs(output_schema), structured_content)
+}
+
+fn project_value(schema: &serde_json::Value, value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(object) => {
+            let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) else {
+                return serde_json::Value::Object(object);
+            };
+            if properties.is_empty() {
+                return serde_json::Value::Object(object);
+            }
+            object
+                .into_iter()
+                .filter_map(|(name, value)| {
+                    let property = properties.get(&name)?;
+                    Some((name, project_value(property, value)))
+                })
+                .collect()
+        }
+        serde_json::Value::Array(values) => match schema.get("items") {
+            Some(items) if items.is_object() => values
+                .into_iter()
+                .map(|value| project_value(items, value))
+                .collect(),
+            _ => serde_json::Value::Array(values),
+        },
+        value => value,
+    }
+}
+
+/// Create the `ToolInfo` type of the rows of tool catalog collections
+fn create_tool_info_type() -> ObjectType {
+    let field = |description: &str, r#type: Type| ObjectField {
+        description: Some(description.to_string()),
+        r#type,
+        arguments: BTreeMap::new(),
+    };
+    let nullable = |type_name: &str| Type::Nullable {
+        underlying_type: Box::new(create_named_type(type_name)),
+    };
+    let fields = BTreeMap::from([
+        (
+            "name".into(),
+            field("The name of the tool", create_named_type("String")),
+        ),
+        (
+            "ndc_name".into(),
+            field(
+                "The name of the function or procedure calling the tool, null if the tool is \
+                 not exposed in read-only mode",
+                nullable("String"),
+            ),
+        ),
+        (
+            "title".into(),
+            field("The human-readable title of the tool", nullable("String")),
+        ),
+        (
+            "description".into(),
+            field("The description of the tool", nullable("String")),
+        ),
+        (
+            "read_only".into(),
+            field(
+                "Whether the tool is read-only, and exposed as a function rather than a procedure",
+                create_named_type("Boolean"),
+            ),
+        ),
+        (
+            "input_schema".into(),
+            field(
+                "The JSON Schema of the tool arguments",
+                create_named_type("JSON"),
+            ),
+        ),
+        (
+            "output_schema".into(),
+            field(
+                "The JSON Schema of the tool's structured content, if declared",
+                nullable("JSON"),
+            ),
+        ),
+        (
+            "routing_hints".into(),
+            field(
+                "The configured cost, latency and safety hints of the tool, if any",
+                nullable("JSON"),
+            ),
+        ),
+    ]);
+    ObjectType {
+        description: Some("An MCP tool, as listed by tool catalog collections".to_string()),
+        fields,
+        foreign_keys: BTreeMap::new(),
+    }
+}
+
+/// Map the tools of a server to a collection listing them, so they can be discovered at
+/// runtime without a schema rebuild
+fn map_tool_catalog_to_collection(
+    server_name: &McpServerName,
+    tools: &HashMap<String, Tool>,
+    state: &ConnectorState,
+) -> Option<CollectionInfo> {
+    if tools.is_empty() {
+        return None;
+    }
+    Some(CollectionInfo {
+        name: state
+            .ndc_name(server_name, McpItemKind::ToolCatalog, TOOL_CATALOG_NAME)
+            .into(),
+        description: Some(format!("The tools of the {} MCP server", server_name.0)),
+        arguments: BTreeMap::new(),
+        collection_type: "ToolInfo".to_string().into(),
+        uniqueness_constraints: BTreeMap::from([(
+            "ToolByName".to_string(),
+            models::UniquenessConstraint {
+                unique_columns: vec![models::FieldName::new("name".into())],
+            },
+        )]),
+        relational_mutations: None,
+    })
+}
+
+/// Add the `OperationError` type, and wrap the result type of each procedure in a
+/// `{type}_BestEffort` type with a nullable `result` and a nullable `error`. The result types
+/// themselves are shared with functions, so they are left unchanged.
+fn wrap_best_effort_results(
+    procedures: &mut [ProcedureInfo],
+    object_types: &mut BTreeMap<String, ObjectType>,
+) {
+    let mut error_fields = BTreeMap::new();
+    error_fields.insert(
+        "error_class".into(),
         ObjectField {
-            description: Some("The type of the content".to_string()),
-            r#type: Type::Named {
-                name: "String".to_string().into(),
+            description: Some(
+                "The kind of error: not_found, invalid_arguments, unavailable, configuration, \
+                 transport, timeout, protocol, tool or response_too_large"
+                    .to_string(),
+            ),
+            r#type: create_named_type("String"),
+            arguments: BTreeMap::new(),
+        },
+    );
+    error_fields.insert(
+        "code".into(),
+        ObjectField {
+            description: Some("The JSON-RPC error code returned by the MCP server".to_string()),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(create_named_type("Int")),
             },
             arguments: BTreeMap::new(),
         },
     );
-    content_fields.insert(
-        "text".into(),
+    error_fields.insert(
+        "message".into(),
         ObjectField {
-            description: Some("The value of the content".to_string()),
-            r#type: Type::Named {
-                name: "String".to_string().into(),
+            description: Some("The error message".to_string()),
+            r#type: create_named_type("String"),
+            arguments: BTreeMap::new(),
+        },
+    );
+    error_fields.insert(
+        "data".into(),
+        ObjectField {
+            description: Some("The data of the error returned by the tool, if any".to_string()),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(create_named_type("JSON")),
             },
             arguments: BTreeMap::new(),
         },
     );
     object_types.insert(
-        "Content".to_string(),
+        "OperationError".to_string(),
         ObjectType {
-            description: Some("Content type for MCP tools".to_string()),
-            fields: content_fields,
+            description: Some("Error of a failed mutation operation".to_string()),
+            fields: error_fields,
             foreign_keys: BTreeMap::new(),
         },
     );
 
-    // Create ToolOutput type
-    let mut tool_fields = BTreeMap::new();
-    // content field
-    tool_fields.insert(
+    for procedure in procedures {
+        // Procedures sharing a result type share its wrapper type
+        let wrapper_type_name = match &procedure.result_type {
+            Type::Named { name } => format!("{}_BestEffort", name),
+            _ => format!("{}_BestEffort", procedure.name),
+        };
+        let result_type = match &procedure.result_type {
+            Type::Nullable { .. } => procedure.result_type.clone(),
+            underlying_type => Type::Nullable {
+                underlying_type: Box::new(underlying_type.clone()),
+            },
+        };
+        object_types
+            .entry(wrapper_type_name.clone())
+            .or_insert_with(|| {
+                let mut fields = BTreeMap::new();
+                fields.insert(
+                    "result".into(),
+                    ObjectField {
+                        description: Some(
+                            "The result of the operation, if it succeeded".to_string(),
+                        ),
+                        r#type: result_type,
+                        arguments: BTreeMap::new(),
+                    },
+                );
+                fields.insert(
+                    "error".into(),
+                    ObjectField {
+                        description: Some("The error of the operation, if it failed".to_string()),
+                        r#type: Type::Nullable {
+                            underlying_type: Box::new(create_named_type("OperationError")),
+                        },
+                        arguments: BTreeMap::new(),
+                    },
+                );
+                ObjectType {
+                    description: Some(
+                        "Result or error of a mutation operation executed in best-effort mode"
+                            .to_string(),
+                    ),
+                    fields,
+                    foreign_keys: BTreeMap::new(),
+                }
+            });
+        procedure.result_type = create_named_type(&wrapper_type_name);
+    }
+}
+
+/// Create object types for resources and tools
+fn create_object_types(configuration: &ConnectorConfig) -> BTreeMap<String, ObjectType> {
+    let mut object_types = BTreeMap::new();
+
+    // Create ResourceOutput type
+    let mut resource_fields = BTreeMap::new();
+    resource_fields.insert(
         "content".into(),
         ObjectField {
-            description: Some("The text output of the tool".to_string()),
-            r#type: Type::Array {
-                element_type: Box::new(Type::Named {
-                    name: "Content".to_string().into(),
+            description: Some("The content of the resource".to_string()),
+            r#type: Type::Named {
+                name: "String".to_string().into(),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+    resource_fields.insert(
+        "text".into(),
+        ObjectField {
+            description: Some("The text contents of the resource, if it is text".to_string()),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(Type::Named {
+                    name: "String".to_string().into(),
                 }),
             },
             arguments: BTreeMap::new(),
         },
     );
-
-    // optional structured content field
-    tool_fields.insert(
-        "structured_content".into(),
+    resource_fields.insert(
+        "blob".into(),
         ObjectField {
             description: Some(
-                "The structured output of the tool. This is a JSON string.".to_string(),
+                "The base64-encoded contents of the resource, if it is binary. Decode it \
+                 according to mime_type."
+                    .to_string(),
             ),
             r#type: Type::Nullable {
                 underlying_type: Box::new(Type::Named {
@@ -318,256 +1801,2156 @@ fn create_object_types() -> BTreeMap<String, ObjectType> {
             arguments: BTreeMap::new(),
         },
     );
-
-    object_types.insert(
-        "ToolOutput".to_string(),
-        ObjectType {
-            description: Some("Output type for MCP tools".to_string()),
-            fields: tool_fields,
-            foreign_keys: BTreeMap::new(),
+    resource_fields.insert(
+        "json".into(),
+        ObjectField {
+            description: Some(
+                "The parsed text contents of the resource, if its MIME type is JSON".to_string(),
+            ),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(Type::Named {
+                    name: "JSON".to_string().into(),
+                }),
+            },
+            arguments: BTreeMap::new(),
         },
     );
-
-    object_types
-}
-
-/// Create a scalar type with the given representation
-fn create_scalar_type(representation: models::TypeRepresentation) -> models::ScalarType {
-    models::ScalarType {
-        representation,
-        aggregate_functions: BTreeMap::new(),
-        comparison_operators: BTreeMap::new(),
-        extraction_functions: BTreeMap::new(),
-    }
-}
-
-fn create_scalar_types() -> BTreeMap<models::ScalarTypeName, models::ScalarType> {
-    let mut scalar_types = BTreeMap::new();
-
-    // Add core scalar types
-    scalar_types.insert(
-        "String".to_string().into(),
-        create_scalar_type(models::TypeRepresentation::String),
+    resource_fields.insert(
+        "name".into(),
+        ObjectField {
+            description: Some("The name of the resource".to_string()),
+            r#type: Type::Named {
+                name: "String".to_string().into(),
+            },
+            arguments: BTreeMap::new(),
+        },
     );
-    scalar_types.insert(
-        "Boolean".to_string().into(),
-        create_scalar_type(models::TypeRepresentation::Boolean),
+    resource_fields.insert(
+        "title".into(),
+        ObjectField {
+            description: Some("The human-readable title of the resource".to_string()),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(Type::Named {
+                    name: "String".to_string().into(),
+                }),
+            },
+            arguments: BTreeMap::new(),
+        },
     );
-    scalar_types.insert(
+    resource_fields.insert(
+        "uri".into(),
+        ObjectField {
+            description: Some("The URI of the resource".to_string()),
+            r#type: Type::Named {
+                name: "URI".to_string().into(),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+    resource_fields.insert(
+        "mime_type".into(),
+        ObjectField {
+            description: Some("The MIME type of the resource, if known".to_string()),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(Type::Named {
+                    name: "String".to_string().into(),
+                }),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+    resource_fields.insert(
+        "audience".into(),
+        ObjectField {
+            description: Some(
+                "The intended audience of the resource: user and/or assistant".to_string(),
+            ),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(Type::Array {
+                    element_type: Box::new(create_named_type("String")),
+                }),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+    resource_fields.insert(
+        "priority".into(),
+        ObjectField {
+            description: Some(
+                "The importance of the resource, from 0 (least) to 1 (most important)".to_string(),
+            ),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(create_named_type("Float")),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+    resource_fields.insert(
+        "description".into(),
+        ObjectField {
+            description: Some("The description of the resource".to_string()),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(Type::Named {
+                    name: "String".to_string().into(),
+                }),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+
+    resource_fields.insert(
+        "size".into(),
+        ObjectField {
+            description: Some("The size of the resource in bytes, if known".to_string()),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(Type::Named {
+                    name: "Int".to_string().into(),
+                }),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+
+    object_types.insert(
+        "ResourceOutput".to_string(),
+        ObjectType {
+            description: Some("Output type for MCP resources".to_string()),
+            fields: resource_fields,
+            foreign_keys: BTreeMap::new(),
+        },
+    );
+
+    object_types.insert("ToolInfo".to_string(), create_tool_info_type());
+    let (name, object_type) = mcp_server_object_type();
+    object_types.insert(name, object_type);
+
+    // Create Content Object
+    let mut content_fields = BTreeMap::new();
+    content_fields.insert(
+        "type".into(),
+        ObjectField {
+            description: Some("The type of the content".to_string()),
+            r#type: Type::Named {
+                name: "String".to_string().into(),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+    // The other fields depend on the type: text, image, audio, resource or resource_link
+    for (name, description) in [
+        (
+            "text",
+            "The text of text content and of embedded text resources",
+        ),
+        (
+            "data",
+            "The base64-encoded data of image and audio content and of embedded binary resources",
+        ),
+        ("mimeType", "The MIME type of the data or resource"),
+        ("uri", "The URI of embedded and linked resources"),
+        ("name", "The name of linked resources"),
+    ] {
+        content_fields.insert(
+            name.into(),
+            ObjectField {
+                description: Some(description.to_string()),
+                r#type: Type::Nullable {
+                    underlying_type: Box::new(create_named_type("String")),
+                },
+                arguments: BTreeMap::new(),
+            },
+        );
+    }
+    object_types.insert(
+        "Content".to_string(),
+        ObjectType {
+            description: Some("A content item of an MCP tool result or prompt message".to_string()),
+            fields: content_fields,
+            foreign_keys: BTreeMap::new(),
+        },
+    );
+
+    // Create ToolOutput type
+    let mut tool_fields = BTreeMap::new();
+    // content field
+    tool_fields.insert(
+        "content".into(),
+        ObjectField {
+            description: Some("The content items of the tool result".to_string()),
+            r#type: Type::Array {
+                element_type: Box::new(Type::Named {
+                    name: "Content".to_string().into(),
+                }),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+
+    // optional structured content field
+    tool_fields.insert(
+        "structured_content".into(),
+        ObjectField {
+            description: Some(if configuration.stringify_structured_content {
+                "The structured output of the tool. This is a JSON string.".to_string()
+            } else {
+                "The structured output of the tool".to_string()
+            }),
+            r#type: Type::Nullable {
+                underlying_type: Box::new(create_named_type(
+                    if configuration.stringify_structured_content {
+                        "String"
+                    } else {
+                        "JSON"
+                    },
+                )),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+
+    if configuration.text_field.is_some() {
+        tool_fields.insert("text".into(), text_output_field());
+    }
+    if configuration.tool_errors == Some(ToolErrors::Field) {
+        tool_fields.insert("tool_error".into(), tool_error_output_field());
+        object_types.insert("ToolError".to_string(), create_tool_error_type());
+    }
+
+    object_types.insert(
+        "ToolOutput".to_string(),
+        ObjectType {
+            description: Some("Output type for MCP tools".to_string()),
+            fields: tool_fields,
+            foreign_keys: BTreeMap::new(),
+        },
+    );
+
+    // Create PromptMessage type
+    let mut prompt_message_fields = BTreeMap::new();
+    prompt_message_fields.insert(
+        "role".into(),
+        ObjectField {
+            description: Some("The sender of the message, user or assistant".to_string()),
+            r#type: Type::Named {
+                name: "String".to_string().into(),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+    prompt_message_fields.insert(
+        "content".into(),
+        ObjectField {
+            description: Some("The content of the message".to_string()),
+            r#type: Type::Named {
+                name: "Content".to_string().into(),
+            },
+            arguments: BTreeMap::new(),
+        },
+    );
+    object_types.insert(
+        "PromptMessage".to_string(),
+        ObjectType {
+            description: Some("A message returned by an MCP prompt".to_string()),
+            fields: prompt_message_fields,
+            foreign_keys: BTreeMap::new(),
+        },
+    );
+
+    object_types
+}
+
+/// Create a scalar type with the given representation
+fn create_scalar_type(representation: models::TypeRepresentation) -> models::ScalarType {
+    models::ScalarType {
+        representation,
+        aggregate_functions: BTreeMap::new(),
+        comparison_operators: BTreeMap::new(),
+        extraction_functions: BTreeMap::new(),
+    }
+}
+
+/// Create a scalar type supporting the comparison operators and aggregate functions evaluated on
+/// resource collections. Ordered types also support `_lt`, `_lte`, `_gt` and `_gte`, and the
+/// `min` and `max` aggregate functions.
+fn create_comparable_scalar_type(
+    name: &str,
+    representation: models::TypeRepresentation,
+    ordered: bool,
+) -> models::ScalarType {
+    let mut comparison_operators = BTreeMap::new();
+    comparison_operators.insert(
+        "_eq".to_string().into(),
+        models::ComparisonOperatorDefinition::Equal,
+    );
+    comparison_operators.insert(
+        "_neq".to_string().into(),
+        models::ComparisonOperatorDefinition::Custom {
+            argument_type: create_named_type(name),
+        },
+    );
+    comparison_operators.insert(
+        "_in".to_string().into(),
+        models::ComparisonOperatorDefinition::In,
+    );
+    if ordered {
+        comparison_operators.insert(
+            "_lt".to_string().into(),
+            models::ComparisonOperatorDefinition::LessThan,
+        );
+        comparison_operators.insert(
+            "_lte".to_string().into(),
+            models::ComparisonOperatorDefinition::LessThanOrEqual,
+        );
+        comparison_operators.insert(
+            "_gt".to_string().into(),
+            models::ComparisonOperatorDefinition::GreaterThan,
+        );
+        comparison_operators.insert(
+            "_gte".to_string().into(),
+            models::ComparisonOperatorDefinition::GreaterThanOrEqual,
+        );
+    }
+
+    let mut aggregate_functions = BTreeMap::new();
+    aggregate_functions.insert(
+        "count".to_string().into(),
+        models::AggregateFunctionDefinition::Custom {
+            result_type: create_named_type("Int"),
+        },
+    );
+    if ordered {
+        aggregate_functions.insert(
+            "min".to_string().into(),
+            models::AggregateFunctionDefinition::Min,
+        );
+        aggregate_functions.insert(
+            "max".to_string().into(),
+            models::AggregateFunctionDefinition::Max,
+        );
+    }
+
+    models::ScalarType {
+        comparison_operators,
+        aggregate_functions,
+        ..create_scalar_type(representation)
+    }
+}
+
+fn create_scalar_types() -> BTreeMap<models::ScalarTypeName, models::ScalarType> {
+    let mut scalar_types = BTreeMap::new();
+
+    // Add core scalar types
+    scalar_types.insert(
+        "String".to_string().into(),
+        create_comparable_scalar_type("String", models::TypeRepresentation::String, true),
+    );
+    scalar_types.insert(
+        "Boolean".to_string().into(),
+        create_comparable_scalar_type("Boolean", models::TypeRepresentation::Boolean, false),
+    );
+    scalar_types.insert(
         "Int".to_string().into(),
-        create_scalar_type(models::TypeRepresentation::Int32),
+        create_comparable_scalar_type("Int", models::TypeRepresentation::Int32, true),
     );
     scalar_types.insert(
         "Float".to_string().into(),
-        create_scalar_type(models::TypeRepresentation::Float64),
+        create_comparable_scalar_type("Float", models::TypeRepresentation::Float64, true),
+    );
+
+    scalar_types.insert(
+        "BigInt".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::Int64),
+    );
+    scalar_types.insert(
+        "BigInteger".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::BigInteger),
+    );
+
+    // Add scalar types for string formats
+    scalar_types.insert(
+        "TimestampTZ".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::TimestampTZ),
+    );
+    scalar_types.insert(
+        "Date".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::Date),
+    );
+    scalar_types.insert(
+        "UUID".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::UUID),
+    );
+    scalar_types.insert(
+        "URI".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::String),
+    );
+    scalar_types.insert(
+        "Email".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::String),
+    );
+    scalar_types.insert(
+        "Bytes".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::Bytes),
+    );
+
+    // Add the scalar type for arbitrary JSON values that can't be mapped to other types
+    scalar_types.insert(
+        "JSON".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::JSON),
+    );
+
+    // Add the scalar type for forwarded HTTP headers
+    scalar_types.insert(
+        "HeaderMap".to_string().into(),
+        create_scalar_type(models::TypeRepresentation::JSON),
     );
 
     scalar_types
 }
 
 /// Generate the NDC schema from the connector state
-pub fn generate_schema_from_state(state: &ConnectorState) -> models::SchemaResponse {
+pub fn generate_schema_from_state(
+    state: &ConnectorState,
+    configuration: &ConnectorConfig,
+) -> models::SchemaResponse {
     let mut collections = Vec::new();
     let mut functions = Vec::new();
     let mut procedures = Vec::new();
 
-    // Process each MCP server from state
-    for (server_name, client) in &state.clients {
-        // Map resources to collections
-        collections.extend(map_resources_to_collections(server_name, &client.resources));
+    // Create object and scalar types
+    let mut object_types = create_object_types(configuration);
+    let mut scalar_types = create_scalar_types();
+
+    // Process each MCP server from state
+    for (server_name, client) in &state.clients {
+        // Map resources to collections
+        collections.extend(map_resources_to_collections(
+            server_name,
+            &client.resources,
+            state,
+            configuration,
+            &mut object_types,
+        ));
+        collections.extend(map_resource_templates_to_collections(
+            server_name,
+            &client.resource_templates,
+            state,
+        ));
+
+        // Map tools to functions and procedures, adding any typed output objects
+        functions.extend(map_tools_to_functions(
+            server_name,
+            &client.tools,
+            state,
+            configuration,
+            &mut object_types,
+            &mut scalar_types,
+        ));
+        if !configuration.read_only {
+            procedures.extend(map_tools_to_procedures(
+                server_name,
+                &client.tools,
+                state,
+                configuration,
+                &mut object_types,
+                &mut scalar_types,
+            ));
+        }
+
+        // Map tools returning rows to collections
+        collections.extend(map_rows_tools_to_collections(
+            server_name,
+            &client.tools,
+            state,
+            configuration,
+            &mut object_types,
+            &mut scalar_types,
+        ));
+
+        // List the tools in a collection
+        collections.extend(map_tool_catalog_to_collection(
+            server_name,
+            &client.tools,
+            state,
+        ));
+
+        // Map prompts to functions
+        functions.extend(map_prompts_to_functions(
+            server_name,
+            &client.prompts,
+            state,
+        ));
+    }
+
+    // List the configured servers
+    collections.push(mcp_servers_collection_info());
+
+    // Add the diagnostics function
+    if configuration.diagnostics {
+        functions.push(diagnostics_function_info());
+    }
+
+    // In best-effort mode procedure results carry the error of a failed operation
+    if configuration.best_effort_mutations {
+        wrap_best_effort_results(&mut procedures, &mut object_types);
+    }
+
+    // Keep a stable order, so the schema and its hash don't depend on map iteration order
+    collections.sort_by(|a, b| a.name.cmp(&b.name));
+    functions.sort_by(|a, b| a.name.cmp(&b.name));
+    procedures.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // Convert object types to use ObjectTypeName keys
+    let mut typed_object_types = BTreeMap::new();
+    for (name, obj_type) in object_types {
+        typed_object_types.insert(name.into(), obj_type);
+    }
+
+    // Create schema response
+    models::SchemaResponse {
+        collections,
+        functions,
+        procedures,
+        object_types: typed_object_types,
+        scalar_types,
+        capabilities: Some(schema_capabilities()),
+        request_arguments: None,
+    }
+}
+
+/// Capabilities that depend on the schema. Counts of resource collection rows and columns
+/// are `Int`s, the scalar type with the `count` aggregate function.
+fn schema_capabilities() -> models::CapabilitySchemaInfo {
+    models::CapabilitySchemaInfo {
+        query: Some(models::QueryCapabilitiesSchemaInfo {
+            aggregates: Some(models::AggregateCapabilitiesSchemaInfo {
+                count_scalar_type: "Int".to_string().into(),
+            }),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NamingConfig;
+    use crate::state::McpClient;
+    use rmcp::model::ToolAnnotations;
+    use serde_json::json;
+
+    #[test]
+    fn test_map_schema_to_ndc_type_primitives() {
+        // Test string type
+        let string_schema = serde_json::from_value(json!({
+            "type": "string"
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&string_schema);
+        match ndc_type {
+            Type::Named { name } => assert_eq!(name.as_str(), "String"),
+            _ => panic!("Expected Named type"),
+        }
+
+        // Test integer type
+        let int_schema = serde_json::from_value(json!({
+            "type": "integer"
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&int_schema);
+        match ndc_type {
+            Type::Named { name } => assert_eq!(name.as_str(), "Int"),
+            _ => panic!("Expected Named type"),
+        }
+
+        // Test number type
+        let number_schema = serde_json::from_value(json!({
+            "type": "number"
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&number_schema);
+        match ndc_type {
+            Type::Named { name } => assert_eq!(name.as_str(), "Float"),
+            _ => panic!("Expected Named type"),
+        }
+
+        // Test boolean type
+        let bool_schema = serde_json::from_value(json!({
+            "type": "boolean"
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&bool_schema);
+        match ndc_type {
+            Type::Named { name } => assert_eq!(name.as_str(), "Boolean"),
+            _ => panic!("Expected Named type"),
+        }
+    }
+
+    #[test]
+    fn test_binary_arguments() {
+        let schema = serde_json::from_value(json!({
+            "type": "string",
+            "contentEncoding": "base64"
+        }))
+        .unwrap();
+        match map_schema_to_ndc_type(&schema) {
+            Type::Named { name } => assert_eq!(name.as_str(), "Bytes"),
+            _ => panic!("Expected Named type"),
+        }
+
+        let tool = Tool::new(
+            "upload",
+            "Upload a file",
+            Arc::new(
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "name": {"type": "string"},
+                        "file": {"type": "string", "contentEncoding": "base64"}
+                    }
+                })
+                .as_object()
+                .unwrap()
+                .clone(),
+            ),
+        );
+        let arguments = |file: &str| {
+            json!({"name": "hello.txt", "file": file})
+                .as_object()
+                .unwrap()
+                .clone()
+        };
+        assert!(check_binary_arguments(&tool, &arguments("aGVsbG8="), Some(5)).is_ok());
+        assert_eq!(
+            check_binary_arguments(&tool, &arguments("aGVsbG8="), Some(4)),
+            Err("Argument file is 5 bytes, more than the limit of 4 bytes".to_string())
+        );
+        assert!(check_binary_arguments(&tool, &arguments("not base64!"), None).is_err());
+    }
+
+    #[test]
+    fn test_check_nested_binary_arguments() {
+        let tool = Tool::new(
+            "send_email",
+            "Send an email",
+            Arc::new(
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "attachments": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "name": {"type": "string"},
+                                    "data": {"type": "string", "format": "byte"}
+                                }
+                            }
+                        }
+                    }
+                })
+                .as_object()
+                .unwrap()
+                .clone(),
+            ),
+        );
+        let arguments = |data: &str| {
+            json!({"attachments": [{"name": "a.txt", "data": "aGVsbG8="}, {"name": "b.txt", "data": data}]})
+                .as_object()
+                .unwrap()
+                .clone()
+        };
+        assert!(check_binary_arguments(&tool, &arguments("aGk="), None).is_ok());
+        assert_eq!(
+            check_binary_arguments(&tool, &arguments("aGVsbG8="), Some(4)),
+            Err(
+                "Argument attachments[0].data is 5 bytes, more than the limit of 4 bytes"
+                    .to_string()
+            )
+        );
+        assert!(check_binary_arguments(&tool, &arguments("not base64!"), None).is_err());
+    }
+
+    #[test]
+    fn test_map_schema_to_ndc_type_formats() {
+        for (format, expected) in [
+            ("date-time", "TimestampTZ"),
+            ("date", "Date"),
+            ("uuid", "UUID"),
+            ("uri", "URI"),
+            ("email", "Email"),
+            ("byte", "Bytes"),
+            ("hostname", "String"),
+        ] {
+            let schema = serde_json::from_value(json!({
+                "type": "string",
+                "format": format
+            }))
+            .unwrap();
+            match map_schema_to_ndc_type(&schema) {
+                Type::Named { name } => assert_eq!(name.as_str(), expected),
+                _ => panic!("Expected Named type"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_schema_to_ndc_type_integer_formats() {
+        for (format, expected) in [
+            ("int64", "BigInt"),
+            ("uint64", "BigInteger"),
+            ("int32", "Int"),
+        ] {
+            let schema = serde_json::from_value(json!({
+                "type": "integer",
+                "format": format
+            }))
+            .unwrap();
+            match map_schema_to_ndc_type(&schema) {
+                Type::Named { name } => assert_eq!(name.as_str(), expected),
+                _ => panic!("Expected Named type"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_schema_to_ndc_type_json_fallback() {
+        // Objects, open-ended maps, bool schemas, untyped schemas and multi-type schemas map
+        // to JSON
+        for schema in [
+            json!({"type": "object", "properties": {"a": {"type": "string"}}}),
+            json!({"type": "object", "additionalProperties": true}),
+            json!({"additionalProperties": {"type": "string"}}),
+            json!(true),
+            json!({"description": "Anything"}),
+            json!({"type": ["string", "integer"]}),
+        ] {
+            let schema = serde_json::from_value(schema).unwrap();
+            match map_schema_to_ndc_type(&schema) {
+                Type::Named { name } => assert_eq!(name.as_str(), "JSON"),
+                _ => panic!("Expected Named type"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_schema_to_ndc_type_arrays() {
+        // Test array of strings
+        let string_array_schema = serde_json::from_value(json!({
+            "type": "array",
+            "items": {
+                "type": "string"
+            }
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&string_array_schema);
+        match ndc_type {
+            Type::Array { element_type } => match element_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "String"),
+                _ => panic!("Expected Named element type"),
+            },
+            _ => panic!("Expected Array type"),
+        }
+
+        // Test array of integers
+        let int_array_schema = serde_json::from_value(json!({
+            "type": "array",
+            "items": {
+                "type": "integer"
+            }
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&int_array_schema);
+        match ndc_type {
+            Type::Array { element_type } => match element_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "Int"),
+                _ => panic!("Expected Named element type"),
+            },
+            _ => panic!("Expected Array type"),
+        }
+
+        // Test array of numbers
+        let number_array_schema = serde_json::from_value(json!({
+            "type": "array",
+            "items": {
+                "type": "number"
+            }
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&number_array_schema);
+        match ndc_type {
+            Type::Array { element_type } => match element_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "Float"),
+                _ => panic!("Expected Named element type"),
+            },
+            _ => panic!("Expected Array type"),
+        }
+
+        // Test array of booleans
+        let bool_array_schema = serde_json::from_value(json!({
+            "type": "array",
+            "items": {
+                "type": "boolean"
+            }
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&bool_array_schema);
+        match ndc_type {
+            Type::Array { element_type } => match element_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "Boolean"),
+                _ => panic!("Expected Named element type"),
+            },
+            _ => panic!("Expected Array type"),
+        }
+
+        // Test array without items schema (should default to JSON array)
+        let generic_array_schema = serde_json::from_value(json!({
+            "type": "array"
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&generic_array_schema);
+        match ndc_type {
+            Type::Array { element_type } => match element_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "JSON"),
+                _ => panic!("Expected Named element type"),
+            },
+            _ => panic!("Expected Array type"),
+        }
+    }
+
+    #[test]
+    fn test_map_schema_to_ndc_type_nested_arrays() {
+        // Test array of arrays of strings
+        let nested_array_schema = serde_json::from_value(json!({
+            "type": "array",
+            "items": {
+                "type": "array",
+                "items": {
+                    "type": "string"
+                }
+            }
+        }))
+        .unwrap();
+        let ndc_type = map_schema_to_ndc_type(&nested_array_schema);
+        match ndc_type {
+            Type::Array { element_type } => match element_type.as_ref() {
+                Type::Array {
+                    element_type: inner_element_type,
+                } => match inner_element_type.as_ref() {
+                    Type::Named { name } => assert_eq!(name.as_str(), "String"),
+                    _ => panic!("Expected Named inner element type"),
+                },
+                _ => panic!("Expected Array element type"),
+            },
+            _ => panic!("Expected Array type"),
+        }
+    }
+
+    #[test]
+    fn test_map_schema_to_ndc_type_nullable_type_arrays() {
+        let nullable_string_schema = serde_json::from_value(json!({
+            "type": ["string", "null"]
+        }))
+        .unwrap();
+        match map_schema_to_ndc_type(&nullable_string_schema) {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "String"),
+                _ => panic!("Expected Named underlying type"),
+            },
+            _ => panic!("Expected Nullable type"),
+        }
+
+        let nullable_array_schema = serde_json::from_value(json!({
+            "type": ["null", "array"],
+            "items": {"type": "integer"}
+        }))
+        .unwrap();
+        match map_schema_to_ndc_type(&nullable_array_schema) {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Array { element_type } => match element_type.as_ref() {
+                    Type::Named { name } => assert_eq!(name.as_str(), "Int"),
+                    _ => panic!("Expected Named element type"),
+                },
+                _ => panic!("Expected Array underlying type"),
+            },
+            _ => panic!("Expected Nullable type"),
+        }
+
+        // Required arguments can still be nullable
+        let input_schema = json!({
+            "type": "object",
+            "properties": {
+                "cursor": {"type": ["string", "null"]}
+            },
+            "required": ["cursor"]
+        });
+        let arguments = tool_arguments_schema(
+            "test_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+        let cursor_arg = arguments.get(&ArgumentName::new("cursor".into())).unwrap();
+        match &cursor_arg.argument_type {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "String"),
+                _ => panic!("Expected Named underlying type for cursor"),
+            },
+            _ => panic!("Expected Nullable type for cursor"),
+        }
+    }
+
+    #[test]
+    fn test_map_schema_to_ndc_type_unions() {
+        // Numeric variants widen to Float
+        let numeric_union_schema = serde_json::from_value(json!({
+            "oneOf": [{"type": "integer"}, {"type": "number"}]
+        }))
+        .unwrap();
+        match map_schema_to_ndc_type(&numeric_union_schema) {
+            Type::Named { name } => assert_eq!(name.as_str(), "Float"),
+            _ => panic!("Expected Named type"),
+        }
+
+        // A null variant makes the union nullable
+        let nullable_union_schema = serde_json::from_value(json!({
+            "anyOf": [{"type": "boolean"}, {"type": "null"}]
+        }))
+        .unwrap();
+        match map_schema_to_ndc_type(&nullable_union_schema) {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "Boolean"),
+                _ => panic!("Expected Named underlying type"),
+            },
+            _ => panic!("Expected Nullable type"),
+        }
+
+        // Incompatible variants fall back to JSON
+        let mixed_union_schema = serde_json::from_value(json!({
+            "oneOf": [{"type": "integer"}, {"type": "array", "items": {"type": "string"}}]
+        }))
+        .unwrap();
+        match map_schema_to_ndc_type(&mixed_union_schema) {
+            Type::Named { name } => assert_eq!(name.as_str(), "JSON"),
+            _ => panic!("Expected Named type"),
+        }
+    }
+
+    #[test]
+    fn test_map_schema_to_ndc_type_nested_unions() {
+        // Array items that are themselves unions, inside a union with a null variant
+        let nested_union_schema = serde_json::from_value(json!({
+            "anyOf": [
+                {
+                    "type": "array",
+                    "items": {"oneOf": [{"type": "integer"}, {"type": "number"}]}
+                },
+                {
+                    "anyOf": [
+                        {"type": "array", "items": {"type": "integer"}},
+                        {"type": "null"}
+                    ]
+                }
+            ]
+        }))
+        .unwrap();
+        match map_schema_to_ndc_type(&nested_union_schema) {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Array { element_type } => match element_type.as_ref() {
+                    Type::Named { name } => assert_eq!(name.as_str(), "Float"),
+                    _ => panic!("Expected Named element type"),
+                },
+                _ => panic!("Expected Array underlying type"),
+            },
+            _ => panic!("Expected Nullable type"),
+        }
+
+        // Optional union arguments are not wrapped in Nullable twice
+        let input_schema = json!({
+            "type": "object",
+            "properties": {
+                "limit": {"anyOf": [{"type": "integer"}, {"type": "null"}]}
+            }
+        });
+        let arguments = tool_arguments_schema(
+            "test_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+        let limit_arg = arguments.get(&ArgumentName::new("limit".into())).unwrap();
+        match &limit_arg.argument_type {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "Int"),
+                _ => panic!("Expected Named underlying type for limit"),
+            },
+            _ => panic!("Expected Nullable type for limit"),
+        }
+    }
+
+    #[test]
+    fn test_tool_arguments_schema_with_examples() {
+        let input_schema = json!({
+            "type": "object",
+            "properties": {
+                "city": {
+                    "type": "string",
+                    "description": "Name of the city.",
+                    "examples": ["London", "Paris"]
+                },
+                "days": {
+                    "type": "integer",
+                    "examples": [3]
+                },
+                "units": {
+                    "type": "string",
+                    "description": "Units to use."
+                },
+                "country": {
+                    "type": "string",
+                    "example": "GB"
+                }
+            }
+        });
+        let arguments = tool_arguments_schema(
+            "test_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+
+        let city_arg = arguments.get(&ArgumentName::new("city".into())).unwrap();
+        assert_eq!(
+            city_arg.description.as_deref(),
+            Some(r#"Name of the city. Examples: "London", "Paris""#)
+        );
+        let days_arg = arguments.get(&ArgumentName::new("days".into())).unwrap();
+        assert_eq!(days_arg.description.as_deref(), Some("Examples: 3"));
+        let units_arg = arguments.get(&ArgumentName::new("units".into())).unwrap();
+        assert_eq!(units_arg.description.as_deref(), Some("Units to use."));
+        let country_arg = arguments.get(&ArgumentName::new("country".into())).unwrap();
+        assert_eq!(
+            country_arg.description.as_deref(),
+            Some(r#"Examples: "GB""#)
+        );
+    }
+
+    #[test]
+    fn test_tool_arguments_schema_with_defaults() {
+        let input_schema = json!({
+            "type": "object",
+            "properties": {
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of results.",
+                    "default": 10
+                },
+                "order": {
+                    "type": "string",
+                    "default": "asc",
+                    "examples": ["desc"]
+                }
+            }
+        });
+        let arguments = tool_arguments_schema(
+            "test_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+
+        let limit_arg = arguments.get(&ArgumentName::new("limit".into())).unwrap();
+        assert_eq!(
+            limit_arg.description.as_deref(),
+            Some("Maximum number of results. Defaults to 10.")
+        );
+        let order_arg = arguments.get(&ArgumentName::new("order".into())).unwrap();
+        assert_eq!(
+            order_arg.description.as_deref(),
+            Some(r#"Defaults to "asc". Examples: "desc""#)
+        );
+    }
+
+    #[test]
+    fn test_tool_arguments_schema_with_const() {
+        let input_schema = json!({
+            "type": "object",
+            "properties": {
+                "api_version": {"type": "string", "const": "2024-01"},
+                "page_size": {"type": "integer", "const": 100}
+            },
+            "required": ["api_version", "page_size"]
+        });
+        let mut scalar_types = BTreeMap::new();
+        let arguments = tool_arguments_schema(
+            "list_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut scalar_types,
+        );
+
+        // Constant strings are single-value enums, optional as they are filled in
+        let api_version_arg = arguments
+            .get(&ArgumentName::new("api_version".into()))
+            .unwrap();
+        match &api_version_arg.argument_type {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "list_Input_api_version"),
+                _ => panic!("Expected Named underlying type for api_version"),
+            },
+            _ => panic!("Expected Nullable type for api_version"),
+        }
+        match &scalar_types[&models::ScalarTypeName::from("list_Input_api_version".to_string())]
+            .representation
+        {
+            models::TypeRepresentation::Enum { one_of } => assert_eq!(one_of, &["2024-01"]),
+            _ => panic!("Expected Enum representation"),
+        }
+
+        // Other constants keep their type
+        let page_size_arg = arguments
+            .get(&ArgumentName::new("page_size".into()))
+            .unwrap();
+        match &page_size_arg.argument_type {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "Int"),
+                _ => panic!("Expected Named underlying type for page_size"),
+            },
+            _ => panic!("Expected Nullable type for page_size"),
+        }
+        assert_eq!(scalar_types.len(), 1);
+    }
+
+    #[test]
+    fn test_argument_constraints() {
+        let input_schema = json!({
+            "type": "object",
+            "properties": {
+                "limit": {"type": "integer", "description": "Page size", "minimum": 1, "maximum": 100},
+                "code": {"type": "string", "pattern": "^[A-Z]{3}$", "maxLength": 3},
+                "tags": {"type": "array", "items": {"type": "string"}, "uniqueItems": true}
+            }
+        });
+        let arguments = tool_arguments_schema(
+            "test_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+        let limit_arg = arguments.get(&ArgumentName::new("limit".into())).unwrap();
+        assert_eq!(
+            limit_arg.description.as_deref(),
+            Some("Page size Constraints: minimum 1, maximum 100.")
+        );
+
+        let tool = Tool::new(
+            "search",
+            "Search",
+            Arc::new(input_schema.as_object().unwrap().clone()),
+        );
+        let check = |arguments: serde_json::Value| {
+            check_argument_constraints(&tool, arguments.as_object().unwrap())
+        };
+        assert!(check(json!({"limit": 10, "code": "ABC", "tags": ["a", "b"]})).is_ok());
+        assert_eq!(
+            check(json!({"limit": 0})),
+            Err("Argument limit must be at least 1".to_string())
+        );
+        assert_eq!(
+            check(json!({"code": "abc"})),
+            Err("Argument code must match pattern ^[A-Z]{3}$".to_string())
+        );
+        assert_eq!(
+            check(json!({"tags": ["a", "a"]})),
+            Err("Argument tags must have unique items".to_string())
+        );
+    }
+
+    #[test]
+    fn test_map_prompts_to_functions() {
+        let prompt: Prompt = serde_json::from_value(json!({
+            "name": "summarize",
+            "description": "Summarize a document",
+            "arguments": [
+                {"name": "document", "required": true},
+                {"name": "style", "description": "Summary style"}
+            ]
+        }))
+        .unwrap();
+        let server_name = McpServerName("docs".to_string());
+        let mut state = ConnectorState::new();
+        state.add_client(
+            server_name.clone(),
+            McpClient {
+                service: None,
+                resources: HashMap::new(),
+                resource_templates: HashMap::new(),
+                tools: HashMap::new(),
+                prompts: HashMap::from([("summarize".to_string(), prompt)]),
+            },
+        );
+        state.assign_ndc_names(&NamingConfig::default(), &HashMap::new());
+
+        let functions =
+            map_prompts_to_functions(&server_name, &state.clients[&server_name].prompts, &state);
+        assert_eq!(functions.len(), 1);
+        let function = &functions[0];
+        assert_eq!(function.name.to_string(), "docs__summarize");
+        match &function.result_type {
+            Type::Array { element_type } => match element_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.to_string(), "PromptMessage"),
+                _ => panic!("Expected PromptMessage elements"),
+            },
+            _ => panic!("Expected Array type"),
+        }
+
+        let document_arg = function
+            .arguments
+            .get(&ArgumentName::new("document".into()))
+            .unwrap();
+        assert!(matches!(document_arg.argument_type, Type::Named { .. }));
+        let style_arg = function
+            .arguments
+            .get(&ArgumentName::new("style".into()))
+            .unwrap();
+        assert!(matches!(style_arg.argument_type, Type::Nullable { .. }));
+        assert_eq!(style_arg.description.as_deref(), Some("Summary style"));
+    }
+
+    #[test]
+    fn test_schema_capabilities() {
+        let configuration: ConnectorConfig =
+            serde_json::from_value(json!({"servers": {}})).unwrap();
+        let schema = generate_schema_from_state(&ConnectorState::new(), &configuration);
+        let capabilities = serde_json::to_value(&schema.capabilities).unwrap();
+        assert_eq!(
+            capabilities,
+            json!({"query": {"aggregates": {"count_scalar_type": "Int"}}})
+        );
+        assert!(schema
+            .scalar_types
+            .contains_key(&models::ScalarTypeName::from("Int".to_string())));
+    }
+
+    #[test]
+    fn test_expose_tools_as_both() {
+        let input_schema = Arc::new(json!({"type": "object"}).as_object().unwrap().clone());
+        let server_name = McpServerName("github".to_string());
+        let mut state = ConnectorState::new();
+        state.add_client(
+            server_name.clone(),
+            McpClient {
+                service: None,
+                resources: HashMap::new(),
+                resource_templates: HashMap::new(),
+                tools: ["get_issue", "create_issue"]
+                    .into_iter()
+                    .map(|name| (name.to_string(), Tool::new(name, "", input_schema.clone())))
+                    .collect(),
+                prompts: HashMap::new(),
+            },
+        );
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {"github": {
+                "type": "http",
+                "url": "http://localhost:8080/mcp",
+                "exposeToolsAs": "both"
+            }}
+        }))
+        .unwrap();
+        state.assign_ndc_names(&configuration.naming, &configuration.servers);
+
+        let schema = generate_schema_from_state(&state, &configuration);
+        let mut functions = schema
+            .functions
+            .iter()
+            .map(|function| function.name.to_string())
+            .filter(|name| name.starts_with("github__"))
+            .collect::<Vec<_>>();
+        functions.sort();
+        let mut procedures = schema
+            .procedures
+            .iter()
+            .map(|procedure| procedure.name.to_string())
+            .collect::<Vec<_>>();
+        procedures.sort();
+        assert_eq!(
+            functions,
+            [
+                "github__create_issue_function",
+                "github__get_issue_function"
+            ]
+        );
+        assert_eq!(
+            procedures,
+            [
+                "github__create_issue_procedure",
+                "github__get_issue_procedure"
+            ]
+        );
+        let (_, _, kind, tool) = state.resolve_name("github__get_issue_procedure").unwrap();
+        assert_eq!((kind, tool), (McpItemKind::ToolProcedure, "get_issue"));
+    }
+
+    #[test]
+    fn test_best_effort_result_types() {
+        let input_schema = Arc::new(json!({"type": "object"}).as_object().unwrap().clone());
+        let server_name = McpServerName("github".to_string());
+        let mut state = ConnectorState::new();
+        state.add_client(
+            server_name.clone(),
+            McpClient {
+                service: None,
+                resources: HashMap::new(),
+                resource_templates: HashMap::new(),
+                tools: ["get_issue", "create_issue"]
+                    .into_iter()
+                    .map(|name| (name.to_string(), Tool::new(name, "", input_schema.clone())))
+                    .collect(),
+                prompts: HashMap::new(),
+            },
+        );
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {"github": {
+                "type": "http",
+                "url": "http://localhost:8080/mcp",
+                "exposeToolsAs": "both"
+            }},
+            "best_effort_mutations": true
+        }))
+        .unwrap();
+        state.assign_ndc_names(&configuration.naming, &configuration.servers);
+
+        let schema = generate_schema_from_state(&state, &configuration);
+        for procedure in &schema.procedures {
+            assert_eq!(
+                procedure.result_type,
+                create_named_type("ToolOutput_BestEffort")
+            );
+        }
+        let object_type = |name: &str| {
+            schema
+                .object_types
+                .get(&models::ObjectTypeName::from(name.to_string()))
+                .unwrap()
+        };
+        let field_name = |name: &str| -> models::FieldName { name.into() };
+        let wrapper = object_type("ToolOutput_BestEffort");
+        assert_eq!(
+            wrapper.fields[&field_name("result")].r#type,
+            Type::Nullable {
+                underlying_type: Box::new(create_named_type("ToolOutput"))
+            }
+        );
+        assert!(wrapper.fields.contains_key(&field_name("error")));
+
+        // Functions share the tool output type, which has no error field
+        assert!(!object_type("ToolOutput")
+            .fields
+            .contains_key(&field_name("error")));
+        assert!(schema
+            .functions
+            .iter()
+            .filter(|function| function.name.as_str().starts_with("github__"))
+            .all(|function| function.result_type == create_named_type("ToolOutput")));
+    }
+
+    #[test]
+    fn test_rows_tool_collection() {
+        let input_schema = Arc::new(
+            json!({"type": "object", "properties": {"query": {"type": "string"}}})
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+        let mut tool = Tool::new("search_repositories", "", input_schema);
+        tool.output_schema = Some(Arc::new(
+            json!({
+                "type": "object",
+                "properties": {
+                    "total": {"type": "integer"},
+                    "items": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {"name": {"type": "string"}, "stars": {"type": "integer"}},
+                            "required": ["name"]
+                        }
+                    }
+                }
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ));
+        let server_name = McpServerName("github".to_string());
+        let mut state = ConnectorState::new();
+        state.add_client(
+            server_name.clone(),
+            McpClient {
+                service: None,
+                resources: HashMap::new(),
+                resource_templates: HashMap::new(),
+                tools: HashMap::from([("search_repositories".to_string(), tool)]),
+                prompts: HashMap::new(),
+            },
+        );
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {},
+            "tools": {"github": {"search_repositories": {"resultMode": "rows"}}}
+        }))
+        .unwrap();
+        state.assign_ndc_names(&configuration.naming, &configuration.servers);
+
+        let schema = generate_schema_from_state(&state, &configuration);
+        assert!(schema
+            .functions
+            .iter()
+            .all(|function| function.name.as_str() != "github__search_repositories"));
+        let collection = schema
+            .collections
+            .iter()
+            .find(|collection| collection.name.as_str() == "github__search_repositories")
+            .unwrap();
+        assert_eq!(
+            collection.collection_type.as_str(),
+            "github__search_repositories_Row"
+        );
+        assert!(collection
+            .arguments
+            .contains_key(&ArgumentName::new("query".into())));
+        let row_type = &schema.object_types
+            [&models::ObjectTypeName::new("github__search_repositories_Row".into())];
+        assert_eq!(
+            row_type
+                .fields
+                .keys()
+                .map(|f| f.as_str())
+                .collect::<Vec<_>>(),
+            ["name", "stars"]
+        );
+
+        // Without row expansion the tool is a plain function
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {},
+            "tools": {"github": {"search_repositories": {"resultMode": "rows"}}},
+            "features": {"row_expansion": false}
+        }))
+        .unwrap();
+        let schema = generate_schema_from_state(&state, &configuration);
+        assert!(schema
+            .functions
+            .iter()
+            .any(|function| function.name.as_str() == "github__search_repositories"));
+        assert!(schema
+            .collections
+            .iter()
+            .all(|collection| collection.name.as_str() != "github__search_repositories"));
+    }
+
+    #[test]
+    fn test_read_only_mode() {
+        let input_schema = Arc::new(json!({"type": "object"}).as_object().unwrap().clone());
+        let server_name = McpServerName("github".to_string());
+        let mut state = ConnectorState::new();
+        state.add_client(
+            server_name.clone(),
+            McpClient {
+                service: None,
+                resources: HashMap::new(),
+                resource_templates: HashMap::new(),
+                tools: ["get_issue", "create_issue"]
+                    .into_iter()
+                    .map(|name| (name.to_string(), Tool::new(name, "", input_schema.clone())))
+                    .collect(),
+                prompts: HashMap::new(),
+            },
+        );
+        state.assign_ndc_names(&NamingConfig::default(), &HashMap::new());
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {},
+            "read_only": true,
+            "tools": {"github": {
+                "get_issue": {"read_only": true},
+                "create_issue": {"read_only": false}
+            }}
+        }))
+        .unwrap();
+
+        let schema = generate_schema_from_state(&state, &configuration);
+        assert!(schema.procedures.is_empty());
+        assert!(schema
+            .functions
+            .iter()
+            .any(|function| function.name.as_str() == "github__get_issue"));
+    }
+
+    #[test]
+    fn test_scalar_comparison_operators() {
+        let scalar_types = create_scalar_types();
+        let operators = |name: &str| {
+            scalar_types[&models::ScalarTypeName::from(name.to_string())]
+                .comparison_operators
+                .keys()
+                .map(|operator| operator.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            operators("Int"),
+            vec!["_eq", "_gt", "_gte", "_in", "_lt", "_lte", "_neq"]
+        );
+        assert_eq!(operators("Boolean"), vec!["_eq", "_in", "_neq"]);
+        assert!(operators("JSON").is_empty());
+
+        let aggregate_functions = |name: &str| {
+            scalar_types[&models::ScalarTypeName::from(name.to_string())]
+                .aggregate_functions
+                .keys()
+                .map(|function| function.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(aggregate_functions("String"), vec!["count", "max", "min"]);
+        assert_eq!(aggregate_functions("Boolean"), vec!["count"]);
+    }
+
+    #[test]
+    fn test_resource_description_with_annotations() {
+        let annotations: Annotations =
+            serde_json::from_value(json!({"audience": ["user", "assistant"], "priority": 0.5}))
+                .unwrap();
+        assert_eq!(
+            resource_description(Some("Project readme"), Some(&annotations)).as_deref(),
+            Some("Project readme Audience: user, assistant. Priority: 0.5.")
+        );
+        assert_eq!(resource_description(None, None), None);
+    }
+
+    #[test]
+    fn test_resource_collections_are_unique_by_uri() {
+        let resource: Resource = serde_json::from_value(json!({
+            "uri": "file:///readme.md",
+            "name": "readme"
+        }))
+        .unwrap();
+        let server_name = McpServerName("files".to_string());
+        let mut state = ConnectorState::new();
+        state.add_client(
+            server_name.clone(),
+            McpClient {
+                service: None,
+                resources: HashMap::from([("readme".to_string(), resource)]),
+                resource_templates: HashMap::new(),
+                tools: HashMap::new(),
+                prompts: HashMap::new(),
+            },
+        );
+        state.assign_ndc_names(&NamingConfig::default(), &HashMap::new());
+
+        let configuration: ConnectorConfig =
+            serde_json::from_value(json!({"servers": {}})).unwrap();
+        let collections = map_resources_to_collections(
+            &server_name,
+            &state.clients[&server_name].resources,
+            &state,
+            &configuration,
+            &mut BTreeMap::new(),
+        );
+        assert_eq!(collections.len(), 1);
+        let constraint = &collections[0].uniqueness_constraints["ResourceByUri"];
+        assert_eq!(
+            constraint.unique_columns,
+            vec![models::FieldName::new("uri".into())]
+        );
+    }
+
+    #[test]
+    fn test_is_read_only_tool_classification() {
+        let server_name = McpServerName("db".to_string());
+        let input_schema = Arc::new(json!({"type": "object"}).as_object().unwrap().clone());
+        let get_lock = Tool::new("get_lock", "Acquire a lock", input_schema.clone());
+        let mut execute_query = Tool::new("execute_query", "Run a query", input_schema);
+        execute_query.annotations = Some(ToolAnnotations::new().read_only(true));
+
+        let configuration: ConnectorConfig =
+            serde_json::from_value(json!({"servers": {}})).unwrap();
+        assert!(is_read_only_tool(&server_name, &get_lock, &configuration));
+        assert!(is_read_only_tool(
+            &server_name,
+            &execute_query,
+            &configuration
+        ));
+
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {},
+            "trust_read_only_hint": true
+        }))
+        .unwrap();
+        assert!(!is_read_only_tool(&server_name, &get_lock, &configuration));
+        assert!(is_read_only_tool(
+            &server_name,
+            &execute_query,
+            &configuration
+        ));
 
-        // Map tools to functions and procedures
-        functions.extend(map_tools_to_functions(server_name, &client.tools));
-        procedures.extend(map_tools_to_procedures(server_name, &client.tools));
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {},
+            "tools": {
+                "db": {
+                    "get_lock": {"readOnly": false},
+                    "execute_query": {"read_only": false}
+                }
+            }
+        }))
+        .unwrap();
+        assert!(!is_read_only_tool(&server_name, &get_lock, &configuration));
+        assert!(!is_read_only_tool(
+            &server_name,
+            &execute_query,
+            &configuration
+        ));
     }
 
-    // Create object types
-    let object_types = create_object_types();
+    #[test]
+    fn test_procedure_description_with_hints() {
+        let input_schema = Arc::new(json!({"type": "object"}).as_object().unwrap().clone());
+        let mut tool = Tool::new("delete_file", "Delete a file.", input_schema);
+        assert_eq!(
+            procedure_description(&tool).as_deref(),
+            Some("Delete a file.")
+        );
+        // Tools without annotations may be destructive
+        assert!(is_destructive_tool(&tool));
 
-    // Create scalar types
-    let scalar_types = create_scalar_types();
+        tool.annotations = Some(ToolAnnotations::new().read_only(true));
+        assert!(!is_destructive_tool(&tool));
+        tool.annotations = Some(ToolAnnotations::new().destructive(false));
+        assert!(!is_destructive_tool(&tool));
 
-    // Convert object types to use ObjectTypeName keys
-    let mut typed_object_types = BTreeMap::new();
-    for (name, obj_type) in object_types {
-        typed_object_types.insert(name.into(), obj_type);
+        tool.annotations = Some(ToolAnnotations::new().destructive(true).idempotent(true));
+        assert_eq!(
+            procedure_description(&tool).as_deref(),
+            Some(
+                "Delete a file. Destructive: may delete or overwrite data. Idempotent: repeated \
+                 calls with the same arguments have no additional effect."
+            )
+        );
+        assert!(is_destructive_tool(&tool));
     }
 
-    // Create schema response
-    models::SchemaResponse {
-        collections,
-        functions,
-        procedures,
-        object_types: typed_object_types,
-        scalar_types,
-        capabilities: None,
-        request_arguments: None,
-    }
-}
+    #[test]
+    fn test_tool_description_with_title() {
+        let input_schema = Arc::new(json!({"type": "object"}).as_object().unwrap().clone());
+        let mut tool = Tool::new(
+            "list_repos",
+            "List the repositories of a user.",
+            input_schema,
+        );
+        tool.annotations = Some(ToolAnnotations::with_title("List Repositories"));
+        assert_eq!(
+            tool_description(&tool).as_deref(),
+            Some("List Repositories. List the repositories of a user.")
+        );
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
+        // The tool's own title takes precedence, and titles repeating the name are ignored
+        tool.title = Some("list_repos".to_string());
+        assert_eq!(
+            tool_description(&tool).as_deref(),
+            Some("List the repositories of a user.")
+        );
+    }
 
     #[test]
-    fn test_map_schema_to_ndc_type_primitives() {
-        // Test string type
-        let string_schema = serde_json::from_value(json!({
-            "type": "string"
+    fn test_description_with_routing_hints() {
+        let input_schema = Arc::new(json!({"type": "object"}).as_object().unwrap().clone());
+        let tool = Tool::new("send_email", "Send an email.", input_schema);
+        let server_name = McpServerName("mail".to_string());
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {"mail": {"type": "http", "url": "http://localhost:8080/mcp"}},
+            "tools": {"mail": {"send_email": {
+                "routing_hints": {"cost": "low", "latency": "high", "safety": "review"}
+            }}}
         }))
         .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&string_schema);
-        match ndc_type {
-            Type::Named { name } => assert_eq!(name.as_str(), "String"),
-            _ => panic!("Expected Named type"),
-        }
+        assert_eq!(
+            with_routing_hints(tool_description(&tool), &server_name, &tool, &configuration)
+                .as_deref(),
+            Some(
+                "Send an email. Routing hints: \
+                 {\"cost\":\"low\",\"latency\":\"high\",\"safety\":\"review\"}"
+            )
+        );
 
-        // Test integer type
-        let int_schema = serde_json::from_value(json!({
-            "type": "integer"
-        }))
-        .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&int_schema);
-        match ndc_type {
-            Type::Named { name } => assert_eq!(name.as_str(), "Int"),
-            _ => panic!("Expected Named type"),
-        }
+        let other_server = McpServerName("other".to_string());
+        assert_eq!(
+            with_routing_hints(
+                tool_description(&tool),
+                &other_server,
+                &tool,
+                &configuration
+            )
+            .as_deref(),
+            Some("Send an email.")
+        );
+    }
 
-        // Test number type
-        let number_schema = serde_json::from_value(json!({
-            "type": "number"
-        }))
-        .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&number_schema);
-        match ndc_type {
-            Type::Named { name } => assert_eq!(name.as_str(), "Float"),
-            _ => panic!("Expected Named type"),
-        }
+    #[test]
+    fn test_sanitize_names() {
+        assert_eq!(sanitize_name("get_weather"), "get_weather");
+        assert_eq!(sanitize_name("repo.search-issues"), "repo_search_issues");
+        assert_eq!(sanitize_name("files/read__all"), "files_read_all");
+        assert_eq!(sanitize_name("server-"), "server");
+        assert_eq!(sanitize_name("2fa"), "_2fa");
 
-        // Test boolean type
-        let bool_schema = serde_json::from_value(json!({
-            "type": "boolean"
-        }))
-        .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&bool_schema);
-        match ndc_type {
-            Type::Named { name } => assert_eq!(name.as_str(), "Boolean"),
-            _ => panic!("Expected Named type"),
-        }
+        let names = sanitized_names(["a_b", "a-b", "a.b"].into_iter());
+        assert_eq!(names["a_b"], "a_b");
+        assert_eq!(names["a-b"], "a_b_2");
+        assert_eq!(names["a.b"], "a_b_3");
     }
 
     #[test]
-    fn test_map_schema_to_ndc_type_arrays() {
-        // Test array of strings
-        let string_array_schema = serde_json::from_value(json!({
-            "type": "array",
-            "items": {
-                "type": "string"
-            }
-        }))
-        .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&string_array_schema);
-        match ndc_type {
+    fn test_deprecated_tools_and_arguments() {
+        let mut tool = Tool::new(
+            "list_issues",
+            "List issues",
+            Arc::new(
+                json!({
+                    "type": "object",
+                    "deprecated": true,
+                    "properties": {
+                        "repo": {"type": "string", "deprecated": true},
+                        "state": {"type": "string", "description": "Issue state.", "deprecated": true},
+                        "labels": {"type": "array", "items": {"type": "string"}}
+                    },
+                    "required": ["repo"]
+                })
+                .as_object()
+                .unwrap()
+                .clone(),
+            ),
+        );
+
+        // Deprecated items are marked in descriptions
+        assert!(is_deprecated_tool(&tool));
+        assert_eq!(
+            tool_description(&tool).as_deref(),
+            Some("DEPRECATED. List issues")
+        );
+        let arguments = tool_arguments_schema(
+            "test_Input",
+            &tool.input_schema,
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+        let state_arg = arguments.get(&ArgumentName::new("state".into())).unwrap();
+        assert_eq!(
+            state_arg.description.as_deref(),
+            Some("DEPRECATED. Issue state.")
+        );
+
+        // Only optional deprecated arguments are removed
+        remove_deprecated_arguments(&mut tool);
+        let arguments = tool_arguments_schema(
+            "test_Input",
+            &tool.input_schema,
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+        assert!(arguments.contains_key(&ArgumentName::new("repo".into())));
+        assert!(!arguments.contains_key(&ArgumentName::new("state".into())));
+        assert!(arguments.contains_key(&ArgumentName::new("labels".into())));
+    }
+
+    #[test]
+    fn test_tool_arguments_schema_with_refs() {
+        let input_schema = json!({
+            "type": "object",
+            "$defs": {
+                "Tag": {"type": "string", "description": "A tag."},
+                "Ids": {"type": "array", "items": {"type": "integer"}},
+                "Node": {
+                    "type": "array",
+                    "items": {"$ref": "#/$defs/Node"}
+                }
+            },
+            "definitions": {
+                "Score": {"type": "number"}
+            },
+            "properties": {
+                "tags": {"type": "array", "items": {"$ref": "#/$defs/Tag"}},
+                "ids": {"$ref": "#/$defs/Ids", "description": "Issue IDs."},
+                "score": {"$ref": "#/definitions/Score"},
+                "tree": {"$ref": "#/$defs/Node"},
+                "missing": {"$ref": "#/$defs/Missing"}
+            },
+            "required": ["tags", "ids", "score", "tree", "missing"]
+        });
+        let arguments = tool_arguments_schema(
+            "test_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+
+        let tags_arg = arguments.get(&ArgumentName::new("tags".into())).unwrap();
+        match &tags_arg.argument_type {
             Type::Array { element_type } => match element_type.as_ref() {
                 Type::Named { name } => assert_eq!(name.as_str(), "String"),
-                _ => panic!("Expected Named element type"),
+                _ => panic!("Expected String element type for tags"),
             },
-            _ => panic!("Expected Array type"),
+            _ => panic!("Expected Array type for tags"),
         }
 
-        // Test array of integers
-        let int_array_schema = serde_json::from_value(json!({
-            "type": "array",
-            "items": {
-                "type": "integer"
-            }
-        }))
-        .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&int_array_schema);
-        match ndc_type {
+        // Sibling keywords override the referenced schema
+        let ids_arg = arguments.get(&ArgumentName::new("ids".into())).unwrap();
+        assert_eq!(ids_arg.description.as_deref(), Some("Issue IDs."));
+        match &ids_arg.argument_type {
             Type::Array { element_type } => match element_type.as_ref() {
                 Type::Named { name } => assert_eq!(name.as_str(), "Int"),
-                _ => panic!("Expected Named element type"),
+                _ => panic!("Expected Int element type for ids"),
             },
-            _ => panic!("Expected Array type"),
+            _ => panic!("Expected Array type for ids"),
         }
 
-        // Test array of numbers
-        let number_array_schema = serde_json::from_value(json!({
-            "type": "array",
-            "items": {
-                "type": "number"
-            }
-        }))
-        .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&number_array_schema);
-        match ndc_type {
-            Type::Array { element_type } => match element_type.as_ref() {
-                Type::Named { name } => assert_eq!(name.as_str(), "Float"),
-                _ => panic!("Expected Named element type"),
-            },
-            _ => panic!("Expected Array type"),
+        let score_arg = arguments.get(&ArgumentName::new("score".into())).unwrap();
+        match &score_arg.argument_type {
+            Type::Named { name } => assert_eq!(name.as_str(), "Float"),
+            _ => panic!("Expected Named type for score"),
         }
 
-        // Test array of booleans
-        let bool_array_schema = serde_json::from_value(json!({
-            "type": "array",
-            "items": {
-                "type": "boolean"
-            }
-        }))
-        .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&bool_array_schema);
-        match ndc_type {
+        // Recursive references are cut off, leaving an untyped (JSON) element
+        let tree_arg = arguments.get(&ArgumentName::new("tree".into())).unwrap();
+        match &tree_arg.argument_type {
             Type::Array { element_type } => match element_type.as_ref() {
-                Type::Named { name } => assert_eq!(name.as_str(), "Boolean"),
-                _ => panic!("Expected Named element type"),
+                Type::Named { name } => assert_eq!(name.as_str(), "JSON"),
+                _ => panic!("Expected JSON element type for tree"),
             },
-            _ => panic!("Expected Array type"),
+            _ => panic!("Expected Array type for tree"),
         }
 
-        // Test array without items schema (should default to String array)
-        let generic_array_schema = serde_json::from_value(json!({
-            "type": "array"
-        }))
-        .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&generic_array_schema);
-        match ndc_type {
-            Type::Array { element_type } => match element_type.as_ref() {
-                Type::Named { name } => assert_eq!(name.as_str(), "String"),
-                _ => panic!("Expected Named element type"),
+        // Unresolvable references fall back to JSON
+        let missing_arg = arguments.get(&ArgumentName::new("missing".into())).unwrap();
+        match &missing_arg.argument_type {
+            Type::Named { name } => assert_eq!(name.as_str(), "JSON"),
+            _ => panic!("Expected Named type for missing"),
+        }
+    }
+
+    #[test]
+    fn test_tool_arguments_schema_with_multi_type_primitives() {
+        // Arguments accepting several primitives are JSON, so numbers reach the server as
+        // numbers rather than being collapsed to strings
+        let input_schema = json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": ["string", "number"]},
+                "limit": {"type": ["integer", "string", "null"]}
             },
-            _ => panic!("Expected Array type"),
+            "required": ["id"]
+        });
+        let arguments = tool_arguments_schema(
+            "lookup_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+        match &arguments[&ArgumentName::new("id".into())].argument_type {
+            Type::Named { name } => assert_eq!(name.as_str(), "JSON"),
+            _ => panic!("Expected Named type"),
+        }
+        match &arguments[&ArgumentName::new("limit".into())].argument_type {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "JSON"),
+                _ => panic!("Expected Named underlying type"),
+            },
+            _ => panic!("Expected Nullable type"),
         }
     }
 
     #[test]
-    fn test_map_schema_to_ndc_type_nested_arrays() {
-        // Test array of arrays of strings
-        let nested_array_schema = serde_json::from_value(json!({
-            "type": "array",
-            "items": {
-                "type": "array",
-                "items": {
-                    "type": "string"
+    fn test_tool_arguments_schema_with_nested_objects() {
+        let input_schema = json!({
+            "type": "object",
+            "properties": {
+                "filter": {
+                    "type": "object",
+                    "description": "Issue filter.",
+                    "properties": {
+                        "state": {"type": "string"},
+                        "author": {
+                            "type": "object",
+                            "properties": {
+                                "login": {"type": "string"},
+                                "team": {
+                                    "type": "object",
+                                    "properties": {
+                                        "slug": {"type": "string"},
+                                        "org": {"type": "string"}
+                                    },
+                                    "required": ["slug"]
+                                }
+                            },
+                            "required": ["login"]
+                        }
+                    },
+                    "required": ["author"]
                 }
             }
-        }))
-        .unwrap();
-        let ndc_type = map_schema_to_ndc_type(&nested_array_schema);
-        match ndc_type {
-            Type::Array { element_type } => match element_type.as_ref() {
-                Type::Array {
-                    element_type: inner_element_type,
-                } => match inner_element_type.as_ref() {
-                    Type::Named { name } => assert_eq!(name.as_str(), "String"),
-                    _ => panic!("Expected Named inner element type"),
+        });
+        let mut object_types = BTreeMap::new();
+        let arguments = tool_arguments_schema(
+            "search_Input",
+            input_schema.as_object().unwrap(),
+            &mut object_types,
+            &mut BTreeMap::new(),
+        );
+
+        // The argument itself is optional
+        let filter_arg = arguments.get(&ArgumentName::new("filter".into())).unwrap();
+        match &filter_arg.argument_type {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => assert_eq!(name.as_str(), "search_Input_filter"),
+                _ => panic!("Expected Named underlying type for filter"),
+            },
+            _ => panic!("Expected Nullable type for filter"),
+        }
+        assert_eq!(
+            object_types["search_Input_filter"].description.as_deref(),
+            Some("Issue filter.")
+        );
+
+        // Each level applies its own required list
+        let field_type = |type_name: &str, field_name: &str| {
+            let field_name: models::FieldName = field_name.into();
+            object_types[type_name].fields[&field_name].r#type.clone()
+        };
+        let is_nullable = |field_type: Type| matches!(field_type, Type::Nullable { .. });
+        assert!(!is_nullable(field_type("search_Input_filter", "author")));
+        assert!(is_nullable(field_type("search_Input_filter", "state")));
+        assert!(!is_nullable(field_type(
+            "search_Input_filter_author",
+            "login"
+        )));
+        assert!(is_nullable(field_type(
+            "search_Input_filter_author",
+            "team"
+        )));
+        assert!(!is_nullable(field_type(
+            "search_Input_filter_author_team",
+            "slug"
+        )));
+        assert!(is_nullable(field_type(
+            "search_Input_filter_author_team",
+            "org"
+        )));
+    }
+
+    #[test]
+    fn test_project_structured_content() {
+        let output_schema = json!({
+            "type": "object",
+            "properties": {
+                "temperature": {"type": "number"},
+                "alerts": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {"title": {"type": "string"}}
+                    }
                 },
-                _ => panic!("Expected Array element type"),
+                "raw": {"type": "object"}
+            }
+        });
+        let structured_content = json!({
+            "temperature": 21.5,
+            "station": "KSFO",
+            "alerts": [{"title": "Fog", "severity": 2}],
+            "raw": {"any": "thing"}
+        });
+        assert_eq!(
+            project_structured_content(output_schema.as_object().unwrap(), structured_content),
+            json!({
+                "temperature": 21.5,
+                "alerts": [{"title": "Fog"}],
+                "raw": {"any": "thing"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_tool_result_type_from_output_schema() {
+        let mut tool = Tool::new(
+            "get_weather",
+            "Get the weather",
+            Arc::new(json!({"type": "object"}).as_object().unwrap().clone()),
+        );
+        let configuration: ConnectorConfig =
+            serde_json::from_value(json!({"servers": {}, "text_field": "first"})).unwrap();
+        let mut object_types = BTreeMap::new();
+
+        // Tools without an output schema use the generic ToolOutput type
+        match tool_result_type(
+            "weather__get_weather",
+            &tool,
+            &configuration,
+            &mut object_types,
+        ) {
+            Type::Named { name } => assert_eq!(name.as_str(), "ToolOutput"),
+            _ => panic!("Expected Named type"),
+        }
+        assert!(object_types.is_empty());
+
+        tool.output_schema = Some(Arc::new(
+            json!({
+                "type": "object",
+                "properties": {
+                    "temperature": {"type": "number", "description": "Temperature in C"},
+                    "location": {
+                        "type": "object",
+                        "properties": {
+                            "city": {"type": "string"}
+                        },
+                        "required": ["city"]
+                    },
+                    "alerts": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {"severity": {"type": "integer"}}
+                        }
+                    }
+                },
+                "required": ["temperature"]
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        ));
+
+        match tool_result_type(
+            "weather__get_weather",
+            &tool,
+            &configuration,
+            &mut object_types,
+        ) {
+            Type::Named { name } => assert_eq!(name.as_str(), "weather__get_weather_Output"),
+            _ => panic!("Expected Named type"),
+        }
+
+        let output_type = object_types.get("weather__get_weather_Output").unwrap();
+        assert!(output_type
+            .fields
+            .contains_key(&models::FieldName::new("text".into())));
+        match &output_type
+            .fields
+            .get(&models::FieldName::new("structured_content".into()))
+            .unwrap()
+            .r#type
+        {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => {
+                    assert_eq!(name.as_str(), "weather__get_weather_StructuredContent")
+                }
+                _ => panic!("Expected Named underlying type"),
             },
-            _ => panic!("Expected Array type"),
+            _ => panic!("Expected Nullable type"),
+        }
+
+        let structured_type = object_types
+            .get("weather__get_weather_StructuredContent")
+            .unwrap();
+        match &structured_type
+            .fields
+            .get(&models::FieldName::new("temperature".into()))
+            .unwrap()
+            .r#type
+        {
+            Type::Named { name } => assert_eq!(name.as_str(), "Float"),
+            _ => panic!("Expected Named type for temperature"),
+        }
+        match &structured_type
+            .fields
+            .get(&models::FieldName::new("location".into()))
+            .unwrap()
+            .r#type
+        {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Named { name } => {
+                    assert_eq!(
+                        name.as_str(),
+                        "weather__get_weather_StructuredContent_location"
+                    )
+                }
+                _ => panic!("Expected Named underlying type for location"),
+            },
+            _ => panic!("Expected Nullable type for location"),
+        }
+        match &structured_type
+            .fields
+            .get(&models::FieldName::new("alerts".into()))
+            .unwrap()
+            .r#type
+        {
+            Type::Nullable { underlying_type } => match underlying_type.as_ref() {
+                Type::Array { element_type } => match element_type.as_ref() {
+                    Type::Named { name } => {
+                        assert_eq!(
+                            name.as_str(),
+                            "weather__get_weather_StructuredContent_alerts"
+                        )
+                    }
+                    _ => panic!("Expected Named element type for alerts"),
+                },
+                _ => panic!("Expected Array underlying type for alerts"),
+            },
+            _ => panic!("Expected Nullable type for alerts"),
         }
+        assert!(object_types.contains_key("weather__get_weather_StructuredContent_alerts"));
+    }
+
+    #[test]
+    fn test_hide_forwarded_header_arguments() {
+        let forwarded_headers = ForwardedHeadersConfig {
+            argument: "_headers".to_string(),
+            mappings: HashMap::from([("X-User-Id".to_string(), "user_id".to_string())]),
+        };
+
+        let input_schema = json!({
+            "type": "object",
+            "properties": {
+                "user_id": {"type": "string"},
+                "query": {"type": "string"}
+            },
+            "required": ["user_id", "query"]
+        });
+        let mut arguments = tool_arguments_schema(
+            "test_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+        hide_forwarded_header_arguments(&mut arguments, &forwarded_headers);
+        assert!(!arguments.contains_key(&ArgumentName::new("user_id".into())));
+        assert!(arguments.contains_key(&ArgumentName::new("query".into())));
+        match &arguments
+            .get(&ArgumentName::new("_headers".into()))
+            .unwrap()
+            .argument_type
+        {
+            Type::Named { name } => assert_eq!(name.as_str(), "HeaderMap"),
+            _ => panic!("Expected Named type for _headers"),
+        }
+
+        // Tools without mapped arguments don't get the headers argument
+        let input_schema = json!({
+            "type": "object",
+            "properties": {"query": {"type": "string"}}
+        });
+        let mut arguments = tool_arguments_schema(
+            "test_Input",
+            input_schema.as_object().unwrap(),
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
+        hide_forwarded_header_arguments(&mut arguments, &forwarded_headers);
+        assert!(!arguments.contains_key(&ArgumentName::new("_headers".into())));
     }
 
     #[test]
@@ -613,7 +3996,12 @@ mod tests {
         });
 
         let input_schema_obj = input_schema.as_object().unwrap().clone();
-        let arguments = tool_arguments_schema(&input_schema_obj);
+        let arguments = tool_arguments_schema(
+            "test_Input",
+            &input_schema_obj,
+            &mut BTreeMap::new(),
+            &mut BTreeMap::new(),
+        );
 
         // Check that we have the expected arguments
         assert_eq!(arguments.len(), 5);
@@ -664,15 +4052,15 @@ mod tests {
             _ => panic!("Expected Nullable type for ids"),
         }
 
-        // Check mixed_data argument (optional String array)
+        // Check mixed_data argument (optional JSON array)
         let mixed_arg = arguments
             .get(&ArgumentName::new("mixed_data".into()))
             .unwrap();
         match &mixed_arg.argument_type {
             Type::Nullable { underlying_type } => match underlying_type.as_ref() {
                 Type::Array { element_type } => match element_type.as_ref() {
-                    Type::Named { name } => assert_eq!(name.as_str(), "String"),
-                    _ => panic!("Expected String element type for mixed_data"),
+                    Type::Named { name } => assert_eq!(name.as_str(), "JSON"),
+                    _ => panic!("Expected JSON element type for mixed_data"),
                 },
                 _ => panic!("Expected Array underlying type for mixed_data"),
             },
@@ -0,0 +1,78 @@
+//! The `schema` command prints the NDC schema, optionally restricted to the collections,
+//! functions and procedures contributed by selected servers. This helps review what a new
+//! server adds to a large supergraph.
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use crate::config::{ConnectorConfig, McpServerName, CONFIG_FILE_NAME};
+use crate::connector::{initialize_mcp_clients, initialize_offline_state};
+use crate::schema::generate_schema_from_state;
+
+/// Arguments for the `schema` command
+#[derive(Debug, Parser)]
+#[command(name = "schema")]
+pub struct SchemaArgs {
+    /// Directory containing the connector configuration
+    #[arg(
+        long,
+        env = "HASURA_CONFIGURATION_DIRECTORY",
+        default_value = "/etc/connector"
+    )]
+    configuration: PathBuf,
+
+    /// Only include the schema contributed by this server. Can be repeated.
+    #[arg(long = "server")]
+    servers: Vec<String>,
+}
+
+/// Run the `schema` command with the given command line arguments
+pub async fn run(args: impl IntoIterator<Item = String>) -> ExitCode {
+    let args = SchemaArgs::parse_from(args);
+    match export_schema(&args).await {
+        Ok(schema) => {
+            println!("{schema}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Generate the schema of the selected servers as pretty-printed JSON
+async fn export_schema(args: &SchemaArgs) -> Result<String> {
+    let configuration = ConnectorConfig::from_file(&args.configuration.join(CONFIG_FILE_NAME))?;
+
+    let selected = args
+        .servers
+        .iter()
+        .map(|server| McpServerName(server.clone()))
+        .collect::<Vec<_>>();
+    if let Some(unknown) = selected
+        .iter()
+        .find(|server_name| !configuration.servers.contains_key(*server_name))
+    {
+        return Err(anyhow!("Unknown server: {}", unknown.0));
+    }
+
+    let mut state = if configuration.offline {
+        initialize_offline_state(&configuration)
+    } else {
+        initialize_mcp_clients(&configuration).await
+    }
+    .map_err(|e| anyhow!("Failed to initialize MCP clients: {:?}", e))?;
+
+    // Keep the prefixes computed from all servers, so names match the full schema
+    if !selected.is_empty() {
+        state
+            .clients
+            .retain(|server_name, _| selected.contains(server_name));
+    }
+
+    let schema = generate_schema_from_state(&state, &configuration);
+    Ok(serde_json::to_string_pretty(&schema)?)
+}
@@ -0,0 +1,63 @@
+//! Stable hash of the generated schema, so automation can rebuild supergraph metadata only
+//! when the shape of the connector actually changed.
+
+use ndc_sdk::models::SchemaResponse;
+use sha2::{Digest, Sha256};
+
+/// Compute the SHA-256 hash of a schema as a hex string. The schema lists collections,
+/// functions and procedures in order of name, so equal schemas have equal hashes.
+pub fn schema_hash(schema: &SchemaResponse) -> String {
+    let json = serde_json::to_vec(schema).unwrap_or_default();
+    format!("{:x}", Sha256::digest(&json))
+}
+
+/// Log a change of the schema hash after re-introspection. Returns `true` if the hash differs
+/// from the previous one.
+pub fn log_schema_change(previous_hash: &str, hash: &str) -> bool {
+    if previous_hash == hash {
+        return false;
+    }
+    tracing::info!(
+        previous_hash = %previous_hash,
+        schema_hash = %hash,
+        "Schema changed after re-introspection"
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn schema(collections: usize) -> SchemaResponse {
+        SchemaResponse {
+            scalar_types: BTreeMap::new(),
+            object_types: BTreeMap::new(),
+            collections: (0..collections)
+                .map(|i| ndc_sdk::models::CollectionInfo {
+                    name: format!("files__file_{}", i).into(),
+                    description: None,
+                    arguments: BTreeMap::new(),
+                    collection_type: "ResourceOutput".to_string().into(),
+                    uniqueness_constraints: BTreeMap::new(),
+                    relational_mutations: None,
+                })
+                .collect(),
+            functions: Vec::new(),
+            procedures: Vec::new(),
+            capabilities: None,
+            request_arguments: None,
+        }
+    }
+
+    #[test]
+    fn test_schema_hash_changes() {
+        let hash = schema_hash(&schema(1));
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, schema_hash(&schema(1)));
+
+        assert!(!log_schema_change(&hash, &hash));
+        assert!(log_schema_change(&hash, &schema_hash(&schema(2))));
+    }
+}
@@ -0,0 +1,211 @@
+//! The `mcp_servers` collection lists the configured MCP servers with what they reported when
+//! connected, so operators can audit what the connector is talking to.
+
+use indexmap::IndexMap;
+use ndc_sdk::models::{self, CollectionInfo, ObjectField, ObjectType, Type};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::config::{ConnectorConfig, McpServerName};
+use crate::state::ConnectorState;
+
+/// Name of the collection listing the MCP servers
+pub const MCP_SERVERS_COLLECTION_NAME: &str = "mcp_servers";
+
+/// Name of the type of the rows of the `mcp_servers` collection
+const MCP_SERVER_TYPE_NAME: &str = "McpServer";
+
+/// Schema of the `mcp_servers` collection
+pub fn mcp_servers_collection_info() -> CollectionInfo {
+    CollectionInfo {
+        name: MCP_SERVERS_COLLECTION_NAME.to_string().into(),
+        description: Some("The MCP servers the connector is configured with".to_string()),
+        arguments: BTreeMap::new(),
+        collection_type: MCP_SERVER_TYPE_NAME.to_string().into(),
+        uniqueness_constraints: BTreeMap::from([(
+            "McpServerByName".to_string(),
+            models::UniquenessConstraint {
+                unique_columns: vec![models::FieldName::new("name".into())],
+            },
+        )]),
+        relational_mutations: None,
+    }
+}
+
+/// Type of the rows of the `mcp_servers` collection
+pub fn mcp_server_object_type() -> (String, ObjectType) {
+    let field = |description: &str, type_name: &str, nullable: bool| {
+        let named_type = Type::Named {
+            name: type_name.to_string().into(),
+        };
+        ObjectField {
+            description: Some(description.to_string()),
+            r#type: if nullable {
+                Type::Nullable {
+                    underlying_type: Box::new(named_type),
+                }
+            } else {
+                named_type
+            },
+            arguments: BTreeMap::new(),
+        }
+    };
+    let fields = BTreeMap::from([
+        (
+            "name".into(),
+            field(
+                "The name of the server in the configuration",
+                "String",
+                false,
+            ),
+        ),
+        (
+            "transport".into(),
+            field("The transport type: stdio, sse or http", "String", false),
+        ),
+        (
+            "status".into(),
+            field(
+                "The connection health: connected, degraded (failing fast after repeated \
+                 failures), offline (offline mode) or unavailable (failed to initialize)",
+                "String",
+                false,
+            ),
+        ),
+        (
+            "protocol_version".into(),
+            field("The negotiated MCP protocol version", "String", true),
+        ),
+        (
+            "server_info".into(),
+            field(
+                "The name and version the server reported as serverInfo",
+                "JSON",
+                true,
+            ),
+        ),
+        (
+            "capabilities".into(),
+            field("The capabilities declared by the server", "JSON", true),
+        ),
+    ]);
+    (
+        MCP_SERVER_TYPE_NAME.to_string(),
+        ObjectType {
+            description: Some("An MCP server the connector is configured with".to_string()),
+            fields,
+            foreign_keys: BTreeMap::new(),
+        },
+    )
+}
+
+/// The rows of the `mcp_servers` collection, in order of server name
+pub fn mcp_server_rows(
+    configuration: &ConnectorConfig,
+    state: &ConnectorState,
+) -> Vec<IndexMap<models::FieldName, models::RowFieldValue>> {
+    let mut servers = configuration.servers.iter().collect::<Vec<_>>();
+    servers.sort_by(|a, b| a.0 .0.cmp(&b.0 .0));
+    servers
+        .into_iter()
+        .map(|(server_name, server_config)| {
+            let client = state.clients.get(server_name);
+            let peer_info = client
+                .and_then(|client| client.service.as_ref())
+                .and_then(|service| service.peer_info());
+            let status = server_status(state, server_name);
+            let to_json = |value: Result<Value, serde_json::Error>| value.unwrap_or(Value::Null);
+
+            let fields = [
+                ("name", Value::String(server_name.0.clone())),
+                (
+                    "transport",
+                    Value::String(server_config.transport_name().to_string()),
+                ),
+                ("status", Value::String(status.to_string())),
+                (
+                    "protocol_version",
+                    peer_info
+                        .map(|info| to_json(serde_json::to_value(&info.protocol_version)))
+                        .unwrap_or(Value::Null),
+                ),
+                (
+                    "server_info",
+                    peer_info
+                        .map(|info| to_json(serde_json::to_value(&info.server_info)))
+                        .unwrap_or(Value::Null),
+                ),
+                (
+                    "capabilities",
+                    peer_info
+                        .map(|info| to_json(serde_json::to_value(&info.capabilities)))
+                        .unwrap_or(Value::Null),
+                ),
+            ];
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    (
+                        models::FieldName::new(name.into()),
+                        models::RowFieldValue(value),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The connection health of a server: connected, degraded, offline or unavailable
+pub(crate) fn server_status(state: &ConnectorState, server_name: &McpServerName) -> &'static str {
+    match state.clients.get(server_name) {
+        None => "unavailable",
+        Some(client) if client.service.is_none() => "offline",
+        Some(_) if state.server_health.open_for(server_name).is_some() => "degraded",
+        Some(_) => "connected",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::McpClient;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_mcp_server_rows() {
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {
+                "files": {"type": "stdio", "command": "mcp-files"},
+                "github": {"type": "http", "url": "http://localhost:8080/mcp"}
+            }
+        }))
+        .unwrap();
+        let mut state = ConnectorState::new();
+        state.add_client(
+            McpServerName("files".to_string()),
+            McpClient {
+                service: None,
+                resources: HashMap::new(),
+                resource_templates: HashMap::new(),
+                tools: HashMap::new(),
+                prompts: HashMap::new(),
+            },
+        );
+
+        let rows = mcp_server_rows(&configuration, &state)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(name, value)| (name.to_string(), value.0))
+                    .collect::<serde_json::Map<_, _>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows[0]["name"], "files");
+        assert_eq!(rows[0]["transport"], "stdio");
+        assert_eq!(rows[0]["status"], "offline");
+        assert_eq!(rows[1]["name"], "github");
+        assert_eq!(rows[1]["status"], "unavailable");
+        assert_eq!(rows[1]["protocol_version"], Value::Null);
+    }
+}
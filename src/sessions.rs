@@ -0,0 +1,149 @@
+//! Usage of the MCP session of each server. The connector holds one session per server and
+//! multiplexes concurrent tool calls over it, so calls never wait for a session; the number of
+//! calls in flight on each session is what capacity planning needs. There is no session pool,
+//! so no pool wait times are reported: they would always be zero.
+
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::config::McpServerName;
+use crate::server_info::server_status;
+use crate::state::ConnectorState;
+
+/// Tool calls in flight on the session of each server
+#[derive(Default)]
+pub struct SessionStats {
+    in_flight: Mutex<HashMap<McpServerName, usize>>,
+    metrics: Option<prometheus::IntGaugeVec>,
+}
+
+impl SessionStats {
+    /// Export the number of calls in flight in the given registry
+    pub fn with_metrics(mut self, registry: &mut prometheus::Registry) -> Self {
+        let register = |registry: &mut prometheus::Registry| {
+            let in_flight = prometheus::IntGaugeVec::new(
+                prometheus::Opts::new(
+                    "ndc_mcp_session_in_flight_calls",
+                    "Tool calls in flight on the session of each MCP server",
+                ),
+                &["server"],
+            )?;
+            registry.register(Box::new(in_flight.clone()))?;
+            prometheus::Result::Ok(in_flight)
+        };
+        match register(registry) {
+            Ok(in_flight) => self.metrics = Some(in_flight),
+            Err(err) => tracing::warn!("Failed to register the session metrics: {}", err),
+        }
+        self
+    }
+
+    /// Record the start of a call on the session of a server, until the returned guard is
+    /// dropped
+    pub fn start_call<'a>(&'a self, server_name: &McpServerName) -> InFlightCall<'a> {
+        self.add(server_name, 1);
+        InFlightCall {
+            stats: self,
+            server_name: server_name.clone(),
+        }
+    }
+
+    /// Number of calls in flight on the session of a server
+    pub fn in_flight(&self, server_name: &McpServerName) -> usize {
+        self.in_flight
+            .lock()
+            .unwrap()
+            .get(server_name)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn add(&self, server_name: &McpServerName, delta: isize) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let count = in_flight.entry(server_name.clone()).or_insert(0);
+        *count = count.saturating_add_signed(delta);
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .with_label_values(&[server_name.0.as_str()])
+                .set(*count as i64);
+        }
+    }
+}
+
+/// A call in flight on the session of a server
+pub struct InFlightCall<'a> {
+    stats: &'a SessionStats,
+    server_name: McpServerName,
+}
+
+impl Drop for InFlightCall<'_> {
+    fn drop(&mut self) {
+        self.stats.add(&self.server_name, -1);
+    }
+}
+
+/// The session of each server for the diagnostics: its status, the number of sessions (1, or
+/// 0 when there is no connection), how many of them have calls in flight and the calls in
+/// flight on it
+pub fn session_diagnostics(state: &ConnectorState) -> Value {
+    let sessions = state
+        .clients
+        .iter()
+        .map(|(server_name, client)| {
+            let in_flight_calls = state.sessions.in_flight(server_name);
+            (
+                server_name.0.clone(),
+                json!({
+                    "status": server_status(state, server_name),
+                    "size": usize::from(client.service.is_some()),
+                    "in_use": usize::from(in_flight_calls > 0),
+                    "in_flight_calls": in_flight_calls,
+                }),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+    json!(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::McpClient;
+
+    #[test]
+    fn test_in_flight_calls() {
+        let stats = SessionStats::default();
+        let server_name = McpServerName("github".to_string());
+        let first = stats.start_call(&server_name);
+        let second = stats.start_call(&server_name);
+        assert_eq!(stats.in_flight(&server_name), 2);
+        drop(first);
+        assert_eq!(stats.in_flight(&server_name), 1);
+        drop(second);
+        assert_eq!(stats.in_flight(&server_name), 0);
+    }
+
+    #[test]
+    fn test_session_diagnostics() {
+        let mut state = ConnectorState::new();
+        let server_name = McpServerName("github".to_string());
+        state.add_client(
+            server_name.clone(),
+            McpClient {
+                service: None,
+                resources: Default::default(),
+                resource_templates: Default::default(),
+                tools: Default::default(),
+                prompts: Default::default(),
+            },
+        );
+        assert_eq!(session_diagnostics(&state)["github"]["in_use"], 0);
+
+        let _call = state.sessions.start_call(&server_name);
+        let sessions = session_diagnostics(&state);
+        assert_eq!(sessions["github"]["size"], 0);
+        assert_eq!(sessions["github"]["in_use"], 1);
+        assert_eq!(sessions["github"]["in_flight_calls"], 1);
+    }
+}
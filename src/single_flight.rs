@@ -0,0 +1,192 @@
+//! Single-flight execution of identical calls, by call key. Identical calls made concurrently,
+//! e.g. when many requests fan out to the same read-only tool, share one call to the MCP
+//! server. With a window, a successful result also answers identical calls made within the
+//! window, which suppresses duplicate procedure calls. A failure is returned to every call
+//! waiting for it but is not remembered, so a later call is made again.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// A call in flight, or completed and remembered within its window
+struct Call<T, E> {
+    /// The result of the call, once it completed
+    result: watch::Receiver<Option<Result<T, E>>>,
+    started_at: Instant,
+    window: Option<Duration>,
+}
+
+impl<T, E> Call<T, E> {
+    /// Whether identical calls can still share this call
+    fn is_live(&self, now: Instant) -> bool {
+        let succeeded = self.result.borrow().as_ref().map(Result::is_ok);
+        match succeeded {
+            // A call whose caller went away before it completed is abandoned
+            None => self.result.has_changed().is_ok(),
+            Some(true) => self
+                .window
+                .is_some_and(|window| now < self.started_at + window),
+            Some(false) => false,
+        }
+    }
+}
+
+/// Calls in flight or within their window, by call key
+pub struct SingleFlight<T, E> {
+    calls: Mutex<HashMap<String, Call<T, E>>>,
+}
+
+impl<T, E> Default for SingleFlight<T, E> {
+    fn default() -> Self {
+        Self {
+            calls: Mutex::default(),
+        }
+    }
+}
+
+impl<T: Clone, E: Clone> SingleFlight<T, E> {
+    /// Make a call, or return the result of an identical call in flight or, with a `window`,
+    /// of an identical call that succeeded within the window
+    pub async fn call(
+        &self,
+        key: String,
+        window: Option<Duration>,
+        call: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let sender = loop {
+            let mut result = {
+                let mut calls = self.calls.lock().unwrap();
+                let now = Instant::now();
+                calls.retain(|_, call| call.is_live(now));
+                match calls.get(&key) {
+                    Some(call) => call.result.clone(),
+                    None => {
+                        let (sender, result) = watch::channel(None);
+                        calls.insert(
+                            key.clone(),
+                            Call {
+                                result,
+                                started_at: now,
+                                window,
+                            },
+                        );
+                        break sender;
+                    }
+                }
+            };
+            if result.borrow().is_some() {
+                tracing::info!("Suppressed a duplicate call");
+            }
+
+            // Share the result of the identical call, or try again if it was abandoned
+            let shared = result
+                .wait_for(Option::is_some)
+                .await
+                .ok()
+                .and_then(|result| (*result).clone());
+            if let Some(result) = shared {
+                return result;
+            }
+        };
+
+        let result = call.await;
+        sender.send_replace(Some(result.clone()));
+        let now = Instant::now();
+        self.calls
+            .lock()
+            .unwrap()
+            .retain(|_, call| call.is_live(now));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_concurrent_calls() {
+        let calls = SingleFlight::default();
+        let upstream_calls = AtomicUsize::new(0);
+        let call = |result| {
+            let upstream_calls = &upstream_calls;
+            async move {
+                upstream_calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                result
+            }
+        };
+
+        let (first, second) = tokio::join!(
+            calls.call("forecast".to_string(), None, call(Ok::<_, ()>(1))),
+            calls.call("forecast".to_string(), None, call(Ok(2))),
+        );
+        assert_eq!((first, second), (Ok(1), Ok(1)));
+        assert_eq!(upstream_calls.load(Ordering::SeqCst), 1);
+
+        // Calls made after the shared call completed go to the server again
+        let third = calls.call("forecast".to_string(), None, call(Ok(3))).await;
+        assert_eq!(third, Ok(3));
+        assert_eq!(upstream_calls.load(Ordering::SeqCst), 2);
+
+        // A failure wakes every waiting call with the error, and is not remembered
+        let (first, second) = tokio::join!(
+            calls.call("forecast".to_string(), None, call(Err(()))),
+            calls.call("forecast".to_string(), None, call(Ok(4))),
+        );
+        assert_eq!((first, second), (Err(()), Err(())));
+        assert_eq!(upstream_calls.load(Ordering::SeqCst), 3);
+        let retried = calls.call("forecast".to_string(), None, call(Ok(5))).await;
+        assert_eq!(retried, Ok(5));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_calls() {
+        let calls = SingleFlight::default();
+        let window = Some(Duration::from_secs(60));
+        let first = calls
+            .call("send_email".to_string(), window, async { Ok::<_, ()>(1) })
+            .await;
+        let second = calls
+            .call("send_email".to_string(), window, async { Ok(2) })
+            .await;
+        assert_eq!((first, second), (Ok(1), Ok(1)));
+
+        let other = calls
+            .call("send_sms".to_string(), window, async { Ok(3) })
+            .await;
+        assert_eq!(other, Ok(3));
+
+        // Failed calls are not remembered within the window
+        let failed = calls
+            .call("send_fax".to_string(), window, async { Err(()) })
+            .await;
+        assert_eq!(failed, Err(()));
+        let retried = calls
+            .call("send_fax".to_string(), window, async { Ok(4) })
+            .await;
+        assert_eq!(retried, Ok(4));
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_calls() {
+        let calls = SingleFlight::<i32, ()>::default();
+
+        // The waiting call makes its own call when the first one is dropped
+        let abandoned = tokio::time::timeout(
+            Duration::from_millis(10),
+            calls.call("forecast".to_string(), None, std::future::pending()),
+        );
+        let (abandoned, waiting) = tokio::join!(abandoned, async {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            calls
+                .call("forecast".to_string(), None, async { Ok(1) })
+                .await
+        });
+        assert!(abandoned.is_err());
+        assert_eq!(waiting, Ok(1));
+    }
+}
@@ -0,0 +1,247 @@
+//! The `smoke-test` command calls every read-only tool exposed as an NDC function with
+//! sample arguments and reports which calls succeeded. It exits non-zero if any call fails,
+//! so it can be used as a release gate when upgrading MCP servers.
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use rmcp::model::{CallToolRequestParam, Meta, RawContent, Tool};
+use schemars::schema::ObjectValidation;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Mutex;
+
+use crate::config::{ConnectorConfig, CONFIG_FILE_NAME};
+use crate::connector::{call_tool_with_meta, initialize_mcp_clients};
+use crate::schema::{is_read_only_tool, resolve_schema_refs};
+use crate::state::McpItemKind;
+
+/// Arguments for the `smoke-test` command
+#[derive(Debug, Parser)]
+#[command(name = "smoke-test")]
+pub struct SmokeTestArgs {
+    /// Directory containing the connector configuration
+    #[arg(
+        long,
+        env = "HASURA_CONFIGURATION_DIRECTORY",
+        default_value = "/etc/connector"
+    )]
+    configuration: PathBuf,
+
+    /// JSON file mapping function names to sample arguments, e.g.
+    /// `{"server__tool": {"arg": "value"}}`. These take precedence over schema examples.
+    #[arg(long)]
+    samples: Option<PathBuf>,
+}
+
+/// Outcome of calling a single tool
+#[derive(Debug)]
+pub enum SmokeTestOutcome {
+    Passed,
+    Failed(String),
+    Skipped(String),
+}
+
+/// Run the `smoke-test` command with the given command line arguments
+pub async fn run(args: impl IntoIterator<Item = String>) -> ExitCode {
+    let args = SmokeTestArgs::parse_from(args);
+    match run_smoke_test(&args).await {
+        Ok(report) => {
+            let mut passed = 0;
+            let mut failed = 0;
+            let mut skipped = 0;
+            for (function_name, outcome) in &report {
+                match outcome {
+                    SmokeTestOutcome::Passed => {
+                        passed += 1;
+                        println!("PASS {function_name}");
+                    }
+                    SmokeTestOutcome::Failed(reason) => {
+                        failed += 1;
+                        println!("FAIL {function_name}: {reason}");
+                    }
+                    SmokeTestOutcome::Skipped(reason) => {
+                        skipped += 1;
+                        println!("SKIP {function_name}: {reason}");
+                    }
+                }
+            }
+            println!("{passed} passed, {failed} failed, {skipped} skipped");
+            if failed > 0 {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Call every read-only tool and collect the outcome per function name
+async fn run_smoke_test(args: &SmokeTestArgs) -> Result<Vec<(String, SmokeTestOutcome)>> {
+    let configuration = ConnectorConfig::from_file(&args.configuration.join(CONFIG_FILE_NAME))?;
+
+    let samples: HashMap<String, Map<String, Value>> = match &args.samples {
+        Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+        None => HashMap::new(),
+    };
+
+    let state = initialize_mcp_clients(&configuration)
+        .await
+        .map_err(|e| anyhow!("Failed to initialize MCP clients: {:?}", e))?;
+
+    let mut report = Vec::new();
+    for (server_name, client) in &state.clients {
+        let Some(service) = &client.service else {
+            continue;
+        };
+        let mut tools: Vec<(&String, &Tool)> = client
+            .tools
+            .iter()
+            .filter(|(_, tool)| is_read_only_tool(server_name, tool, &configuration))
+            .collect();
+        tools.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (tool_id, tool) in tools {
+            let function_name = state.ndc_name(server_name, McpItemKind::Tool, tool_id);
+            let arguments = match sample_arguments(tool, samples.get(&function_name)) {
+                Ok(arguments) => arguments,
+                Err(reason) => {
+                    report.push((function_name, SmokeTestOutcome::Skipped(reason)));
+                    continue;
+                }
+            };
+
+            let call_request = CallToolRequestParam {
+                name: tool.name.clone(),
+                arguments: if arguments.is_empty() {
+                    None
+                } else {
+                    Some(arguments)
+                },
+            };
+
+            let meta = match configuration.tool_config(server_name, &tool.name) {
+                Some(tool_config) => tool_config.resolve_meta()?.map(Meta),
+                None => None,
+            };
+            let outcome =
+                match call_tool_with_meta(service, call_request, meta, &Mutex::default()).await {
+                    Ok(result) if result.is_error == Some(true) => {
+                        let message = result
+                            .content
+                            .into_iter()
+                            .filter_map(|content| match content.raw {
+                                RawContent::Text(text) => Some(text.text),
+                                _ => None,
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        SmokeTestOutcome::Failed(format!("tool returned an error: {}", message))
+                    }
+                    Ok(_) => SmokeTestOutcome::Passed,
+                    Err(err) => SmokeTestOutcome::Failed(err.to_string()),
+                };
+            report.push((function_name, outcome));
+        }
+    }
+
+    Ok(report)
+}
+
+/// Build sample arguments for a tool.
+///
+/// Each argument is taken from the provided samples if present, then from the schema `default`,
+/// then from the first schema example. Returns an error if a required argument has no sample.
+pub fn sample_arguments(
+    tool: &Tool,
+    samples: Option<&Map<String, Value>>,
+) -> Result<Map<String, Value>, String> {
+    let input_schema: ObjectValidation =
+        serde_json::from_value(resolve_schema_refs(&tool.input_schema))
+            .map_err(|e| format!("invalid input schema: {}", e))?;
+
+    let mut arguments = Map::new();
+    for (property_name, property) in input_schema.properties {
+        let sample = samples
+            .and_then(|samples| samples.get(&property_name).cloned())
+            .or_else(|| {
+                let metadata = *property.into_object().metadata?;
+                metadata
+                    .default
+                    .or_else(|| metadata.examples.into_iter().next())
+            });
+
+        match sample {
+            Some(value) => {
+                arguments.insert(property_name, value);
+            }
+            None if input_schema.required.contains(&property_name) => {
+                return Err(format!(
+                    "no sample value for required argument `{}`",
+                    property_name
+                ));
+            }
+            None => {}
+        }
+    }
+
+    Ok(arguments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn tool_with_schema(input_schema: Value) -> Tool {
+        Tool::new(
+            "get_weather",
+            "Get the weather",
+            Arc::new(input_schema.as_object().unwrap().clone()),
+        )
+    }
+
+    #[test]
+    fn test_sample_arguments_from_schema() {
+        let tool = tool_with_schema(json!({
+            "type": "object",
+            "properties": {
+                "city": {"type": "string", "examples": ["London", "Paris"]},
+                "units": {"type": "string", "default": "metric"},
+                "days": {"type": "integer"}
+            },
+            "required": ["city"]
+        }));
+
+        let arguments = sample_arguments(&tool, None).unwrap();
+        assert_eq!(arguments.get("city"), Some(&json!("London")));
+        assert_eq!(arguments.get("units"), Some(&json!("metric")));
+        assert_eq!(arguments.get("days"), None);
+    }
+
+    #[test]
+    fn test_sample_arguments_overrides_and_missing_required() {
+        let tool = tool_with_schema(json!({
+            "type": "object",
+            "properties": {
+                "city": {"type": "string", "examples": ["London"]},
+                "date": {"type": "string"}
+            },
+            "required": ["city", "date"]
+        }));
+
+        assert!(sample_arguments(&tool, None).is_err());
+
+        let samples = json!({"city": "Tokyo", "date": "2024-01-01"});
+        let arguments = sample_arguments(&tool, samples.as_object()).unwrap();
+        assert_eq!(arguments.get("city"), Some(&json!("Tokyo")));
+        assert_eq!(arguments.get("date"), Some(&json!("2024-01-01")));
+    }
+}
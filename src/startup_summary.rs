@@ -0,0 +1,198 @@
+//! Summary of what a connector deployment loaded, logged at startup and returned by the
+//! `_diagnostics` function so the configuration in effect can be checked at a glance.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+use crate::config::{CacheBackendConfig, ConnectorConfig, DeprecatedPolicy};
+use crate::features::active_features;
+use crate::state::ConnectorState;
+
+/// What was loaded from a server
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerSummary {
+    pub transport: String,
+    /// Whether the server was initialized, or its stored introspection loaded when offline
+    pub loaded: bool,
+    pub resources: usize,
+    pub resource_templates: usize,
+    pub tools: usize,
+    pub prompts: usize,
+}
+
+/// What a connector deployment loaded
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StartupSummary {
+    pub connector_version: String,
+    /// SHA-256 hash of the configuration, identifying the configuration version in effect
+    pub configuration_hash: String,
+    pub offline: bool,
+    /// Server names by transport type
+    pub servers_by_transport: BTreeMap<String, Vec<String>>,
+    pub servers: BTreeMap<String, ServerSummary>,
+    /// Optional features enabled by the configuration
+    pub enabled_features: Vec<&'static str>,
+    /// Whether each feature with a feature flag is active
+    pub features: BTreeMap<&'static str, bool>,
+}
+
+impl StartupSummary {
+    /// Summarize the configuration and the servers loaded into the state
+    pub fn new(configuration: &ConnectorConfig, state: &ConnectorState) -> Self {
+        let mut servers_by_transport = BTreeMap::<_, Vec<_>>::new();
+        let mut servers = BTreeMap::new();
+        for (server_name, server_config) in &configuration.servers {
+            let transport = server_config.transport_name().to_string();
+            servers_by_transport
+                .entry(transport.clone())
+                .or_default()
+                .push(server_name.0.clone());
+            let client = state.clients.get(server_name);
+            servers.insert(
+                server_name.0.clone(),
+                ServerSummary {
+                    transport,
+                    loaded: client.is_some(),
+                    resources: client.map_or(0, |client| client.resources.len()),
+                    resource_templates: client.map_or(0, |client| client.resource_templates.len()),
+                    tools: client.map_or(0, |client| client.tools.len()),
+                    prompts: client.map_or(0, |client| client.prompts.len()),
+                },
+            );
+        }
+        for server_names in servers_by_transport.values_mut() {
+            server_names.sort();
+        }
+
+        Self {
+            connector_version: env!("CARGO_PKG_VERSION").to_string(),
+            configuration_hash: configuration_hash(configuration),
+            offline: configuration.offline,
+            servers_by_transport,
+            servers,
+            enabled_features: enabled_features(configuration),
+            features: active_features(&configuration.features),
+        }
+    }
+
+    /// Log the summary, with a line per server
+    pub fn log(&self) {
+        let transports = self
+            .servers_by_transport
+            .iter()
+            .map(|(transport, server_names)| format!("{} {}", server_names.len(), transport))
+            .collect::<Vec<_>>();
+        tracing::info!(
+            connector_version = %self.connector_version,
+            configuration_hash = %self.configuration_hash,
+            offline = self.offline,
+            enabled_features = ?self.enabled_features,
+            features = ?self.features,
+            "Starting NDC MCP connector {} with {} servers ({})",
+            self.connector_version,
+            self.servers.len(),
+            transports.join(", ")
+        );
+        for (server_name, server) in &self.servers {
+            tracing::info!(
+                server = %server_name,
+                transport = server.transport,
+                loaded = server.loaded,
+                resources = server.resources,
+                resource_templates = server.resource_templates,
+                tools = server.tools,
+                prompts = server.prompts,
+                "Loaded MCP server {}",
+                server_name
+            );
+        }
+    }
+}
+
+/// Hash the configuration. Secrets in it are hashed, never logged. The configuration is
+/// converted to a JSON value first, whose object keys are sorted, so the hash doesn't depend on
+/// the iteration order of maps.
+fn configuration_hash(configuration: &ConnectorConfig) -> String {
+    let json = serde_json::to_value(configuration)
+        .and_then(|value| serde_json::to_vec(&value))
+        .unwrap_or_default();
+    format!("{:x}", Sha256::digest(&json))
+}
+
+fn enabled_features(configuration: &ConnectorConfig) -> Vec<&'static str> {
+    let features = [
+        ("diagnostics", configuration.diagnostics),
+        ("debug_endpoint", configuration.debug_address.is_some()),
+        ("read_only", configuration.read_only),
+        ("offline", configuration.offline),
+        ("trust_read_only_hint", configuration.trust_read_only_hint),
+        (
+            "hide_destructive_tools",
+            configuration.hide_destructive_tools,
+        ),
+        (
+            "hide_deprecated",
+            configuration.deprecated == DeprecatedPolicy::Hide,
+        ),
+        (
+            "stringify_structured_content",
+            configuration.stringify_structured_content,
+        ),
+        ("circuit_breaker", configuration.circuit_breaker.is_some()),
+        ("retry", configuration.retry.is_some()),
+        ("validate_arguments", configuration.validate_arguments),
+        ("strict_requests", configuration.strict_requests),
+        ("tool_errors", configuration.tool_errors.is_some()),
+        ("best_effort_mutations", configuration.best_effort_mutations),
+        (
+            "parallel_mutations",
+            configuration.parallel_mutations.is_some(),
+        ),
+        (
+            "forwarded_headers",
+            configuration.forwarded_headers.is_some(),
+        ),
+        (
+            "redis_cache",
+            matches!(
+                configuration.cache_backend,
+                CacheBackendConfig::Redis { .. }
+            ),
+        ),
+        ("sealed_arguments", configuration.sealed_arguments.is_some()),
+    ];
+    features
+        .into_iter()
+        .filter_map(|(feature, enabled)| enabled.then_some(feature))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_startup_summary() {
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {
+                "files": {"type": "stdio", "command": "mcp-files"},
+                "github": {"type": "http", "url": "http://localhost:8080/mcp"}
+            },
+            "retry": {},
+            "validate_arguments": true
+        }))
+        .unwrap();
+        let summary = StartupSummary::new(&configuration, &ConnectorState::new());
+
+        assert_eq!(summary.servers_by_transport["stdio"], vec!["files"]);
+        assert_eq!(summary.servers_by_transport["http"], vec!["github"]);
+        assert!(!summary.servers["files"].loaded);
+        assert_eq!(
+            summary.enabled_features,
+            vec!["retry", "validate_arguments"]
+        );
+        assert_eq!(summary.configuration_hash.len(), 64);
+    }
+}
@@ -1,20 +1,83 @@
+use ndc_sdk::models::SchemaResponse;
+use rmcp::ServiceError;
 use rmcp::{
-    model::{Resource, Tool},
+    model::{CallToolResult, Prompt, Resource, ResourceTemplate, Tool},
     service::RunningService,
     RoleClient,
 };
-use std::collections::HashMap;
+use serde::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
 
-use crate::config::McpServerName;
+use crate::cache::ResponseCache;
+use crate::call_sampling::CallSampler;
+use crate::config::{ConnectorConfig, McpServerConfig, McpServerName, NamingConfig, ToolExposure};
+use crate::decrypt::ArgumentDecryptor;
+use crate::events::{ConnectionEventKind, ConnectionEventLog};
+use crate::failed_calls::FailedCallLog;
+use crate::health::{CircuitTransition, ServerHealth};
+use crate::inference::OutputSchemaInference;
+use crate::operation_error::OperationError;
+use crate::pagination::PaginationMemory;
+use crate::retry::RetryBudget;
+use crate::schema::{generate_schema_from_state, sanitized_names};
+use crate::server_info::MCP_SERVERS_COLLECTION_NAME;
+use crate::sessions::SessionStats;
+use crate::single_flight::SingleFlight;
+use crate::startup_summary::StartupSummary;
 
 /// Represents a connected MCP client
 pub struct McpClient {
-    /// The service connection to the MCP server
-    pub service: RunningService<RoleClient, ()>,
+    /// The service connection to the MCP server, `None` when running offline
+    pub service: Option<RunningService<RoleClient, ()>>,
     /// Resources provided by this server
     pub resources: HashMap<String, Resource>,
+    /// Resource templates provided by this server
+    pub resource_templates: HashMap<String, ResourceTemplate>,
     /// Tools provided by this server
     pub tools: HashMap<String, Tool>,
+    /// Prompts provided by this server
+    pub prompts: HashMap<String, Prompt>,
+}
+
+/// Kind of the MCP item an NDC collection, function or procedure is generated from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum McpItemKind {
+    Resource,
+    ResourceTemplate,
+    Tool,
+    Prompt,
+    /// The collection listing the tools of a server
+    ToolCatalog,
+    /// The procedure of a tool exposed as both a function and a procedure
+    ToolProcedure,
+}
+
+/// Name of the tool catalog item of a server, so the collection listing its tools is named
+/// like `{server}__tools`
+pub const TOOL_CATALOG_NAME: &str = "tools";
+
+/// The MCP item an NDC name refers to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct McpItem {
+    pub server_name: McpServerName,
+    pub kind: McpItemKind,
+    /// The key of the item in the client's resources, tools or prompts
+    pub name: String,
+}
+
+/// An MCP item whose NDC name was already taken, and the name it was given instead
+#[derive(Debug, Clone, Serialize)]
+pub struct NameCollision {
+    pub name: String,
+    pub server: String,
+    pub kind: McpItemKind,
+    pub item: String,
+    pub renamed_to: String,
 }
 
 /// The state of the connector
@@ -22,6 +85,47 @@ pub struct McpClient {
 pub struct ConnectorState {
     /// Connected MCP clients
     pub clients: HashMap<McpServerName, McpClient>,
+    /// Prefixes of the NDC names of each server, sanitized from the server names
+    pub server_prefixes: HashMap<McpServerName, String>,
+    /// NDC names of the resources, tools and prompts of all servers
+    pub ndc_names: HashMap<McpItem, String>,
+    /// MCP items by NDC name
+    pub mcp_items: HashMap<String, McpItem>,
+    /// Items that were renamed because their NDC name was already taken
+    pub name_collisions: Vec<NameCollision>,
+    /// Decryptor for sealed argument values
+    pub decryptor: Option<Arc<dyn ArgumentDecryptor>>,
+    /// Cache of read-only tool responses
+    pub response_cache: ResponseCache,
+    /// Read-only tool calls in flight, shared by identical concurrent calls
+    pub in_flight_calls: SingleFlight<CallToolResult, OperationError>,
+    /// Output types inferred for tools without an output schema
+    pub output_schema_inference: OutputSchemaInference,
+    /// Recent connection lifecycle events per server
+    pub connection_events: Arc<ConnectionEventLog>,
+    /// Most recent failed calls to MCP servers
+    pub failed_calls: Arc<FailedCallLog>,
+    /// Circuit state of each server
+    pub server_health: Arc<ServerHealth>,
+    /// Retry budget of each server
+    pub retry_budget: RetryBudget,
+    /// Calls in flight on the session of each server
+    pub sessions: SessionStats,
+    /// Tool calls logged in detail per server
+    pub call_sampler: CallSampler,
+    /// Cursors of the pagination sessions of paginated tools
+    pub pagination: PaginationMemory,
+    /// Results of recent calls of tools with a duplicate call window
+    pub duplicate_calls: SingleFlight<Value, Result<Value, OperationError>>,
+    /// Limits on the operations of parallel mutations calling each server, shared by all
+    /// mutations
+    pub mutation_permits: Mutex<HashMap<McpServerName, Arc<Semaphore>>>,
+    /// Schema generated from this state at startup, which requests are validated against
+    pub schema: Option<SchemaResponse>,
+    /// Hash of the schema generated from this state
+    pub schema_hash: String,
+    /// What the deployment loaded, logged at startup
+    pub startup_summary: StartupSummary,
 }
 
 impl ConnectorState {
@@ -29,6 +133,48 @@ impl ConnectorState {
     pub fn new() -> Self {
         Self {
             clients: HashMap::new(),
+            server_prefixes: HashMap::new(),
+            ndc_names: HashMap::new(),
+            mcp_items: HashMap::new(),
+            name_collisions: Vec::new(),
+            decryptor: None,
+            response_cache: ResponseCache::default(),
+            in_flight_calls: SingleFlight::default(),
+            output_schema_inference: OutputSchemaInference::default(),
+            connection_events: Arc::default(),
+            failed_calls: Arc::default(),
+            server_health: Arc::default(),
+            retry_budget: RetryBudget::default(),
+            sessions: SessionStats::default(),
+            call_sampler: CallSampler::default(),
+            pagination: PaginationMemory::default(),
+            duplicate_calls: SingleFlight::default(),
+            mutation_permits: Mutex::default(),
+            schema: None,
+            schema_hash: String::new(),
+            startup_summary: StartupSummary::default(),
+        }
+    }
+
+    /// The limit on the operations of parallel mutations calling a server at a time
+    pub fn mutation_permits(
+        &self,
+        server_name: &McpServerName,
+        max_concurrency: usize,
+    ) -> Arc<Semaphore> {
+        self.mutation_permits
+            .lock()
+            .unwrap()
+            .entry(server_name.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(max_concurrency)))
+            .clone()
+    }
+
+    /// The schema generated at startup, or generated now for states created without one
+    pub fn schema(&self, configuration: &ConnectorConfig) -> Cow<'_, SchemaResponse> {
+        match &self.schema {
+            Some(schema) => Cow::Borrowed(schema),
+            None => Cow::Owned(generate_schema_from_state(self, configuration)),
         }
     }
 
@@ -36,5 +182,236 @@ impl ConnectorState {
     pub fn add_client(&mut self, name: McpServerName, client: McpClient) {
         // Add the client
         self.clients.insert(name, client);
+
+        // Recompute the prefixes, as sanitized names may now collide
+        self.server_prefixes = sanitized_names(self.clients.keys().map(|name| name.0.as_str()))
+            .into_iter()
+            .map(|(name, prefix)| (McpServerName(name), prefix))
+            .collect();
+    }
+
+    /// Record the outcome of a call to a server in its connection events and circuit state
+    pub fn record_outcome<T>(&self, server_name: &McpServerName, result: &Result<T, ServiceError>) {
+        match result {
+            Ok(_) => self.connection_events.record_success(server_name),
+            Err(err) => self
+                .connection_events
+                .record_service_error(server_name, err),
+        }
+        match self.server_health.record(server_name, result) {
+            Some(CircuitTransition::Opened) => {
+                tracing::warn!(
+                    "MCP server {} is degraded after repeated failures",
+                    server_name.0
+                );
+                self.connection_events.record(
+                    server_name,
+                    ConnectionEventKind::CircuitOpened,
+                    result.as_ref().err().map(|err| err.to_string()),
+                );
+            }
+            Some(CircuitTransition::Closed) => {
+                tracing::info!("MCP server {} recovered", server_name.0);
+                self.connection_events
+                    .record(server_name, ConnectionEventKind::Recovered, None);
+            }
+            None => {}
+        }
+    }
+
+    /// Get the NDC name prefix of a server
+    pub fn server_prefix(&self, name: &McpServerName) -> &str {
+        self.server_prefixes
+            .get(name)
+            .map(|prefix| prefix.as_str())
+            .unwrap_or(name.0.as_str())
+    }
+
+    /// Assign the NDC names of the resources, tools and prompts of all servers. Items are
+    /// named in order of server, kind and name, and an item whose name is already taken gets
+    /// a numeric suffix. The tools of servers exposing them as both functions and procedures
+    /// get an item of each kind, with distinct suffixes. Must be called after all clients are
+    /// added.
+    pub fn assign_ndc_names(
+        &mut self,
+        naming: &NamingConfig,
+        servers: &HashMap<McpServerName, McpServerConfig>,
+    ) {
+        let mut items = Vec::new();
+        for (server_name, client) in &self.clients {
+            let tools_as_both = servers
+                .get(server_name)
+                .and_then(|server_config| server_config.options().expose_tools_as)
+                == Some(ToolExposure::Both);
+            let kinds = [
+                (
+                    McpItemKind::Resource,
+                    client.resources.keys().collect::<Vec<_>>(),
+                ),
+                (
+                    McpItemKind::ResourceTemplate,
+                    client.resource_templates.keys().collect(),
+                ),
+                (McpItemKind::Tool, client.tools.keys().collect()),
+                (McpItemKind::Prompt, client.prompts.keys().collect()),
+            ];
+            let tool_catalog = (!client.tools.is_empty()).then(|| McpItem {
+                server_name: server_name.clone(),
+                kind: McpItemKind::ToolCatalog,
+                name: TOOL_CATALOG_NAME.to_string(),
+            });
+            items.extend(tool_catalog);
+            if tools_as_both {
+                items.extend(client.tools.keys().map(|name| McpItem {
+                    server_name: server_name.clone(),
+                    kind: McpItemKind::ToolProcedure,
+                    name: name.clone(),
+                }));
+            }
+            for (kind, names) in kinds {
+                for name in names {
+                    items.push(McpItem {
+                        server_name: server_name.clone(),
+                        kind,
+                        name: name.clone(),
+                    });
+                }
+            }
+        }
+        items.sort_by(|a, b| {
+            (&a.server_name.0, a.kind, &a.name).cmp(&(&b.server_name.0, b.kind, &b.name))
+        });
+        let tools_as_both = items
+            .iter()
+            .filter(|item| item.kind == McpItemKind::ToolProcedure)
+            .map(|item| item.server_name.clone())
+            .collect::<HashSet<_>>();
+
+        self.ndc_names.clear();
+        self.mcp_items.clear();
+        self.name_collisions.clear();
+        for item in items {
+            let item_name = match item.kind {
+                McpItemKind::Tool if tools_as_both.contains(&item.server_name) => {
+                    format!("{}_function", item.name)
+                }
+                McpItemKind::ToolProcedure => format!("{}_procedure", item.name),
+                _ => item.name.clone(),
+            };
+            let name = naming.name(self.server_prefix(&item.server_name), &item_name);
+            let mut ndc_name = name.clone();
+            let mut suffix = 2;
+            // The name of the collection listing the servers is reserved
+            while self.mcp_items.contains_key(&ndc_name) || ndc_name == MCP_SERVERS_COLLECTION_NAME
+            {
+                ndc_name = format!("{}_{}", name, suffix);
+                suffix += 1;
+            }
+            if ndc_name != name {
+                tracing::warn!(
+                    "NDC name {} of {:?} {} on server {} is already taken, using {}",
+                    name,
+                    item.kind,
+                    item.name,
+                    item.server_name.0,
+                    ndc_name
+                );
+                self.name_collisions.push(NameCollision {
+                    name,
+                    server: item.server_name.0.clone(),
+                    kind: item.kind,
+                    item: item.name.clone(),
+                    renamed_to: ndc_name.clone(),
+                });
+            }
+            self.mcp_items.insert(ndc_name.clone(), item.clone());
+            self.ndc_names.insert(item, ndc_name);
+        }
+    }
+
+    /// Get the NDC name of a resource, tool or prompt
+    pub fn ndc_name(&self, server_name: &McpServerName, kind: McpItemKind, name: &str) -> String {
+        let item = McpItem {
+            server_name: server_name.clone(),
+            kind,
+            name: name.to_string(),
+        };
+        self.ndc_names
+            .get(&item)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Resolve an NDC collection, function or procedure name to its server, client and
+    /// the kind and key of the resource, tool or prompt
+    pub fn resolve_name(
+        &self,
+        name: &str,
+    ) -> Option<(&McpServerName, &McpClient, McpItemKind, &str)> {
+        let item = self.mcp_items.get(name)?;
+        let (server_name, client) = self.clients.get_key_value(&item.server_name)?;
+        Some((server_name, client, item.kind, item.name.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn client_with_tools(names: &[&str]) -> McpClient {
+        let input_schema = Arc::new(json!({"type": "object"}).as_object().unwrap().clone());
+        McpClient {
+            service: None,
+            resources: HashMap::new(),
+            resource_templates: HashMap::new(),
+            tools: names
+                .iter()
+                .map(|name| {
+                    (
+                        name.to_string(),
+                        Tool::new(name.to_string(), "", input_schema.clone()),
+                    )
+                })
+                .collect(),
+            prompts: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_assign_ndc_names_with_collisions() {
+        let mut state = ConnectorState::new();
+        state.add_client(
+            McpServerName("github".to_string()),
+            client_with_tools(&["search"]),
+        );
+        state.add_client(
+            McpServerName("gitlab".to_string()),
+            client_with_tools(&["search"]),
+        );
+        let naming: NamingConfig = serde_json::from_value(json!({"style": "none"})).unwrap();
+        state.assign_ndc_names(&naming, &HashMap::new());
+
+        let github = McpServerName("github".to_string());
+        let gitlab = McpServerName("gitlab".to_string());
+        assert_eq!(
+            state.ndc_name(&github, McpItemKind::Tool, "search"),
+            "search"
+        );
+        assert_eq!(
+            state.ndc_name(&gitlab, McpItemKind::Tool, "search"),
+            "search_2"
+        );
+        assert_eq!(
+            state.ndc_name(&gitlab, McpItemKind::ToolCatalog, TOOL_CATALOG_NAME),
+            "tools_2"
+        );
+        assert_eq!(state.name_collisions.len(), 2);
+        assert_eq!(state.name_collisions[0].server, "gitlab");
+
+        let (server_name, _, kind, name) = state.resolve_name("search_2").unwrap();
+        assert_eq!(server_name, &gitlab);
+        assert_eq!(kind, McpItemKind::Tool);
+        assert_eq!(name, "search");
     }
 }
@@ -0,0 +1,114 @@
+//! Error results of tools. Servers report a failed tool call with `isError: true` and the error
+//! in the result: in the structured content or a JSON text block, often under an `error` key,
+//! or as plain text. With `tool_errors` configured it is read into a typed `ToolError` instead
+//! of being returned as normal content.
+
+use rmcp::model::{CallToolResult, RawContent};
+use serde::Serialize;
+use serde_json::Value;
+
+/// The error of a tool error result
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolError {
+    /// The error code, if the tool returned one
+    pub code: Option<String>,
+    pub message: String,
+    /// Additional data about the error, if the tool returned any
+    pub data: Option<Value>,
+}
+
+/// Read the error of a tool result, `None` if the call succeeded
+pub fn tool_error(result: &CallToolResult) -> Option<ToolError> {
+    if result.is_error != Some(true) {
+        return None;
+    }
+    let texts = result
+        .content
+        .iter()
+        .filter_map(|content| RawContent::as_text(&content.raw))
+        .map(|text| text.text.as_str())
+        .collect::<Vec<_>>();
+    let error_object = result
+        .structured_content
+        .clone()
+        .into_iter()
+        .chain(
+            texts
+                .iter()
+                .filter_map(|text| serde_json::from_str::<Value>(text).ok()),
+        )
+        .filter_map(|value| match value {
+            Value::Object(mut object) => match object.remove("error") {
+                Some(Value::Object(error)) => Some(error),
+                _ => Some(object),
+            },
+            _ => None,
+        })
+        .find(|object| object.contains_key("message"));
+
+    let Some(mut error_object) = error_object else {
+        let message = if texts.is_empty() {
+            "The tool returned an error".to_string()
+        } else {
+            texts.join("\n")
+        };
+        return Some(ToolError {
+            code: None,
+            message,
+            data: None,
+        });
+    };
+    let message = match error_object.remove("message") {
+        Some(Value::String(message)) => message,
+        Some(message) => message.to_string(),
+        None => String::new(),
+    };
+    let code = match error_object.remove("code") {
+        Some(Value::String(code)) => Some(code),
+        Some(Value::Number(code)) => Some(code.to_string()),
+        _ => None,
+    };
+    Some(ToolError {
+        code,
+        message,
+        data: error_object.remove("data").filter(|data| !data.is_null()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_error() {
+        assert_eq!(
+            tool_error(&CallToolResult::success(vec![Content::text("ok")])),
+            None
+        );
+        assert_eq!(
+            tool_error(&CallToolResult::error(vec![Content::text(
+                "Repository not found"
+            )])),
+            Some(ToolError {
+                code: None,
+                message: "Repository not found".to_string(),
+                data: None,
+            })
+        );
+
+        let mut result = CallToolResult::error(vec![Content::text("Rate limited")]);
+        result.structured_content = Some(json!({
+            "error": {"code": 429, "message": "Rate limited", "data": {"retry_after": 30}}
+        }));
+        assert_eq!(
+            tool_error(&result),
+            Some(ToolError {
+                code: Some("429".to_string()),
+                message: "Rate limited".to_string(),
+                data: Some(json!({"retry_after": 30})),
+            })
+        );
+    }
+}
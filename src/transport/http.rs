@@ -1,26 +1,75 @@
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use rmcp::{
     service::RunningService,
-    transport::streamable_http_client::{
-        StreamableHttpClientTransport, StreamableHttpClientTransportConfig,
+    transport::{
+        sse_client::{SseClientConfig, SseClientTransport},
+        streamable_http_client::{
+            StreamableHttpClientTransport, StreamableHttpClientTransportConfig,
+        },
     },
     RoleClient, ServiceExt,
 };
+use serde_json::Value;
 use std::time::Duration;
 
+use super::{config_schema, parse_settings, InitializationTimeout, McpTransportFactory};
 use crate::config::StreamableHttpConfig;
 
-/// Create an MCP client using streamable HTTP transport
-pub async fn create_http_client(
+/// The `http` transport, using streamable HTTP with a fallback to HTTP+SSE
+pub struct HttpTransport;
+
+#[async_trait]
+impl McpTransportFactory for HttpTransport {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    fn config_schema(&self) -> Value {
+        config_schema::<StreamableHttpConfig>()
+    }
+
+    async fn connect(&self, settings: &Value) -> Result<RunningService<RoleClient, ()>> {
+        create_http_client(&parse_settings(settings)?).await
+    }
+}
+
+/// Create an MCP client using streamable HTTP transport. Servers that don't accept it, such
+/// as servers only answering with an event stream on the HTTP+SSE transport of earlier
+/// protocol versions, are connected with that transport instead.
+async fn create_http_client(
+    config: &StreamableHttpConfig,
+) -> Result<RunningService<RoleClient, ()>> {
+    let err = match create_streamable_http_client(config).await {
+        Ok(service) => return Ok(service),
+        // Don't wait for the timeout a second time
+        Err(err) if !config.sse_fallback || err.is::<InitializationTimeout>() => return Err(err),
+        Err(err) => err,
+    };
+
+    tracing::info!(
+        "Streamable HTTP initialization of {} failed, falling back to the HTTP+SSE transport: {}",
+        config.url,
+        err
+    );
+    create_sse_client(config)
+        .await
+        .map_err(|sse_err| anyhow!("{}; HTTP+SSE fallback failed: {}", err, sse_err))
+}
+
+async fn create_streamable_http_client(
     config: &StreamableHttpConfig,
 ) -> Result<RunningService<RoleClient, ()>> {
     // Extract Authorization header value from config if present
     let auth_header = config.headers.get("Authorization");
     // build the config to use with this transport
     let mut http_config = StreamableHttpClientTransportConfig::with_uri(config.url.clone());
-    // set auth header if present
+    // set auth header if present, skipping an optional one that is not set
     if let Some(auth_header) = auth_header {
-        http_config = http_config.auth_header(auth_header.resolve()?);
+        if let Some(auth_header) = auth_header.resolve()? {
+            http_config = http_config.auth_header(auth_header);
+        }
     }
     // Create streamable HTTP transport using the reqwest client
     let transport = StreamableHttpClientTransport::from_config(http_config);
@@ -31,8 +80,45 @@ pub async fn create_http_client(
         ().serve(transport),
     )
     .await
-    .map_err(|_| anyhow!("Timeout during MCP service initialization"))?
+    .map_err(|_| InitializationTimeout)?
     .map_err(|e| anyhow!("Failed to initialize MCP service: {}", e))?;
 
     Ok(service)
 }
+
+/// Create an MCP client using the HTTP+SSE transport, sending the configured headers with
+/// every request
+async fn create_sse_client(
+    config: &StreamableHttpConfig,
+) -> Result<RunningService<RoleClient, ()>> {
+    let mut headers = HeaderMap::new();
+    for (key, value) in &config.headers {
+        if let Some(value) = value.resolve()? {
+            headers.insert(
+                HeaderName::from_bytes(key.as_bytes())?,
+                HeaderValue::from_str(&value)?,
+            );
+        }
+    }
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()?;
+    let sse_config = SseClientConfig {
+        sse_endpoint: config.url.clone().into(),
+        ..Default::default()
+    };
+
+    // Open the event stream and initialize the client with timeout
+    let service = tokio::time::timeout(Duration::from_secs(config.timeout_seconds), async {
+        let transport = SseClientTransport::start_with_client(client, sse_config)
+            .await
+            .map_err(|e| anyhow!("Failed to open event stream: {}", e))?;
+        ().serve(transport)
+            .await
+            .map_err(|e| anyhow!("Failed to initialize MCP service: {}", e))
+    })
+    .await
+    .map_err(|_| InitializationTimeout)??;
+
+    Ok(service)
+}
@@ -1,16 +1,149 @@
+//! Transports connecting to MCP servers. Each transport is a `McpTransportFactory` registered
+//! under the name servers are configured with: the `type` of built-in transports, or the
+//! `transport` of servers with `"type": "custom"`. Embedders can register their own transports
+//! with `register_transport` before the connector starts.
+
 mod http;
 mod sse;
 mod stdio;
 
 use crate::config::McpServerConfig;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use rmcp::{service::RunningService, RoleClient};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, LazyLock, RwLock};
+
+pub use self::http::HttpTransport;
+pub use self::sse::SseTransport;
+pub use self::stdio::StdioTransport;
+
+/// Error returned when an MCP server does not complete initialization in time
+#[derive(Debug)]
+pub struct InitializationTimeout;
+
+impl fmt::Display for InitializationTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Timeout during MCP service initialization")
+    }
+}
+
+impl std::error::Error for InitializationTimeout {}
+
+/// Connects to MCP servers over a transport
+#[async_trait]
+pub trait McpTransportFactory: Send + Sync {
+    /// The name servers are configured with to use the transport
+    fn name(&self) -> &str;
+
+    /// The JSON Schema of the settings of the transport
+    fn config_schema(&self) -> Value;
+
+    /// Connect to a server and initialize the MCP session
+    async fn connect(&self, settings: &Value) -> Result<RunningService<RoleClient, ()>>;
+}
+
+/// The registered transports by name
+static TRANSPORTS: LazyLock<RwLock<BTreeMap<String, Arc<dyn McpTransportFactory>>>> =
+    LazyLock::new(|| {
+        let factories: [Arc<dyn McpTransportFactory>; 3] = [
+            Arc::new(StdioTransport),
+            Arc::new(SseTransport),
+            Arc::new(HttpTransport),
+        ];
+        RwLock::new(
+            factories
+                .into_iter()
+                .map(|factory| (factory.name().to_string(), factory))
+                .collect(),
+        )
+    });
+
+/// Register a transport, replacing any transport registered under the same name
+pub fn register_transport(factory: Arc<dyn McpTransportFactory>) {
+    let mut transports = TRANSPORTS.write().unwrap();
+    transports.insert(factory.name().to_string(), factory);
+}
+
+/// The transport registered under a name
+pub fn transport_factory(name: &str) -> Option<Arc<dyn McpTransportFactory>> {
+    TRANSPORTS.read().unwrap().get(name).cloned()
+}
+
+/// The settings schema of every registered transport, by name
+pub fn transport_config_schemas() -> BTreeMap<String, Value> {
+    TRANSPORTS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(name, factory)| (name.clone(), factory.config_schema()))
+        .collect()
+}
 
 /// Create an MCP client based on the server configuration
 pub async fn create_mcp_client(config: &McpServerConfig) -> Result<RunningService<RoleClient, ()>> {
-    match config {
-        McpServerConfig::Stdio(stdio_config) => stdio::create_stdio_client(stdio_config).await,
-        McpServerConfig::Sse(sse_config) => sse::create_sse_client(sse_config).await,
-        McpServerConfig::Http(http_config) => http::create_http_client(http_config).await,
+    let name = config.transport_name();
+    let factory = transport_factory(name).ok_or_else(|| anyhow!("Unknown transport: {}", name))?;
+    factory.connect(&config.transport_settings()?).await
+}
+
+/// The JSON Schema of a built-in transport's configuration
+fn config_schema<T: JsonSchema>() -> Value {
+    serde_json::to_value(schemars::schema_for!(T)).unwrap_or(Value::Null)
+}
+
+/// Read the settings of a built-in transport into its configuration
+fn parse_settings<T: DeserializeOwned>(settings: &Value) -> Result<T> {
+    serde_json::from_value(settings.clone())
+        .map_err(|e| anyhow!("Invalid transport settings: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct UnreachableTransport;
+
+    #[async_trait]
+    impl McpTransportFactory for UnreachableTransport {
+        fn name(&self) -> &str {
+            "unreachable"
+        }
+
+        fn config_schema(&self) -> Value {
+            json!({"type": "object", "properties": {"host": {"type": "string"}}})
+        }
+
+        async fn connect(&self, settings: &Value) -> Result<RunningService<RoleClient, ()>> {
+            Err(anyhow!("Cannot reach {}", settings["host"]))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_transport() {
+        let config: McpServerConfig = serde_json::from_value(json!({
+            "type": "custom",
+            "transport": "unreachable",
+            "settings": {"host": "mcp.internal"}
+        }))
+        .unwrap();
+        let err = create_mcp_client(&config).await.unwrap_err();
+        assert_eq!(err.to_string(), "Unknown transport: unreachable");
+
+        register_transport(Arc::new(UnreachableTransport));
+        let err = create_mcp_client(&config).await.unwrap_err();
+        assert_eq!(err.to_string(), "Cannot reach \"mcp.internal\"");
+
+        let schemas = transport_config_schemas();
+        assert_eq!(
+            schemas.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["http", "sse", "stdio", "unreachable"]
+        );
+        assert_eq!(schemas["stdio"]["required"], json!(["command"]));
     }
 }
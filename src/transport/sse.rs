@@ -1,12 +1,33 @@
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use rmcp::{service::RunningService, RoleClient};
+use serde_json::Value;
 use url::Url;
 
+use super::{config_schema, parse_settings, McpTransportFactory};
 use crate::config::SseConfig;
 
+/// The deprecated `sse` transport
+pub struct SseTransport;
+
+#[async_trait]
+impl McpTransportFactory for SseTransport {
+    fn name(&self) -> &str {
+        "sse"
+    }
+
+    fn config_schema(&self) -> Value {
+        config_schema::<SseConfig>()
+    }
+
+    async fn connect(&self, settings: &Value) -> Result<RunningService<RoleClient, ()>> {
+        create_sse_client(&parse_settings(settings)?).await
+    }
+}
+
 /// Create an MCP client using SSE transport
-pub async fn create_sse_client(config: &SseConfig) -> Result<RunningService<RoleClient, ()>> {
+async fn create_sse_client(config: &SseConfig) -> Result<RunningService<RoleClient, ()>> {
     // Extract fields from the config
     // Parse URL
     let _url = Url::parse(&config.url)?;
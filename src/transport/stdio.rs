@@ -1,21 +1,44 @@
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use rmcp::{service::RunningService, transport::TokioChildProcess, RoleClient, ServiceExt};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::path::Path;
 use tokio::process::Command;
 
+use super::{config_schema, parse_settings, InitializationTimeout, McpTransportFactory};
 use crate::config::StdioConfig;
 
+/// The `stdio` transport, running the server as a child process
+pub struct StdioTransport;
+
+#[async_trait]
+impl McpTransportFactory for StdioTransport {
+    fn name(&self) -> &str {
+        "stdio"
+    }
+
+    fn config_schema(&self) -> Value {
+        config_schema::<StdioConfig>()
+    }
+
+    async fn connect(&self, settings: &Value) -> Result<RunningService<RoleClient, ()>> {
+        create_stdio_client(&parse_settings(settings)?).await
+    }
+}
+
 /// Create an MCP client using stdio transport
-pub async fn create_stdio_client(config: &StdioConfig) -> Result<RunningService<RoleClient, ()>> {
-    // Extract fields from the config
-    // Build command
-    let mut cmd = Command::new(&config.command);
-    cmd.args(&config.args);
+async fn create_stdio_client(config: &StdioConfig) -> Result<RunningService<RoleClient, ()>> {
+    // Build command, resolving the command and arguments read from environment variables
+    let (command, args) = config.resolve_command()?;
+    let mut cmd = Command::new(command);
+    cmd.args(args);
 
-    // Add environment variables
+    // Add environment variables, skipping optional ones that are not set
     for (key, value) in &config.env {
-        cmd.env(key, value.resolve()?);
+        if let Some(value) = value.resolve()? {
+            cmd.env(key, value);
+        }
     }
 
     // Load environment variables from file if specified
@@ -30,7 +53,7 @@ pub async fn create_stdio_client(config: &StdioConfig) -> Result<RunningService<
     // Create and initialize the client with timeout
     let service = tokio::time::timeout(std::time::Duration::from_secs(10), ().serve(child_process))
         .await
-        .map_err(|_| anyhow!("Timeout during MCP service initialization"))?
+        .map_err(|_| InitializationTimeout)?
         .map_err(|e| anyhow!("Failed to initialize MCP service: {}", e))?;
 
     Ok(service)
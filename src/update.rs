@@ -0,0 +1,205 @@
+//! The `update` command introspects every configured MCP server and stores the resources and
+//! tools it finds in the configuration, so the schema can be served offline.
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::config::{ConnectorConfig, McpServerName, ServerIntrospection, CONFIG_FILE_NAME};
+use crate::connector::{initialize_offline_state, introspect_server};
+use crate::schema::generate_schema_from_state;
+use crate::schema_hash::schema_hash;
+use crate::transport::create_mcp_client;
+
+/// Arguments for the `update` command
+#[derive(Debug, Parser)]
+#[command(name = "update")]
+pub struct UpdateArgs {
+    /// Directory containing the connector configuration
+    #[arg(
+        long,
+        env = "HASURA_CONFIGURATION_DIRECTORY",
+        default_value = "/etc/connector"
+    )]
+    configuration: PathBuf,
+
+    /// Introspect every server again, ignoring the checkpoint of an interrupted run
+    #[arg(long)]
+    fresh: bool,
+}
+
+/// Name of the file in the configuration directory where `update` checkpoints the servers
+/// introspected so far
+const CHECKPOINT_FILE_NAME: &str = ".update-checkpoint.json";
+
+/// Servers introspected by an `update` run that didn't complete
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UpdateCheckpoint {
+    /// Hash of the server configurations, so a checkpoint isn't resumed after they changed
+    servers_hash: String,
+    introspection: HashMap<McpServerName, ServerIntrospection>,
+}
+
+impl UpdateCheckpoint {
+    /// Load the checkpoint, if there is one for the current server configurations
+    fn load(path: &Path, servers_hash: &str) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let checkpoint = serde_json::from_str::<Self>(&content)
+            .inspect_err(|err| eprintln!("warning: ignoring invalid checkpoint: {err}"))
+            .ok()?;
+        if checkpoint.servers_hash != servers_hash {
+            println!("Ignoring the checkpoint of a run with different servers");
+            return None;
+        }
+        Some(checkpoint)
+    }
+
+    /// Write the checkpoint, replacing the previous one at once so an interruption can't
+    /// leave it half written
+    fn save(&self, path: &Path) -> Result<()> {
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string(self)?)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Hash the server configurations. Secrets in them are hashed, never written to the checkpoint.
+fn servers_hash(configuration: &ConnectorConfig) -> String {
+    let json = serde_json::to_value(&configuration.servers)
+        .and_then(|value| serde_json::to_vec(&value))
+        .unwrap_or_default();
+    format!("{:x}", Sha256::digest(&json))
+}
+
+/// Run the `update` command with the given command line arguments
+pub async fn run(args: impl IntoIterator<Item = String>) -> ExitCode {
+    let args = UpdateArgs::parse_from(args);
+    match update_configuration(&args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Introspect every server and write the results back to the configuration file
+async fn update_configuration(args: &UpdateArgs) -> Result<()> {
+    let config_path = args.configuration.join(CONFIG_FILE_NAME);
+    let mut configuration = ConnectorConfig::from_file(&config_path)?;
+
+    // Hash of the schema from the previous introspection, if it is complete
+    let previous_hash = initialize_offline_state(&configuration)
+        .ok()
+        .map(|state| schema_hash(&generate_schema_from_state(&state, &configuration)));
+
+    // Resume from the servers introspected by an interrupted run
+    let checkpoint_path = args.configuration.join(CHECKPOINT_FILE_NAME);
+    let servers_hash = servers_hash(&configuration);
+    let mut checkpoint = if args.fresh {
+        None
+    } else {
+        UpdateCheckpoint::load(&checkpoint_path, &servers_hash)
+    }
+    .unwrap_or_else(|| UpdateCheckpoint {
+        servers_hash,
+        introspection: HashMap::new(),
+    });
+
+    for (server_name, server_config) in &configuration.servers {
+        if checkpoint.introspection.contains_key(server_name) {
+            println!("{}: resumed from checkpoint", server_name.0);
+            continue;
+        }
+        let service = create_mcp_client(&server_config.for_introspection()).await?;
+        let server_introspection = introspect_server(server_name, &service)
+            .await
+            .map_err(|e| anyhow!("Failed to introspect server {}: {:?}", server_name.0, e))?;
+        println!(
+            "{}: {} resources, {} resource templates, {} tools, {} prompts",
+            server_name.0,
+            server_introspection.resources.len(),
+            server_introspection.resource_templates.len(),
+            server_introspection.tools.len(),
+            server_introspection.prompts.len()
+        );
+        checkpoint
+            .introspection
+            .insert(server_name.clone(), server_introspection);
+        checkpoint.save(&checkpoint_path)?;
+        service.cancel().await?;
+    }
+
+    configuration.introspection = checkpoint.introspection;
+
+    // Report resources, tools and prompts that were renamed because their names collide
+    let state = initialize_offline_state(&configuration)
+        .map_err(|e| anyhow!("Failed to build the schema: {:?}", e))?;
+    for collision in &state.name_collisions {
+        println!(
+            "warning: {:?} {} on server {} is named {} because {} is already taken",
+            collision.kind, collision.item, collision.server, collision.renamed_to, collision.name
+        );
+    }
+
+    // Report whether the schema changed, so metadata only needs rebuilding when it did
+    let hash = schema_hash(&generate_schema_from_state(&state, &configuration));
+    let status = match previous_hash {
+        Some(previous_hash) if previous_hash == hash => "unchanged",
+        Some(_) => "changed",
+        None => "new",
+    };
+    println!("schema hash: {} ({})", hash, status);
+
+    configuration.to_file(&config_path)?;
+    // The run is complete, the next one starts over
+    if let Err(err) = fs::remove_file(&checkpoint_path) {
+        eprintln!("warning: failed to remove the checkpoint: {err}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_update_checkpoint() {
+        let configuration: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {"files": {"type": "stdio", "command": "mcp-files"}}
+        }))
+        .unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "ndc-mcp-update-checkpoint-{}.json",
+            std::process::id()
+        ));
+        let checkpoint = UpdateCheckpoint {
+            servers_hash: servers_hash(&configuration),
+            introspection: HashMap::from([(
+                McpServerName("files".to_string()),
+                ServerIntrospection::default(),
+            )]),
+        };
+        checkpoint.save(&path).unwrap();
+
+        let resumed = UpdateCheckpoint::load(&path, &servers_hash(&configuration)).unwrap();
+        assert!(resumed
+            .introspection
+            .contains_key(&McpServerName("files".to_string())));
+
+        // A checkpoint of other servers is not resumed
+        let other: ConnectorConfig = serde_json::from_value(json!({
+            "servers": {"files": {"type": "stdio", "command": "mcp-files", "args": ["/data"]}}
+        }))
+        .unwrap();
+        assert!(UpdateCheckpoint::load(&path, &servers_hash(&other)).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,222 @@
+//! Expansion of the URI templates of MCP resource templates (RFC 6570, level 3). Value
+//! modifiers such as `{var:3}` and `{var*}` are accepted but ignored.
+
+use std::collections::HashMap;
+
+/// A variable of a URI template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateVariable {
+    pub name: String,
+    /// Variables in query-style expressions (`{?var}`, `{&var}`, `{;var}`) may be omitted
+    pub optional: bool,
+}
+
+/// How the variables of an expression are expanded, per RFC 6570 section 3.2
+struct Operator {
+    first: &'static str,
+    separator: &'static str,
+    named: bool,
+    if_empty: &'static str,
+    allow_reserved: bool,
+}
+
+fn operator(expression: &str) -> (Operator, &str) {
+    let (first, separator, named, if_empty, allow_reserved) = match expression.chars().next() {
+        Some('+') => ("", ",", false, "", true),
+        Some('#') => ("#", ",", false, "", true),
+        Some('.') => (".", ".", false, "", false),
+        Some('/') => ("/", "/", false, "", false),
+        Some(';') => (";", ";", true, "", false),
+        Some('?') => ("?", "&", true, "=", false),
+        Some('&') => ("&", "&", true, "=", false),
+        _ => {
+            return (
+                Operator {
+                    first: "",
+                    separator: ",",
+                    named: false,
+                    if_empty: "",
+                    allow_reserved: false,
+                },
+                expression,
+            )
+        }
+    };
+    (
+        Operator {
+            first,
+            separator,
+            named,
+            if_empty,
+            allow_reserved,
+        },
+        &expression[1..],
+    )
+}
+
+/// Get the name of a variable specification, without modifiers
+fn variable_name(spec: &str) -> &str {
+    spec.trim_end_matches('*')
+        .split(':')
+        .next()
+        .unwrap_or_default()
+        .trim()
+}
+
+/// Iterate over the expressions of a template, as the literal text before each expression
+/// and the expression without braces
+fn expressions(template: &str) -> impl Iterator<Item = (&str, Option<&str>)> {
+    let mut rest = template;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        match rest.find('{') {
+            Some(start) => match rest[start..].find('}') {
+                Some(end) => {
+                    let literal = &rest[..start];
+                    let expression = &rest[start + 1..start + end];
+                    rest = &rest[start + end + 1..];
+                    Some((literal, Some(expression)))
+                }
+                None => {
+                    let literal = rest;
+                    rest = "";
+                    Some((literal, None))
+                }
+            },
+            None => {
+                let literal = rest;
+                rest = "";
+                Some((literal, None))
+            }
+        }
+    })
+}
+
+/// Get the variables of a URI template, in order of first appearance
+pub fn template_variables(template: &str) -> Vec<TemplateVariable> {
+    let mut variables: Vec<TemplateVariable> = Vec::new();
+    for (_, expression) in expressions(template) {
+        let Some(expression) = expression else {
+            continue;
+        };
+        let (operator, specs) = operator(expression);
+        for spec in specs.split(',') {
+            let name = variable_name(spec);
+            if name.is_empty() || variables.iter().any(|variable| variable.name == name) {
+                continue;
+            }
+            variables.push(TemplateVariable {
+                name: name.to_string(),
+                optional: operator.named,
+            });
+        }
+    }
+    variables
+}
+
+/// Percent-encode a value, keeping unreserved characters and, if allowed, reserved ones
+fn encode(value: &str, allow_reserved: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        let c = byte as char;
+        let keep = c.is_ascii_alphanumeric()
+            || matches!(c, '-' | '.' | '_' | '~')
+            || (allow_reserved && ":/?#[]@!$&'()*+,;=".contains(c));
+        if keep {
+            encoded.push(c);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Expand a URI template. Variables without a value are omitted.
+pub fn expand_uri_template(template: &str, values: &HashMap<String, String>) -> String {
+    let mut uri = String::with_capacity(template.len());
+    for (literal, expression) in expressions(template) {
+        uri.push_str(literal);
+        let Some(expression) = expression else {
+            continue;
+        };
+        let (operator, specs) = operator(expression);
+        let mut expanded = Vec::new();
+        for spec in specs.split(',') {
+            let name = variable_name(spec);
+            let Some(value) = values.get(name) else {
+                continue;
+            };
+            let value = encode(value, operator.allow_reserved);
+            if !operator.named {
+                expanded.push(value);
+            } else if value.is_empty() {
+                expanded.push(format!("{}{}", name, operator.if_empty));
+            } else {
+                expanded.push(format!("{}={}", name, value));
+            }
+        }
+        if !expanded.is_empty() {
+            uri.push_str(operator.first);
+            uri.push_str(&expanded.join(operator.separator));
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_template_variables() {
+        assert_eq!(
+            template_variables("repo://{owner}/{repo}/issues{?state,labels*}"),
+            vec![
+                TemplateVariable {
+                    name: "owner".to_string(),
+                    optional: false
+                },
+                TemplateVariable {
+                    name: "repo".to_string(),
+                    optional: false
+                },
+                TemplateVariable {
+                    name: "state".to_string(),
+                    optional: true
+                },
+                TemplateVariable {
+                    name: "labels".to_string(),
+                    optional: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_uri_template() {
+        assert_eq!(
+            expand_uri_template("file:///{+path}", &values(&[("path", "tmp/a b.txt")])),
+            "file:///tmp/a%20b.txt"
+        );
+        assert_eq!(
+            expand_uri_template("users://{id}", &values(&[("id", "a/b")])),
+            "users://a%2Fb"
+        );
+        assert_eq!(
+            expand_uri_template(
+                "repo://{owner}/issues{?state,page}",
+                &values(&[("owner", "hasura"), ("page", "2")])
+            ),
+            "repo://hasura/issues?page=2"
+        );
+    }
+}